@@ -1,5 +1,171 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/**
+ * A flag that stops every search watching it, such as all the searches in
+ * flight for a query that has been replaced.
+ *
+ * Pass it to `SearcherBuilder.cancellationToken`. A token cannot be reset,
+ * so create a new one for the next set of searches.
+ */
+export declare class CancellationToken {
+  constructor()
+  /**
+   * Cancel the searches watching this token, including those running on
+   * other threads.
+   */
+  cancel(): void
+  /** Whether `cancel` has been called. */
+  get isCancelled(): boolean
+}
+
+/**
+ * The files with matches below a directory, as an async iterator.
+ *
+ * Use with `for await`. Leaving the loop early stops the search.
+ */
+export declare class DirSearch {
+  /** Wait for the next file with matches. */
+  next(): Promise<IteratorResult<DirSearchEntry, undefined>>
+  /** Stop the search. */
+  return(): IteratorResult<DirSearchEntry, undefined>
+  /**
+   * Stop the search, releasing its input right away rather than when the
+   * search next produces a value. Also available as `[Symbol.dispose]` and
+   * `[Symbol.asyncDispose]` where the runtime defines them.
+   */
+  close(): void
+}
+
+/**
+ * Named in-memory documents that can be searched repeatedly.
+ *
+ * Documents are copied in once and searched with any matcher, for
+ * in-process search over notes, CMS entries and similar datasets.
+ */
+export declare class DocumentSet {
+  /** Create an empty document set. */
+  constructor(options?: DocumentSetOptions | undefined | null)
+  /** Add a document, replacing any document with the same name. */
+  add(name: string, content: string | Buffer): void
+  /** Remove a document. Returns whether it was in the set. */
+  remove(name: string): boolean
+  /** The number of documents in the set. */
+  get size(): number
+  /**
+   * Search every document, in the order they were added, with `searcher`
+   * or a default searcher.
+   *
+   * Only documents with at least one match are included in the result.
+   */
+  search(matcher: RegexMatcher, searcher?: Searcher | undefined | null): Array<DocumentMatch>
+}
+
+/**
+ * The results of `Searcher.searchFilesIter`, as an async iterator.
+ *
+ * Use with `for await`. Leaving the loop early stops the search.
+ */
+export declare class FileSearch {
+  /** Wait for the result of the next file. */
+  next(): Promise<IteratorResult<FileSearchResult, undefined>>
+  /** Stop the search. */
+  return(): IteratorResult<FileSearchResult, undefined>
+  /**
+   * Stop the search, releasing its input right away rather than when the
+   * search next produces a value. Also available as `[Symbol.dispose]` and
+   * `[Symbol.asyncDispose]` where the runtime defines them.
+   */
+  close(): void
+}
+
+/**
+ * Gitignore rules, evaluated against paths without walking a directory.
+ *
+ * Rules are interpreted exactly as ripgrep's directory walker interprets
+ * `.gitignore` files, including `!` rules, directory-only rules and
+ * anchoring to the root.
+ */
+export declare class Gitignore {
+  /** Build from the content of an ignore file, one rule per line. */
+  static fromContent(content: string, options?: GitignoreOptions | undefined | null): Gitignore
+  /** The number of ignore rules. */
+  get ignoreCount(): number
+  /** The number of `!` rules. */
+  get whitelistCount(): number
+  /**
+   * Match a path against the rules, without considering its parent
+   * directories.
+   */
+  matched(path: string, isDir: boolean): IgnoreMatch
+  /**
+   * Whether a path is ignored, either by a rule matching it or by a rule
+   * matching one of its parent directories below the root.
+   */
+  isIgnored(path: string, isDir: boolean): boolean
+}
+
+/**
+ * Scans text for a large list of keywords at once.
+ *
+ * Keywords are matched literally with an Aho-Corasick automaton, which
+ * scales to word lists far larger than a regex alternation comfortably
+ * handles. Every occurrence of every keyword is reported, including
+ * keywords that overlap.
+ */
+export declare class KeywordScanner {
+  /**
+   * Create a scanner for the given keywords. Empty keywords are ignored,
+   * but still count towards the `index` of the keywords after them.
+   */
+  static fromKeywords(keywords: Array<string>, options?: KeywordScannerOptions | undefined | null): KeywordScanner
+  /**
+   * Create a scanner from a word list file with one keyword per line.
+   *
+   * Blank lines are skipped, and so are `#` comments with `skipComments`.
+   */
+  static fromFile(path: string, options?: KeywordScannerOptions | undefined | null): KeywordScanner
+  /**
+   * Load a scanner saved by `save`, reading its file rather than building
+   * it again.
+   *
+   * A loaded scanner is ready at once but scans more slowly than a built
+   * one, about one and a half times as long.
+   */
+  static load(path: string): KeywordScanner
+  /**
+   * Save the scanner to a file, to be loaded again with `load`.
+   *
+   * The file holds the keywords, the options and the compiled automaton,
+   * which `load` searches straight from the file. The file is replaced
+   * only once it is written in full.
+   */
+  save(path: string): void
+  /** The number of keywords in the scanner. */
+  get keywordCount(): number
+  /** Find all keywords in the given text, ordered by position. */
+  scan(text: string | Buffer): Array<KeywordMatch>
+  /**
+   * Scan files for keywords. Directories are searched recursively.
+   *
+   * Only files with at least one match are included in the result.
+   */
+  scanPaths(paths: Array<string>): Array<KeywordFileMatches>
+}
+
+/** The records of a search, produced by `Searcher.searchRecords`. */
+export declare class RecordSearch {
+  /** Wait for the next record. */
+  next(): Promise<IteratorResult<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish), undefined>>
+  /** Stop the search. */
+  return(): IteratorResult<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish), undefined>
+  /**
+   * Stop the search, releasing its input right away rather than when the
+   * search next produces a value. Also available as `[Symbol.dispose]` and
+   * `[Symbol.asyncDispose]` where the runtime defines them.
+   */
+  close(): void
+}
+
 /**
  * A compiled regex matcher.
  *
@@ -8,6 +174,29 @@
 export declare class RegexMatcher {
   /** Create a new matcher from a pattern with default options. */
   static fromPattern(pattern: string): RegexMatcher
+  /**
+   * Create a matcher sharing this one's compiled pattern, without
+   * compiling it again.
+   */
+  clone(): RegexMatcher
+  /**
+   * Share the compiled pattern with other worker threads.
+   *
+   * Returns a handle that can be posted to a worker and passed to
+   * `RegexMatcher.fromShared` there. The pattern stays in memory until
+   * the handle is passed to `RegexMatcher.releaseShared`.
+   */
+  share(): number
+  /**
+   * Create a matcher from a handle returned by `share`, in any thread of
+   * the process.
+   */
+  static fromShared(handle: number): RegexMatcher
+  /**
+   * Release a handle returned by `share`. Matchers already created from it
+   * keep working. Returns whether the handle was still shared.
+   */
+  static releaseShared(handle: number): boolean
   /** Check if the given text matches the pattern. */
   isMatch(text: string | Buffer): boolean
   /**
@@ -16,8 +205,70 @@ export declare class RegexMatcher {
    * Returns the start and end byte offsets, or null if no match.
    */
   find(text: string | Buffer): MatchRange | null
-  /** Find all matches in the given text. */
-  findAll(text: string | Buffer): Array<MatchRange>
+  /**
+   * Find all matches in the given text.
+   *
+   * With `overlapping` enabled, every occurrence is reported even when it
+   * overlaps a previous one (e.g. `aa` in `aaaa` at 0, 1 and 2).
+   */
+  findAll(text: string | Buffer, options?: FindOptions | undefined | null): Array<MatchRange>
+  /**
+   * Scan raw bytes for matches without any notion of lines.
+   *
+   * Reports absolute offsets of every match, optionally with a window of
+   * surrounding bytes. No line splitting or UTF-8 conversion is performed,
+   * which makes this suitable for firmware images and other blobs.
+   */
+  scanBytes(data: Buffer, options?: BinaryScanOptions | undefined | null): Array<BinaryMatch>
+  /**
+   * Scan a file's raw bytes for matches without any notion of lines.
+   *
+   * See `scanBytes`.
+   */
+  scanFile(path: string, options?: BinaryScanOptions | undefined | null): Array<BinaryMatch>
+  /**
+   * Replace the first match in the given text.
+   *
+   * See `replaceAll` for the replacement template syntax.
+   */
+  replace(text: string | Buffer, replacement: string, options?: ReplaceOptions | undefined | null): string | Buffer
+  /**
+   * Replace every match in the given text.
+   *
+   * The replacement may reference capture groups with `$1`, `$name` or
+   * `${name}`, and supply a fallback with `${name:-default}` that is used
+   * when the group did not participate in the match or matched nothing.
+   * Use `$$` for a literal `$`.
+   *
+   * Returns a string for string input and a Buffer for Buffer input.
+   */
+  replaceAll(text: string | Buffer, replacement: string, options?: ReplaceOptions | undefined | null): string | Buffer
+  /**
+   * The capture groups of the pattern, in index order, without group 0
+   * (the whole match).
+   *
+   * Each group has the index and name replacement templates refer to it
+   * by, and the group it is nested in, as parsed with the matcher's own
+   * syntax options. Useful to offer capture pickers for replacements.
+   */
+  captureGroups(): Array<CaptureGroup>
+  /**
+   * The literals every match contains, for narrowing down candidate
+   * documents in an external index before searching them, or null when
+   * matches require no literal.
+   *
+   * Texts that contain all literals of none of the alternatives cannot
+   * match. Texts that do may still not match.
+   */
+  prefilterLiterals(): RequiredLiterals | null
+  /**
+   * Search several independent inputs in one call.
+   *
+   * Each input is searched on its own, as by `searcher.searchSlice`, with
+   * `searcher` or a default searcher. Results are in the order of the
+   * inputs.
+   */
+  searchAll(haystacks: Array<string | Buffer>, options?: SearchAllOptions | undefined | null, searcher?: Searcher | undefined | null): Array<SearchResult>
 }
 
 /**
@@ -32,10 +283,82 @@ export declare class RegexMatcherBuilder {
   constructor()
   /** Build a new matcher for the provided pattern. */
   build(pattern: string): RegexMatcher
-  /** Build a new matcher from multiple patterns (joined as alternation). */
-  buildMany(patterns: Array<string>): RegexMatcher
+  /**
+   * Build a new matcher for the provided pattern on the libuv thread
+   * pool, without blocking the event loop.
+   *
+   * Resolves with the same matcher as `build`.
+   */
+  buildAsync(pattern: string): Promise<RegexMatcher>
+  /**
+   * Build a new matcher from multiple patterns (joined as alternation).
+   *
+   * Entries may be plain patterns or `PatternEntry` objects carrying their
+   * own flags, so a heterogeneous rule set compiles into a single matcher.
+   */
+  buildMany(patterns: Array<string | PatternEntry>): RegexMatcher
+  /**
+   * Build a new matcher from multiple patterns on the libuv thread pool,
+   * without blocking the event loop. Compiling alternations of many
+   * thousands of patterns, such as a word list, can take long enough to be
+   * noticed.
+   *
+   * Resolves with the same matcher as `buildMany`.
+   */
+  buildManyAsync(patterns: Array<string | PatternEntry>): Promise<RegexMatcher>
+  /**
+   * Build a new matcher from a file containing one pattern per line, like
+   * `rg -f`.
+   *
+   * Both `
+  ` and `\r
+  ` line endings are accepted. Every line is a
+   * pattern unless skipped by `options`.
+   */
+  buildFromPatternFile(path: string, options?: PatternFileOptions | undefined | null): RegexMatcher
+  /**
+   * Build a new matcher from a newline-separated list of patterns, such as
+   * the output of another process.
+   *
+   * Lines are handled exactly as in `buildFromPatternFile`.
+   */
+  buildFromPatternList(patterns: string | Buffer, options?: PatternFileOptions | undefined | null): RegexMatcher
   /** Build a new matcher from literal strings (optimized alternation). */
   buildLiterals(literals: Array<string>): RegexMatcher
+  /**
+   * Build a matcher for a literal query in which every run of whitespace
+   * matches any run of whitespace.
+   *
+   * Useful for finding a sentence or code snippet regardless of how it was
+   * wrapped or indented. Leading and trailing whitespace is ignored. Runs
+   * match line terminators too, so a query can span lines when the
+   * searcher is in multi-line mode. A query of only whitespace, which
+   * would match every line, is an error.
+   */
+  buildWhitespaceFlexible(literal: string): RegexMatcher
+  /**
+   * Build a matcher for an exact sequence of bytes.
+   *
+   * The bytes are matched verbatim regardless of whether they are valid
+   * UTF-8. Options like `caseInsensitive` and `word` still apply.
+   */
+  buildBytes(literal: Buffer): RegexMatcher
+  /**
+   * Build a matcher from a hex byte signature such as `"7F 45 4C 46 ?? 01"`.
+   *
+   * The signature is a sequence of two-digit hex bytes, optionally
+   * separated by whitespace, where `??` matches any single byte.
+   */
+  buildHex(signature: string): RegexMatcher
+  /**
+   * Treat patterns as matching raw bytes rather than Unicode text.
+   *
+   * This is the same as `unicode(false)`: `\xNN` escapes match the byte
+   * `NN` and `.` matches any byte, so patterns can match data that is not
+   * valid UTF-8. Unicode-aware parts of a pattern can still be written
+   * inside a `(?u:...)` group.
+   */
+  bytes(yes: boolean): this
   /**
    * Set the value for the case insensitive (`i`) flag.
    *
@@ -43,6 +366,26 @@ export declare class RegexMatcherBuilder {
    * lower case variants.
    */
   caseInsensitive(yes: boolean): this
+  /**
+   * Match letters case-insensitively by ASCII case folding alone, for data
+   * known to be ASCII.
+   *
+   * Matchers are built as with `caseInsensitive(true)` and
+   * `unicode(false)`: the pattern is compiled without the Unicode case
+   * tables and classes, which makes case-insensitive scans of large logs
+   * cheaper, particularly for patterns with classes like `\w` or `\s`.
+   * Only `A`-`Z` and `a`-`z` fold, so `k` no longer matches the Kelvin
+   * sign, and non-ASCII letters must match exactly. Classes match ASCII
+   * only and `.` matches any byte, as with `bytes(true)`. Turning it off
+   * again leaves `caseInsensitive` and `unicode` as they were set.
+   */
+  asciiCaseInsensitive(yes: boolean): this
+  /**
+   * Apply the matcher defaults of `preset`: smart case for `Ripgrep`,
+   * case-sensitive matching otherwise. Options set afterwards override
+   * them.
+   */
+  preset(preset: Preset): this
   /**
    * Whether to enable "smart case" or not.
    *
@@ -111,9 +454,16 @@ export declare class RegexMatcherBuilder {
   /**
    * Set CRLF mode for line terminators.
    *
-   * When enabled, `$` will match both `\r
+   * When enabled, `^` and `$` treat both `\r
   ` and `
-  `.
+  ` as line endings
+   * and never match between a `\r` and a `
+  `, and reported matches are
+   * widened so they never split a `\r
+  ` pair. Unlike grep's CRLF mode
+   * this does not set a line terminator, so the matcher works with any
+   * searcher and may match across lines in multi-line searches. Use
+   * `lineTerminator` to keep matches within lines.
    */
   crlf(yes: boolean): this
   /** Require that all matches occur on word boundaries. */
@@ -124,6 +474,51 @@ export declare class RegexMatcherBuilder {
    * When enabled, all regex meta characters are matched literally.
    */
   fixedStrings(yes: boolean): this
+  /**
+   * Whether to report the longest match at each position (POSIX
+   * leftmost-longest) instead of the first alternative that matches.
+   *
+   * For example, `a|ab` matches `ab` in `abc` instead of `a`. Which lines
+   * match is unaffected; only reported match ranges change. Capture groups
+   * used by `replace` keep leftmost-first semantics.
+   */
+  leftmostLongest(yes: boolean): this
+  /**
+   * Require matches to start at the beginning of the haystack.
+   *
+   * Unlike `^`, this is unaffected by `multiLine`. Intended for validating
+   * whole inputs with `isMatch`/`find`; in line-oriented searches use
+   * `wholeLine` instead.
+   */
+  anchoredStart(yes: boolean): this
+  /**
+   * Require matches to span the entire haystack.
+   *
+   * Equivalent to wrapping each pattern in `\A(?:...)\z`, so `isMatch`
+   * answers "does this whole buffer match?" regardless of `multiLine`.
+   * In line-oriented searches use `wholeLine` instead.
+   */
+  fullMatch(yes: boolean): this
+  /**
+   * Normalize text to the given Unicode form before matching.
+   *
+   * Patterns are normalized to the same form when the matcher is built.
+   * Reported offsets and lines refer to the original input; a match that
+   * covers part of a rewritten character sequence widens to the whole
+   * sequence. `scanBytes` and `scanFile` always match raw bytes. Pass
+   * `null` to disable (the default).
+   */
+  normalization(form?: NormalizationForm | undefined | null): this
+  /**
+   * Match letters regardless of diacritics, so `e` matches `é`, `ê` and
+   * `ë`.
+   *
+   * Combining marks are removed from both patterns and text, and a few
+   * letters without a decomposition are transliterated (`ø` to `o`, `ł` to
+   * `l`, `ß` to `ss`, ...). As with `normalization`, offsets refer to the
+   * original input.
+   */
+  diacriticInsensitive(yes: boolean): this
   /**
    * Whether each pattern should match the entire line.
    *
@@ -132,6 +527,57 @@ export declare class RegexMatcherBuilder {
   wholeLine(yes: boolean): this
 }
 
+/**
+ * Reads lines of a searched file on demand, such as to show more lines
+ * around a result in a UI without searching the file again.
+ *
+ * Created by `Searcher.resultCursor`. Reading starts at the line of the
+ * result nearest to the first line asked for, going back from a later line
+ * when that is nearer, so lines near a result are read without reading the
+ * file from the start.
+ */
+export declare class ResultCursor {
+  /**
+   * Read lines `first` to `last` (1-based, inclusive) of the file, as
+   * context lines of kind `Other`. Lines past the end of the file are left
+   * out.
+   */
+  readLines(first: number, last: number): Array<SearchContext>
+}
+
+/**
+ * A set of named rules run together over a directory, the core of secret
+ * scanners and linters.
+ *
+ * Each file is read once and searched with every rule whose globs select
+ * it, and the findings are grouped by rule.
+ */
+export declare class RulePack {
+  /** Compile `rules`, such as the parsed content of a JSON rule file. */
+  constructor(rules: Array<Rule>)
+  /** The names of the rules, in order. */
+  get names(): Array<string>
+  /**
+   * Run every rule over the files below a directory, or over a single
+   * file, with `searcher` or a default one.
+   *
+   * Files that no rule applies to are not read. Files that cannot be read
+   * fail the call or are listed in `errors`, according to the read error
+   * policy.
+   */
+  run(dir: string, searcher?: Searcher | undefined | null): RuleReport
+  /**
+   * Run the rules like `run` and render the findings as a SARIF 2.1.0
+   * log, for CI systems and code-scanning services.
+   *
+   * Each match becomes a result with the rule name as its `ruleId` and a
+   * region giving its line, UTF-16 columns and byte range. Severities
+   * `"error"`, `"note"` and `"none"` are used as SARIF levels; any other
+   * severity becomes `"warning"`. Locations are relative to `dir`.
+   */
+  runSarif(dir: string, searcher?: Searcher | undefined | null): string
+}
+
 /**
  * A searcher executes searches over a haystack and collects results.
  *
@@ -140,12 +586,206 @@ export declare class RegexMatcherBuilder {
 export declare class Searcher {
   /** Create a new searcher with default configuration. */
   constructor()
-  /** Search a file for matches. */
-  searchPath(matcher: RegexMatcher, path: string): SearchResult
-  /** Search a byte slice for matches. */
-  searchSlice(matcher: RegexMatcher, slice: string | Buffer): SearchResult
+  /**
+   * Search a file for matches.
+   *
+   * `onTick` is called with the number of bytes read so far every
+   * `tickBytes` bytes, so a long search can report progress or decide to
+   * stop. Returning false stops the search as if it had been cancelled.
+   */
+  searchPath(matcher: RegexMatcher, path: string, options?: SearchOptions | undefined | null, onTick?: ((bytesRead: number) => boolean | void) | undefined | null): SearchResult
+  /**
+   * Search a byte slice for matches.
+   *
+   * `onTick` is called as by `searchPath`.
+   */
+  searchSlice(matcher: RegexMatcher, slice: string | Buffer, options?: SearchOptions | undefined | null, onTick?: ((bytesRead: number) => boolean | void) | undefined | null): SearchResult
+  /**
+   * Search a large byte slice on several threads, as many as `threads`.
+   *
+   * The slice is split at line boundaries, each part is searched on its
+   * own thread with the context lines around it, and the results are
+   * merged with offsets and line numbers into the whole slice, so the
+   * result is the same as that of `searchSlice`. Inputs under a few
+   * megabytes, and searches whose result depends on the input as a whole
+   * (multi-line matching, match limits, binary detection, BOM transcoding,
+   * byte context, line ending reports, content hashes and timeouts), are
+   * searched on the calling thread instead.
+   */
+  searchSliceParallel(matcher: RegexMatcher, slice: string | Buffer): SearchResult
   /** Search a reader for matches. */
-  searchReader(matcher: RegexMatcher, data: Buffer): SearchResult
+  searchReader(matcher: RegexMatcher, data: Buffer, options?: SearchOptions | undefined | null): SearchResult
+  /**
+   * Search a file on the libuv thread pool, without blocking the event
+   * loop.
+   *
+   * Resolves with the same result as `searchPath`. The promise has the
+   * `handle` of the search, which `cancelSearch` takes.
+   */
+  searchPathAsync(matcher: RegexMatcher, path: string, options?: SearchOptions | undefined | null): Promise<SearchResult> & { handle: number }
+  /**
+   * Search a byte slice on the libuv thread pool, without blocking the
+   * event loop.
+   *
+   * The search reads the Buffer in place, so it must not be modified until
+   * the promise settles. Resolves with the same result as `searchSlice`.
+   * The promise has the `handle` of the search, which `cancelSearch` takes.
+   */
+  searchSliceAsync(matcher: RegexMatcher, slice: string | Buffer, options?: SearchOptions | undefined | null): Promise<SearchResult> & { handle: number }
+  /**
+   * Search the files below a directory on a background thread.
+   *
+   * Files are searched in a stable order and only those with at least one
+   * match are produced. The read error policy decides whether a file or
+   * directory that cannot be read ends the iteration with an error or is
+   * skipped. At most `queueSize` results are buffered, so the search only
+   * runs ahead of the consumer by that much.
+   */
+  searchDirIter(matcher: RegexMatcher, dir: string, options?: DirSearchOptions | undefined | null): AsyncIterableIterator<DirSearchEntry>
+  /**
+   * Search the files below a directory, grouping the results by file.
+   *
+   * Files are searched in a stable order, filtered as `options` ask, and
+   * only those with at least one match are returned, in that order unless
+   * the searcher's `sortBy` orders them. A file that cannot be read fails
+   * the call, or is returned with its `error` and no matches, according to
+   * the read error policy.
+   */
+  searchDir(matcher: RegexMatcher, dir: string, options?: DirSearchOptions | undefined | null): Array<FileSearchResult>
+  /**
+   * Search the files below a directory on `threads` threads at once, each
+   * with its own copy of the searcher, passing each file with matches to
+   * `onFile` as soon as it has been searched.
+   *
+   * Files are filtered as `options` ask, but are walked and searched in no
+   * particular order. `onFinish` is called once the search ends, with the
+   * error that ended it or the counts of files searched. The read error
+   * policy decides whether a file that cannot be read ends the search or
+   * is skipped. The search waits while `queueSize` files are queued for
+   * `onFile`, so a slow consumer pauses it.
+   */
+  searchDirParallel(matcher: RegexMatcher, dir: string, onFile: (entry: DirSearchEntry) => void, onFinish?: ((error: Error | null, finish?: DirSearchFinish) => void) | null, options?: DirSearchOptions | null): void
+  /**
+   * Search many files concurrently, returning a result for every path.
+   *
+   * The files are shared out among `threads` threads as they finish, so
+   * one large file does not hold up the rest. Results are in the order of
+   * the paths unless `ordered` is false, in which case they are in the
+   * order the searches finished, or the searcher's `sortBy` orders them
+   * after the search. A file that cannot be read fails the call, or is
+   * returned with its `error` and no matches, according to the read error
+   * policy.
+   */
+  searchFiles(matcher: RegexMatcher, paths: Array<string>, options?: SearchFilesOptions | undefined | null): Array<FileSearchResult>
+  /**
+   * Search many inputs, each a path or the contents of an input, returning
+   * an entry for every input in order.
+   *
+   * A search that fails, such as that of a file that cannot be read, is
+   * reported in its entry's `error` without affecting the others, whatever
+   * the read error policy.
+   */
+  searchMany(matcher: RegexMatcher, inputs: Array<string | Buffer>): Array<SearchManyEntry>
+  /**
+   * Search many files concurrently on background threads, producing the
+   * result of each file as an async iterator.
+   *
+   * With `ordered: false`, each result is produced as soon as a thread
+   * finishes the file, for the lowest latency. Otherwise results are
+   * produced in the order of the paths. At most `queueSize` results are
+   * buffered ahead of the consumer, and leaving the loop stops the search.
+   */
+  searchFilesIter(matcher: RegexMatcher, paths: Array<string>, options?: SearchFilesOptions | undefined | null): AsyncIterableIterator<FileSearchResult>
+  /**
+   * Search a pipe, FIFO or other unbounded input on a background thread.
+   *
+   * Pass `-` to search stdin. Match and context lines are produced as soon
+   * as they are found, and the input is never held in memory beyond the
+   * searcher's buffer (see `heapLimit`) and `queueSize` lines. Results are
+   * not sorted, and matchers that normalize their input are not supported.
+   */
+  searchStream(matcher: RegexMatcher, path: string, options?: StreamSearchOptions | undefined | null): AsyncIterableIterator<SearchMatch | SearchContext>
+  /**
+   * Iterate over the matches of a file, or of a Buffer holding the input
+   * itself, as a background thread finds them.
+   *
+   * Pass `-` to search stdin. At most `queueSize` matches are buffered, so
+   * the search only runs ahead of the consumer by that much, and stopping
+   * the iteration stops the search. Context lines are left out, results are
+   * not sorted, and matchers that normalize their input are not supported.
+   */
+  searchIter(matcher: RegexMatcher, input: string | Buffer, options?: StreamSearchOptions | undefined | null): AsyncIterableIterator<SearchMatch>
+  /**
+   * Search a file, or stdin for `-`, on a background thread, producing
+   * every match and context line, binary data when it is first found, and
+   * then the summary as records tagged with their `type`.
+   *
+   * This is the source of `createSearchStream` (see the `stream` entry
+   * point). At most `queueSize` records are buffered. Results are not
+   * sorted, and matchers that normalize their input are not supported.
+   */
+  searchRecords(matcher: RegexMatcher, path: string, options?: StreamSearchOptions | undefined | null): AsyncIterableIterator<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish)>
+  /**
+   * Search a file, or stdin for `-`, on a background thread, passing each
+   * match to `onMatch` and each context line to `onContext` as soon as it is
+   * found.
+   *
+   * `onFinish` is called once the search ends, with the error that ended it
+   * or the summary. The search waits while `queueSize` lines (default 64)
+   * are queued for the callbacks, so a slow consumer pauses it, and stops
+   * if the environment shuts down. Results are not sorted, and matchers
+   * that normalize their input are not supported.
+   *
+   * The `onBinary` option is called when binary data is first found,
+   * after the lines before it, so the consumer knows when and why a search
+   * that quits at binary data stopped producing lines.
+   */
+  searchPathStreaming(matcher: RegexMatcher, path: string, onMatch: (match: SearchMatch) => void, onContext?: ((context: SearchContext) => void) | null, onFinish?: ((error: Error | null, finish?: SearchFinish) => void) | null, options?: StreamingSearchOptions | null): void
+  /**
+   * Count the lines of a file that the matcher does not match.
+   *
+   * Lines are only counted, so this is much cheaper than an inverted
+   * search. The count does not depend on `invertMatch`.
+   */
+  countNonMatchingLines(matcher: RegexMatcher, path: string): number
+  /**
+   * Read the lines around line `lineNumber` (1-based) of a file: up to
+   * `before` lines before it and `after` lines after it, without the line
+   * itself.
+   *
+   * This lets a search run without context and load it later, such as
+   * when a result is expanded in a UI. Lines are split and decoded as the
+   * searcher does, and the file is only read up to the last line needed.
+   */
+  getContext(path: string, lineNumber: number, before: number, after: number): Array<SearchContext>
+  /**
+   * Make a cursor over `path` that reads lines of the file on demand,
+   * starting from the nearest line of `result`, the result of searching
+   * it, rather than from the start of the file.
+   *
+   * `result` must come from a search of the file as it is now, with line
+   * numbers and without a transcoding encoding, as the cursor seeks to the
+   * byte offsets of its lines.
+   */
+  resultCursor(path: string, result: SearchResult): ResultCursor
+  /**
+   * List the files in which the matcher matches no line, like
+   * `rg --files-without-match`. Directories are searched recursively.
+   *
+   * Each file is searched only up to its first match. Files that cannot be
+   * read are left out or fail the call, according to the read error
+   * policy. The result does not depend on `invertMatch`.
+   */
+  filesWithoutMatch(matcher: RegexMatcher, paths: Array<string>): Array<string>
+  /**
+   * Count matches for every (matcher, file) pair.
+   *
+   * Each file is read once and then searched with every matcher, which
+   * makes this suitable for rule-coverage reports over many patterns.
+   * Every match counts, including several on one line; an inverted search
+   * counts lines.
+   */
+  searchMatrix(matchers: Array<RegexMatcher>, paths: Array<string>): MatchMatrix
 }
 
 /**
@@ -157,8 +797,26 @@ export declare class Searcher {
 export declare class SearcherBuilder {
   /** Create a new builder with default configuration. */
   constructor()
-  /** Build a searcher with the current configuration. */
+  /**
+   * Build a searcher with the current configuration.
+   *
+   * Throws if options conflict (see `validate`), with the first conflict
+   * as the error's `code`.
+   */
   build(): Searcher
+  /**
+   * The combinations of options set on this builder that cannot work
+   * together, which `build` rejects. Empty when the configuration is
+   * coherent.
+   */
+  validate(): Array<ConfigProblem>
+  /**
+   * Apply the searcher defaults of `preset`: line numbers for `Ripgrep`,
+   * binary detection that stops at the first NUL byte for `Ripgrep` and
+   * `Grep`, and BOM sniffing for `Ripgrep`. Options set afterwards
+   * override them.
+   */
+  preset(preset: Preset): this
   /**
    * Set the line terminator used by the searcher.
    *
@@ -187,8 +845,39 @@ export declare class SearcherBuilder {
   multiLine(yes: boolean): this
   /** Number of context lines to include after each match. */
   afterContext(lineCount: number): this
+  /**
+   * Include the lines after each match up to the next blank line, instead
+   * of a fixed number of lines. With a `delimiter`, the after context ends
+   * at the next line it matches instead.
+   *
+   * Suited to stack traces following an error line. The line ending the
+   * context is not included. `maxLines` caps the after context of each
+   * match, which is otherwise unlimited. `afterContext` switches back to a
+   * fixed count.
+   */
+  afterContextUntil(delimiter?: RegexMatcher | undefined | null, maxLines?: number | undefined | null): this
   /** Number of context lines to include before each match. */
   beforeContext(lineCount: number): this
+  /**
+   * Number of bytes to include before each match in its byte window.
+   *
+   * Unlike line context, byte windows ignore line boundaries, which is
+   * useful for data without meaningful lines. Windows are reported in
+   * `SearchMatch.windows` and are limited to the searcher's buffer.
+   */
+  beforeContextBytes(byteCount: number): this
+  /**
+   * Number of bytes to include after each match in its byte window.
+   *
+   * See `beforeContextBytes`.
+   */
+  afterContextBytes(byteCount: number): this
+  /**
+   * Whether to report overlapping match ranges within matching lines.
+   *
+   * See `RegexMatcher.findAll`.
+   */
+  overlapping(yes: boolean): this
   /**
    * Whether to enable passthru mode.
    *
@@ -201,6 +890,18 @@ export declare class SearcherBuilder {
    * Set to 0 to disable heap usage (requires memory maps for large files).
    */
   heapLimit(bytes?: number | undefined | null): this
+  /**
+   * Whether to search files through memory maps when possible.
+   *
+   * Memory maps let the searcher work on file contents directly instead of
+   * copying them through its internal line buffer, which avoids buffer
+   * growth for files with extremely long lines. Only applies to
+   * `searchPath`. Disabled by default.
+   *
+   * Warning: if a file is truncated while it is being searched through a
+   * memory map, the process may crash.
+   */
+  memoryMap(yes: boolean): this
   /**
    * Set binary detection mode.
    *
@@ -216,25 +917,392 @@ export declare class SearcherBuilder {
    */
   bomSniffing(yes: boolean): this
   /**
-   * Stop searching when a non-matching line is found after a matching line.
+   * Count the LF and CRLF line endings of every searched input in
+   * `finish.lineEndings`.
    *
-   * Useful for searching sorted files.
+   * They are counted in the bytes the search reads, so a search that
+   * stops early counts only part of its input. Disabled by default.
    */
-  stopOnNonmatch(yes: boolean): this
-  /** Set the maximum number of matches to return. */
-  maxMatches(limit?: number | undefined | null): this
-}
-
-/** Binary detection mode. */
-export declare const enum BinaryDetectionMode {
-  /** No binary detection. */
-  None = 'None',
-  /** Quit searching when binary data is detected. */
+  lineEndingReport(yes: boolean): this
+  /**
+   * Hash the content of every searched input with `algorithm` into
+   * `finish.contentHash`, or stop hashing with `null`.
+   *
+   * The hash is computed from the bytes the search reads, so a file is
+   * read only once. If the search stops early, the rest of the input is
+   * read for the hash. Disabled by default.
+   */
+  contentHash(algorithm?: HashAlgorithm | undefined | null): this
+  /**
+   * Set how a byte order mark shows up in the first line and in offsets.
+   *
+   * By default it is stripped, as grep does.
+   */
+  bomHandling(mode: BomHandling): this
+  /**
+   * Stop each search after `ms` milliseconds, or never with `null`.
+   *
+   * A search that runs out of time returns what it found so far, with
+   * `finish.timedOut` set and `finish.resumeOffset` at the start of the
+   * first line it did not search. The time is checked between lines and
+   * between the blocks read, so in-memory inputs are read in blocks while a
+   * timeout is set.
+   */
+  timeoutMs(ms?: number | undefined | null): this
+  /**
+   * Stop every search of the built searchers with a "search cancelled"
+   * error once `token` is cancelled, or stop watching a token with `null`.
+   *
+   * Searches check the token between the lines they collect and between
+   * the blocks they read, so in-memory inputs are read in blocks while a
+   * token is set.
+   */
+  cancellationToken(token?: CancellationToken | undefined | null): this
+  /**
+   * Set what happens to undecodable byte sequences when an input with a
+   * byte order mark is transcoded, and count them in
+   * `finish.encodingErrors`.
+   *
+   * Without a policy they are replaced with U+FFFD silently.
+   */
+  encodingErrorPolicy(action?: EncodingErrorAction | undefined | null): this
+  /**
+   * Stop searching when a non-matching line is found after a matching line.
+   *
+   * Useful for searching sorted files.
+   */
+  stopOnNonmatch(yes: boolean): this
+  /** Set the maximum number of matching lines to return. */
+  maxMatches(limit?: number | undefined | null): this
+  /**
+   * Set the maximum number of individual matches to return.
+   *
+   * Unlike `maxMatches`, every match counts, including several on one
+   * line. The search stops at the limit and the ranges of the last
+   * matching line are cut to fit, so exactly this many are reported when
+   * the input has at least as many. An inverted search counts lines.
+   */
+  maxMatchCount(limit?: number | undefined | null): this
+  /**
+   * Limit the number of threads that parallel searches with the built
+   * searchers use, such as `searchFiles` and `searchAll`, to leave CPUs for
+   * other work. Defaults to the number of CPUs.
+   */
+  threads(threads?: number | undefined | null): this
+  /**
+   * Limit the number of match ranges reported for each matching line.
+   *
+   * Lines with more matches are still reported, with the first `limit`
+   * ranges and `moreMatches` set. This keeps lines with thousands of hits,
+   * such as minified files, from dominating the result size.
+   */
+  maxMatchesPerLine(limit?: number | undefined | null): this
+  /**
+   * Whether to merge overlapping and adjacent match ranges on a line into
+   * maximal spans.
+   *
+   * This is what highlighters render, and it shrinks results for dense
+   * matches. Limits on the number of matches count the ranges before they
+   * are merged.
+   */
+  mergeRanges(yes: boolean): this
+  /**
+   * Set a budget in bytes for the results of a single search.
+   *
+   * Sizes are approximated as in `finish.resultBytes`. A match or context
+   * line that would take the results over the budget is not collected, and
+   * `action` decides whether the search then stops with what it has
+   * (the default) or fails.
+   */
+  maxResultBytes(limit?: number | undefined | null, action?: ResultLimitAction | undefined | null): this
+  /**
+   * Set the policy for files that fail to read during multi-file searches.
+   *
+   * By default the first read error aborts the search.
+   */
+  readErrorPolicy(policy: ReadErrorPolicy): this
+  /**
+   * Sort results before returning them.
+   *
+   * Matching lines are sorted by the given key, with ties kept in file
+   * order. `searchFiles` and `searchDir` also sort their files, by path,
+   * by total match count or by the offset of their first match. `searchMatrix` sorts its
+   * files by path or by total match count; `FirstMatchOffset` leaves them
+   * in input order. Pass `null` to return results in search order (the
+   * default).
+   */
+  sortBy(order?: SortBy | undefined | null): this
+  /**
+   * Whether to report phase timings in `SearchResult.profile`.
+   *
+   * Useful to tell whether a slow search is bound by IO, matching or the
+   * cost of handing results to JavaScript. While profiling, `searchPath`
+   * reads files through a timed reader instead of memory maps.
+   */
+  profile(yes: boolean): this
+  /**
+   * Whether to return results made only of plain data.
+   *
+   * Plain results leave out the `bytes` of matches, context lines and byte
+   * windows, and are ordinary objects that survive `structuredClone` and
+   * `postMessage` unchanged. Byte positions are still available through
+   * the offsets and lengths in the result.
+   */
+  plainResults(yes: boolean): this
+}
+
+/**
+ * A pool of search threads of its own, with a queue of search jobs.
+ *
+ * Jobs are queued with a priority and run as threads become free, so a
+ * server can bound how many searches run at once and let urgent ones jump
+ * the queue, while aging keeps the others from waiting forever. Each job's
+ * promise has the `handle` of the job, which `cancel` and `cancelSearch`
+ * take.
+ */
+export declare class SearchService {
+  /** Start the service's threads. */
+  constructor(options?: SearchServiceOptions | undefined | null)
+  /**
+   * Queue a search of a file with `searcher`.
+   *
+   * Resolves with the same result as `searcher.searchPath`.
+   */
+  searchPath(searcher: Searcher, matcher: RegexMatcher, path: string, options?: SearchOptions | undefined | null, job?: SearchJobOptions | undefined | null): Promise<SearchResult> & { handle: number }
+  /**
+   * Queue a search of a byte slice with `searcher`.
+   *
+   * The search reads the Buffer in place, so it must not be modified until
+   * the promise settles. Resolves with the same result as
+   * `searcher.searchSlice`.
+   */
+  searchSlice(searcher: Searcher, matcher: RegexMatcher, slice: string | Buffer, options?: SearchOptions | undefined | null, job?: SearchJobOptions | undefined | null): Promise<SearchResult> & { handle: number }
+  /**
+   * Cancel a job by the `handle` of its promise. A queued job is taken off
+   * the queue, and a running one stops as with `cancelSearch`; either way
+   * its promise rejects with a "search cancelled" error. Returns whether
+   * the job was still queued or running.
+   */
+  cancel(handle: number): boolean
+  /** The number of jobs waiting for a thread. */
+  get queued(): number
+  /** The number of jobs being searched. */
+  get running(): number
+  /**
+   * How long the jobs started so far waited for a thread, for each
+   * priority they were queued with, from highest to lowest. Jobs cancelled
+   * while queued are left out.
+   */
+  waitStats(): Array<QueueWaits>
+  /**
+   * Stop the service. Queued jobs are rejected as cancelled, running jobs
+   * finish, and queueing more jobs throws.
+   */
+  close(): void
+}
+
+/**
+ * Matches in a stream fed chunk by chunk, such as the chunks passing
+ * through a Node.js `Transform`.
+ *
+ * Matches are found without regard to lines, as by
+ * `RegexMatcher.scanBytes`, and may span chunks. A match is reported once
+ * enough input has been fed that no longer match could start where it
+ * does, so only the last `maxMatchLength` bytes are buffered.
+ */
+export declare class StreamMatcher {
+  /**
+   * Create a stream matcher. Matchers that normalize their input are not
+   * supported.
+   */
+  constructor(matcher: RegexMatcher, options?: StreamMatcherOptions | undefined | null)
+  /** The number of bytes fed so far. */
+  get position(): number
+  /**
+   * Feed the next chunk of the stream. Returns the matches that are
+   * complete, with absolute offsets.
+   */
+  push(chunk: string | Buffer): Array<BinaryMatch>
+  /** End the stream. Returns the remaining matches. */
+  end(): Array<BinaryMatch>
+}
+
+/**
+ * The match and context lines of a streaming search, as an async
+ * iterator.
+ *
+ * Use with `for await`. Leaving the loop early stops the search.
+ */
+export declare class StreamSearch {
+  /** Wait for the next match or context line. */
+  next(): Promise<IteratorResult<SearchMatch | SearchContext, undefined>>
+  /** Stop the search. */
+  return(): IteratorResult<SearchMatch | SearchContext, undefined>
+  /**
+   * Stop the search, releasing its input right away rather than when the
+   * search next produces a value. Also available as `[Symbol.dispose]` and
+   * `[Symbol.asyncDispose]` where the runtime defines them.
+   */
+  close(): void
+}
+
+/**
+ * Many small strings, copied in once and tested against matchers in one
+ * call each.
+ *
+ * Meant for running patterns over large numbers of short strings (names,
+ * log lines, table cells), where calling `isMatch` per string costs more
+ * than the match itself.
+ */
+export declare class StringPool {
+  /** Create a pool of `strings`, which keep their position as index. */
+  constructor(strings: Array<string | Buffer>)
+  /** The number of strings in the pool. */
+  get size(): number
+  /**
+   * Test every string, as by `matcher.isMatch`.
+   *
+   * Returns a bitmap with one bit per string: string `i` matches if bit
+   * `i % 8` (the least significant first) of byte `i / 8` is set.
+   */
+  test(matcher: RegexMatcher): Uint8Array
+  /** The number of strings that match. */
+  count(matcher: RegexMatcher): number
+  /**
+   * The number of non-overlapping matches in every string, as by
+   * `matcher.findAll`, in the order of the strings.
+   */
+  matchCounts(matcher: RegexMatcher): Uint32Array
+}
+
+/** Binary detection mode. */
+export declare const enum BinaryDetectionMode {
+  /** No binary detection. */
+  None = 'None',
+  /** Quit searching when binary data is detected. */
   Quit = 'Quit',
   /** Convert binary data to text (replace NUL bytes). */
   Convert = 'Convert'
 }
 
+/** Binary data found by a streaming search. */
+export interface BinaryEvent {
+  /** The absolute byte offset of the binary data. */
+  offset: number
+  /**
+   * What the searcher did: `Quit` ends the search here, and `Convert`
+   * goes on searching with NUL bytes replaced.
+   */
+  action: BinaryDetectionMode
+}
+
+/** A match found by a line-less binary scan. */
+export interface BinaryMatch {
+  /** The absolute byte offset of the start of the match. */
+  start: number
+  /** The absolute byte offset of the end of the match. */
+  end: number
+  /** Bytes around the match, if a window size was requested. */
+  window?: ByteWindow
+}
+
+/** Options for line-less binary scans. */
+export interface BinaryScanOptions {
+  /** Number of bytes to include before each match in its window. */
+  beforeBytes?: number
+  /** Number of bytes to include after each match in its window. */
+  afterBytes?: number
+  /** Stop after this many matches. */
+  maxMatches?: number
+}
+
+/** How a byte order mark is reported when BOM sniffing is enabled. */
+export declare const enum BomHandling {
+  /**
+   * Leave the BOM out of the first line, and count offsets from just
+   * after it.
+   */
+  Strip = 'Strip',
+  /**
+   * Keep the BOM at the start of the first line as U+FEFF, and count
+   * offsets from the start of the input including it. For UTF-8 inputs
+   * offsets are then byte offsets into the input.
+   */
+  Preserve = 'Preserve'
+}
+
+/** Bytes surrounding a single match, taken without regard to line boundaries. */
+export interface ByteWindow {
+  /** The absolute byte offset of the first byte in the window. */
+  absoluteByteOffset: number
+  /**
+   * The bytes of the window.
+   *
+   * Left out of plain results (see `SearcherBuilder.plainResults`).
+   */
+  bytes: Buffer
+  /** The number of bytes in the window. */
+  length: number
+  /** Start byte offset of the match within the window. */
+  matchStart: number
+  /** End byte offset of the match within the window. */
+  matchEnd: number
+}
+
+/**
+ * Cancel an async search by the `handle` of its promise, from
+ * `searchPathAsync`, `searchSliceAsync` or a `SearchService`.
+ *
+ * The search stops at its next read and its promise rejects with a
+ * "search cancelled" error. A slice is searched in place, and so can only
+ * be cancelled before it starts, unless the searcher has a cancellation
+ * token or a timeout. Returns whether the search was still running.
+ */
+export declare function cancelSearch(handle: number): boolean
+
+/** A capture group of a pattern, produced by `RegexMatcher.captureGroups`. */
+export interface CaptureGroup {
+  /** The index of the group, as in `$1`. */
+  index: number
+  /** The name of the group, as in `(?P<name>...)`, if it has one. */
+  name?: string
+  /** The index of the innermost group containing this one, if any. */
+  parent?: number
+  /**
+   * The position of the pattern the group is in, for matchers built from
+   * several patterns.
+   */
+  pattern: number
+}
+
+/**
+ * A combination of `SearcherBuilder` options that cannot work together,
+ * reported by `SearcherBuilder.validate` and as the `code` of the error
+ * thrown by `SearcherBuilder.build`.
+ */
+export declare const enum ConfigConflict {
+  /** Passthru reports every line, so context lines would be ignored. */
+  PassthruWithContext = 'PassthruWithContext',
+  /** A heap limit of 0 leaves no way to search without memory maps. */
+  HeapLimitWithoutMemoryMap = 'HeapLimitWithoutMemoryMap',
+  /**
+   * Stopping at the first non-matching line after a match does not
+   * combine with reporting non-matching lines.
+   */
+  StopOnNonmatchWithInvert = 'StopOnNonmatchWithInvert'
+}
+
+/**
+ * An incoherent combination of options, found by
+ * `SearcherBuilder.validate`.
+ */
+export interface ConfigProblem {
+  /** Which options conflict. */
+  code: ConfigConflict
+  /** A description of the conflict. */
+  message: string
+}
+
 /** The kind of context reported by a searcher. */
 export declare const enum ContextKind {
   /** Context before a match. */
@@ -245,15 +1313,357 @@ export declare const enum ContextKind {
   Other = 'Other'
 }
 
+/** A file with matches, produced by a directory search. */
+export interface DirSearchEntry {
+  /** The path of the file. */
+  path: string
+  /** The result of searching the file. */
+  result: SearchResult
+}
+
+/** The counts of a finished `searchDirParallel`. */
+export interface DirSearchFinish {
+  /** The number of files searched. */
+  filesSearched: number
+  /** The number of files with at least one match. */
+  filesWithMatches: number
+}
+
+/**
+ * Options for directory searches.
+ *
+ * Files are filtered as ripgrep does by default: hidden files, those below
+ * hidden directories, and those ignored by `.gitignore`, `.ignore` and
+ * git's exclude files are skipped, and symbolic links to directories are
+ * not followed.
+ */
+export interface DirSearchOptions {
+  /**
+   * How many files with matches to search ahead of the consumer. Defaults
+   * to 16. Only used by `searchDirIter` and `searchDirParallel`.
+   */
+  queueSize?: number
+  /**
+   * The number of threads `searchDirParallel` walks and searches on.
+   * Defaults to the searcher's `threads`.
+   */
+  threads?: number
+  /**
+   * Search hidden files and directories, those whose names start with a
+   * dot, as `rg --hidden` does. Defaults to false.
+   */
+  hidden?: boolean
+  /**
+   * Skip files ignored by `.gitignore` (within git repositories),
+   * `.ignore` and git's exclude files, in the directory and its parents.
+   * Defaults to true. The options below turn each source on or off.
+   */
+  ignoreFiles?: boolean
+  /** Respect `.gitignore` files. Defaults to `ignoreFiles`. */
+  gitIgnore?: boolean
+  /**
+   * Respect `.ignore` files, which work like `.gitignore` files outside
+   * git too. Defaults to `ignoreFiles`.
+   */
+  dotIgnore?: boolean
+  /**
+   * Names of further ignore files to respect in every directory, such as
+   * `.searchignore` or `.eslintignore`, written like `.gitignore` files and
+   * taking precedence over all other ignore rules. Respected along with
+   * `.ignore` files, so `dotIgnore: false` turns them off too.
+   */
+  customIgnoreFilenames?: Array<string>
+  /**
+   * Respect the global gitignore file, from git's `core.excludesFile`.
+   * Defaults to `ignoreFiles`.
+   */
+  gitGlobal?: boolean
+  /** Respect `.git/info/exclude`. Defaults to `ignoreFiles`. */
+  gitExclude?: boolean
+  /**
+   * Respect the ignore files of the directory's parents too. Defaults to
+   * `ignoreFiles`.
+   */
+  parents?: boolean
+  /**
+   * Only respect git's ignore rules within git repositories. Defaults to
+   * true.
+   */
+  requireGit?: boolean
+  /**
+   * Follow symbolic links to directories. Links back to a directory being
+   * walked are skipped rather than followed forever. Defaults to false.
+   */
+  followLinks?: boolean
+  /**
+   * How deep to descend, where 1 searches only the files directly in the
+   * directory. Unlimited by default.
+   */
+  maxDepth?: number
+  /**
+   * Skip files larger than this many bytes, as ripgrep's `--max-filesize`.
+   * Unlimited by default.
+   */
+  maxFilesize?: number
+  /**
+   * Globs, relative to the directory, that select the files to search, as
+   * ripgrep's `--glob`: a file must match one of them, unless they all
+   * start with `!` to exclude files instead. Where several match a file,
+   * the last one decides. Files matching a glob are searched even if
+   * hidden or ignored.
+   */
+  globs?: Array<string>
+  /**
+   * File types to search, from ripgrep's built-in definitions such as
+   * `"rust"`, `"js"` or `"markdown"` (see `rg --type-list`), as ripgrep's
+   * `--type`. Files of other types are skipped.
+   */
+  types?: Array<string>
+  /**
+   * File types not to search, as ripgrep's `--type-not`. These take
+   * precedence over `types`.
+   */
+  typesNot?: Array<string>
+}
+
+/** A document with matches, produced by `DocumentSet.search`. */
+export interface DocumentMatch {
+  /** The name the document was added under. */
+  name: string
+  /** The result of searching the document. */
+  result: SearchResult
+}
+
+/** Options for creating a `DocumentSet`. */
+export interface DocumentSetOptions {
+  /**
+   * Skip documents that lack the bytes of every literal a match could
+   * start with, without searching them. Defaults to true.
+   */
+  prefilter?: boolean
+}
+
+/**
+ * What to do with byte sequences that cannot be decoded while an input
+ * with a byte order mark is transcoded.
+ */
+export declare const enum EncodingErrorAction {
+  /** Replace them with U+FFFD, as without a policy, and count them. */
+  Replace = 'Replace',
+  /**
+   * Leave the lines that contain them out of the result and list them in
+   * `finish.undecodableLines`.
+   */
+  SkipLine = 'SkipLine',
+  /** Fail the search. */
+  Abort = 'Abort'
+}
+
+/** The kind of failure an error reports, set as the error's `code`. */
+export declare const enum ErrorCode {
+  /**
+   * A pattern failed to compile. The error has the `pattern`, its
+   * `patternIndex` among those built and, for syntax errors, the `span`
+   * of the error within it.
+   */
+  RegexSyntax = 'RegexSyntax',
+  /** An input could not be read. The error has the `path` of a file. */
+  Io = 'Io',
+  /**
+   * An input has data that is not valid in its encoding, and the
+   * searcher's encoding error policy is `Abort`.
+   */
+  Undecodable = 'Undecodable',
+  /** The search was cancelled by a token or an `onTick` callback. */
+  Cancelled = 'Cancelled',
+  /** An argument or option is invalid. */
+  InvalidArgument = 'InvalidArgument',
+  /**
+   * A search went past a limit: its results exceed `maxResultBytes` with
+   * the `Error` action, or a line does not fit in the heap limit.
+   */
+  LimitExceeded = 'LimitExceeded'
+}
+
+/** A file that could not be read during a multi-file search. */
+export interface FileError {
+  /** The path of the file. */
+  path: string
+  /** The error message of the last failed read. */
+  message: string
+}
+
+/** A file searched by `Searcher.searchDir`. */
+export interface FileSearchResult {
+  /** The path of the file. */
+  path: string
+  /** All matching lines. */
+  matches: Array<SearchMatch>
+  /** All context lines. */
+  context: Array<SearchContext>
+  /** Summary information. */
+  finish: SearchFinish
+  /** Phase timings, if profiling is enabled on the searcher. */
+  profile?: SearchProfile
+  /** Why the file could not be read, if the read error policy skipped it. */
+  error?: string
+  /** The code of `error`. */
+  errorCode?: ErrorCode
+}
+
 /** Find the first match of a pattern in the given text. */
 export declare function find(pattern: string, text: string | Buffer): MatchRange | null
 
 /** Find all matches of a pattern in the given text. */
-export declare function findAll(pattern: string, text: string | Buffer): Array<MatchRange>
+export declare function findAll(pattern: string, text: string | Buffer, options?: FindOptions | undefined | null): Array<MatchRange>
+
+/** Options for finding matches. */
+export interface FindOptions {
+  /** Report overlapping occurrences instead of resuming after each match. */
+  overlapping?: boolean
+}
+
+/** Options for building a `Gitignore`. */
+export interface GitignoreOptions {
+  /**
+   * The directory the rules are relative to, as the directory containing
+   * a `.gitignore` file. Defaults to the current directory.
+   */
+  root?: string
+  /** Match paths case insensitively. */
+  caseInsensitive?: boolean
+}
+
+/** The algorithm of `contentHash`. */
+export declare const enum HashAlgorithm {
+  /** 64-bit xxHash, fast but not cryptographic. */
+  Xxh64 = 'Xxh64',
+  /** SHA-256. */
+  Sha256 = 'Sha256'
+}
+
+/**
+ * Highlight the matches of several matchers in a text at once, as ranges
+ * that do not overlap, ordered by start.
+ *
+ * Where matches overlap, the one that takes precedence keeps the
+ * overlapping bytes and the others keep only the bytes no stronger match
+ * covers, so a match may be split into several ranges. Empty matches are
+ * left out. Offsets are in bytes, as with `RegexMatcher.findAll`.
+ */
+export declare function highlight(matchers: Array<RegexMatcher>, text: string | Buffer, options?: HighlightOptions | undefined | null): Array<HighlightRange>
+
+/** Options for `highlight`. */
+export interface HighlightOptions {
+  /** A label for each matcher, in the same order, copied to its ranges. */
+  labels?: Array<string>
+  /** Which match wins where matches overlap. Defaults to `Layer`. */
+  precedence?: HighlightPrecedence
+}
+
+/** Which match wins where the matches of `highlight` overlap. */
+export declare const enum HighlightPrecedence {
+  /**
+   * The match of the earlier matcher, as a higher layer covers the ones
+   * below it.
+   */
+  Layer = 'Layer',
+  /**
+   * The longer match, or of two as long, the match of the earlier
+   * matcher.
+   */
+  Longest = 'Longest'
+}
+
+/** A range of text highlighted by `highlight`. */
+export interface HighlightRange {
+  /** Start byte offset within the text. */
+  start: number
+  /** End byte offset within the text. */
+  end: number
+  /** The index of the matcher that matched, in the order given. */
+  layer: number
+  /** The label of the matcher, if `labels` names it. */
+  label?: string
+}
+
+/** How a path matched a gitignore. */
+export declare const enum IgnoreMatch {
+  /** No rule matched the path. */
+  None = 'None',
+  /** The last matching rule ignores the path. */
+  Ignore = 'Ignore',
+  /** The last matching rule is a `!` rule that re-includes the path. */
+  Whitelist = 'Whitelist'
+}
 
 /** Check if a pattern matches anywhere in the given text. */
 export declare function isMatch(pattern: string, text: string | Buffer): boolean
 
+/** The keywords found in one file by `KeywordScanner.scanPaths`. */
+export interface KeywordFileMatches {
+  path: string
+  matches: Array<KeywordMatch>
+}
+
+/** A keyword found by a `KeywordScanner`. */
+export interface KeywordMatch {
+  /** The keyword that matched. */
+  keyword: string
+  /** The index of the keyword in the word list. */
+  index: number
+  /** The 1-based line number of the match. */
+  lineNumber: number
+  /** The absolute byte offset of the start of the match. */
+  start: number
+  /** The absolute byte offset of the end of the match. */
+  end: number
+}
+
+/** Options for building a `KeywordScanner`. */
+export interface KeywordScannerOptions {
+  /** Match keywords ASCII case insensitively. */
+  caseInsensitive?: boolean
+  /**
+   * Only report keywords that start and end on word boundaries. Defaults
+   * to true.
+   */
+  word?: boolean
+  /**
+   * When loading from a file, skip lines whose first non-whitespace
+   * character is `#`.
+   */
+  skipComments?: boolean
+}
+
+/** The line endings counted in a searched input. */
+export interface LineEndings {
+  /** Lines ended by `
+  ` alone. */
+  lf: number
+  /** Lines ended by `\r
+  `. */
+  crlf: number
+  /** Whether both kinds occur. */
+  mixed: boolean
+}
+
+/** Where a line of a `SearchResult` starts. */
+export interface LinePosition {
+  lineNumber?: number
+  absoluteByteOffset: number
+}
+
+/** Match counts for every (matcher, file) pair. */
+export interface MatchMatrix {
+  /** The searched paths, in input order. */
+  paths: Array<string>
+  /** `counts[m][f]` is the number of matches of matcher `m` in `paths[f]`. */
+  counts: Array<Array<number>>
+  /** Files skipped because of read errors (see `readErrorPolicy`). */
+  errors: Array<FileError>
+}
+
 /** A single match found in a line. */
 export interface MatchRange {
   /** Start byte offset within the line. */
@@ -262,6 +1672,269 @@ export interface MatchRange {
   end: number
 }
 
+/** A context line of a `MergeInput`. */
+export interface MergeContext {
+  lineNumber?: number
+  absoluteByteOffset: number
+  line: string
+  bytes?: Buffer
+  kind: ContextKind
+}
+
+/**
+ * A `SearchResult` passed to `mergeResults`. Lines and windows may come
+ * without their `bytes`, as in plain results.
+ */
+export interface MergeInput {
+  matches: Array<MergeMatch>
+  context: Array<MergeContext>
+  finish: SearchFinish
+}
+
+/** A matching line of a `MergeInput`. */
+export interface MergeMatch {
+  lineNumber?: number
+  absoluteByteOffset: number
+  line: string
+  bytes?: Buffer
+  matches: Array<MatchRange>
+  windows?: Array<MergeWindow>
+  moreMatches?: boolean
+}
+
+/**
+ * Merge results of searches over chunks or ranges of the same haystack.
+ *
+ * Matches and context are ordered by absolute byte offset. Lines reported
+ * by more than one chunk (where chunks overlap), with the same offset and
+ * line number, are kept once, preferring the longest copy, and context
+ * lines that are matches elsewhere are dropped. Line numbers are taken
+ * as-is from the inputs. Plain results, such as those cloned from a
+ * worker, merge into a plain result.
+ */
+export declare function mergeResults(results: Array<MergeInput>): SearchResult
+
+/** A byte window of a `MergeMatch`. */
+export interface MergeWindow {
+  absoluteByteOffset: number
+  bytes?: Buffer
+  length: number
+  matchStart: number
+  matchEnd: number
+}
+
+/** Unicode normalization form applied before matching. */
+export declare const enum NormalizationForm {
+  /**
+   * Canonical composition: composed and decomposed forms of the same
+   * character (e.g. `é` and `e` + U+0301) match each other.
+   */
+  Nfc = 'Nfc',
+  /**
+   * Compatibility composition: additionally folds compatibility variants
+   * such as ligatures and full-width forms (e.g. `ﬁ` matches `fi`).
+   */
+  Nfkc = 'Nfkc'
+}
+
+/**
+ * A pattern with its own flags, for use with `buildMany`.
+ *
+ * Unset flags fall back to the builder configuration.
+ */
+export interface PatternEntry {
+  pattern: string
+  /**
+   * Match this pattern case insensitively (or, when `false`, case
+   * sensitively even if the builder is case insensitive).
+   */
+  caseInsensitive?: boolean
+  /** Only match this pattern on word boundaries. */
+  word?: boolean
+  /** Treat this pattern as a literal string. */
+  fixedString?: boolean
+}
+
+/** Options for reading newline-separated pattern lists. */
+export interface PatternFileOptions {
+  /**
+   * Skip lines that are empty or contain only whitespace.
+   *
+   * By default an empty line is an empty pattern, which matches everything.
+   */
+  skipBlankLines?: boolean
+  /** Skip lines whose first non-whitespace character is `#`. */
+  skipComments?: boolean
+}
+
+/**
+ * The part of a pattern that an error is about, in UTF-16 code units like
+ * JavaScript string indices.
+ */
+export interface PatternSpan {
+  start: number
+  end: number
+}
+
+/**
+ * A bundle of defaults familiar from a command line tool, applied by
+ * `SearcherBuilder.preset` and `RegexMatcherBuilder.preset`.
+ */
+export declare const enum Preset {
+  /**
+   * As `rg`: line numbers, binary files stop at the first NUL byte, BOMs
+   * are sniffed, and patterns without uppercase letters ignore case.
+   */
+  Ripgrep = 'Ripgrep',
+  /**
+   * As `grep`: no line numbers, binary files stop at the first NUL byte,
+   * no BOM sniffing, and case-sensitive patterns.
+   */
+  Grep = 'Grep',
+  /**
+   * The least work per search: no line numbers, no binary detection, no
+   * BOM sniffing, and case-sensitive patterns.
+   */
+  Minimal = 'Minimal'
+}
+
+/**
+ * How long the jobs of one priority waited for a thread of a
+ * `SearchService`.
+ */
+export interface QueueWaits {
+  /** The priority the jobs were queued with. */
+  priority: number
+  /** The number of jobs that have started. */
+  started: number
+  /** The mean time the jobs waited. */
+  meanWaitMs: number
+  /** The longest time a job waited. */
+  maxWaitMs: number
+}
+
+/** What to do when a file cannot be read during a multi-file search. */
+export declare const enum ReadErrorAction {
+  /** Fail the whole search with the read error. */
+  Abort = 'Abort',
+  /** Skip the file and record the error in the result. */
+  Skip = 'Skip'
+}
+
+/**
+ * Policy for read failures during multi-file searches.
+ *
+ * Useful on network filesystems, where a single busy file would otherwise
+ * fail an entire scan.
+ */
+export interface ReadErrorPolicy {
+  /** What to do once all retries have failed. Defaults to `Abort`. */
+  action?: ReadErrorAction
+  /**
+   * How many times to retry a read that failed with an error that may
+   * pass: one that was interrupted, would have blocked, or found the file
+   * busy. Other errors, such as a missing file, fail at once. Only
+   * searches off the JavaScript thread retry, such as async searches and
+   * those that produce their results through iterators or callbacks.
+   * Defaults to 0.
+   */
+  retries?: number
+  /**
+   * Delay before the first retry in milliseconds, doubled for every
+   * further retry. Defaults to 0.
+   */
+  backoffMs?: number
+}
+
+/** Options controlling replacement template expansion. */
+export interface ReplaceOptions {
+  /**
+   * Reject references to capture groups that do not exist in the pattern.
+   *
+   * By default such references expand to an empty string (or to their
+   * `${name:-default}` fallback).
+   */
+  strict?: boolean
+}
+
+/**
+ * Literals every match of a matcher contains, produced by
+ * `RegexMatcher.prefilterLiterals`.
+ */
+export interface RequiredLiterals {
+  /**
+   * The alternatives: a text can only match if it contains every literal
+   * of at least one of them.
+   */
+  anyOf: Array<Array<string>>
+  /**
+   * Whether the matcher ignores case, so that the literals must be looked
+   * up case-insensitively.
+   */
+  caseInsensitive: boolean
+}
+
+/** What to do when a search collects more than `maxResultBytes`. */
+export declare const enum ResultLimitAction {
+  /**
+   * Stop the search and return what was collected, with
+   * `finish.truncated` set.
+   */
+  Truncate = 'Truncate',
+  /** Fail the search. */
+  Error = 'Error'
+}
+
+/**
+ * Where the lines of a `SearchResult` start, read from the result passed
+ * to `Searcher.resultCursor`. Only these fields are read, so results
+ * without line bytes can be passed too.
+ */
+export interface ResultLines {
+  matches: Array<LinePosition>
+  context: Array<LinePosition>
+}
+
+/** A rule of a `RulePack`. */
+export interface Rule {
+  /** The name findings are grouped under. Names must be unique. */
+  name: string
+  /** The regular expression to search for. */
+  pattern: string
+  /** Match the pattern case insensitively. */
+  caseInsensitive?: boolean
+  /** Only match the pattern on word boundaries. */
+  word?: boolean
+  /** Treat the pattern as a literal string. */
+  fixedString?: boolean
+  /** A label passed through to the findings, such as `"error"`. */
+  severity?: string
+  /**
+   * Globs of the files the rule applies to, relative to the searched
+   * directory, as with `rg -g`: a file must match one of them unless all
+   * start with `!`, which excludes. All files by default.
+   */
+  globs?: Array<string>
+}
+
+/** The findings of one rule, produced by `RulePack.run`. */
+export interface RuleFindings {
+  /** The name of the rule. */
+  name: string
+  /** The severity of the rule, if it has one. */
+  severity?: string
+  /** The files the rule matches, in a stable order. */
+  files: Array<FileSearchResult>
+}
+
+/** The result of `RulePack.run`. */
+export interface RuleReport {
+  /** The findings of every rule, in rule order. */
+  rules: Array<RuleFindings>
+  /** Files skipped because of read errors (see `readErrorPolicy`). */
+  errors: Array<FileError>
+}
+
 /**
  * Search a string/buffer for a pattern with default options.
  *
@@ -269,6 +1942,15 @@ export interface MatchRange {
  */
 export declare function search(pattern: string, haystack: string | Buffer): SearchResult
 
+/** Options for `RegexMatcher.searchAll`. */
+export interface SearchAllOptions {
+  /**
+   * Search the inputs on several threads, as many as the searcher's
+   * `threads`. Disabled by default.
+   */
+  parallel?: boolean
+}
+
 /** Represents a context line (before/after a match). */
 export interface SearchContext {
   /** The line number (1-based), if line numbers are enabled. */
@@ -277,21 +1959,136 @@ export interface SearchContext {
   absoluteByteOffset: number
   /** The context line content. */
   line: string
-  /** The bytes of the context line. */
+  /**
+   * The bytes of the context line.
+   *
+   * Left out of plain results (see `SearcherBuilder.plainResults`).
+   */
   bytes: Buffer
   /** The kind of context (before, after, other). */
   kind: ContextKind
 }
 
+/**
+ * Search the files below a directory with default options, grouping the
+ * results by file.
+ *
+ * See `Searcher.searchDir`.
+ */
+export declare function searchDir(matcher: RegexMatcher, dir: string, options?: DirSearchOptions | undefined | null): Array<FileSearchResult>
+
+/**
+ * Search the files below a directory with default options.
+ *
+ * See `Searcher.searchDirIter`.
+ */
+export declare function searchDirIter(matcher: RegexMatcher, dir: string, options?: DirSearchOptions | undefined | null): AsyncIterableIterator<DirSearchEntry>
+
+/**
+ * Search the files below a directory in parallel with default options.
+ *
+ * See `Searcher.searchDirParallel`.
+ */
+export declare function searchDirParallel(matcher: RegexMatcher, dir: string, onFile: (entry: DirSearchEntry) => void, onFinish?: ((error: Error | null, finish?: DirSearchFinish) => void) | null, options?: DirSearchOptions | null): void
+
 /** Search a file for a pattern with default options. */
 export declare function searchFile(pattern: string, path: string): SearchResult
 
+/** Options for `Searcher.searchFiles`. */
+export interface SearchFilesOptions {
+  /**
+   * The number of threads to search on. Defaults to the searcher's
+   * `threads`.
+   */
+  threads?: number
+  /**
+   * Produce results in the order of the paths, holding back each one until
+   * those before it are done. Otherwise results are produced as soon as
+   * they are found. Enabled by default.
+   */
+  ordered?: boolean
+  /**
+   * How many results `searchFilesIter` buffers ahead of the consumer.
+   * Defaults to 16.
+   */
+  queueSize?: number
+}
+
 /** Summary information returned after a search completes. */
 export interface SearchFinish {
   /** The absolute byte offset of the end of the search. */
   byteCount: number
   /** Whether binary data was detected (if binary detection is enabled). */
   binaryByteOffset?: number
+  /**
+   * Approximate peak size in bytes of the buffer holding the input while
+   * it was searched. Compare with `heapLimit` to see how close a search
+   * came to the limit. For slices and memory maps this is the input size.
+   */
+  peakBufferBytes: number
+  /** Approximate size in bytes of the collected results. */
+  resultBytes: number
+  /**
+   * Approximate peak memory used by the search in bytes: the buffer,
+   * collected results and any normalized copy of the input.
+   */
+  peakMemoryBytes: number
+  /**
+   * Whether `maxMatches`, `maxMatchCount` or `maxResultBytes` was
+   * reached, so the search may have stopped before the end of the input.
+   */
+  truncated: boolean
+  /**
+   * Where the search stopped, if it stopped before the end of the input
+   * because of a match limit, `stopOnNonmatch` or binary data. Pass it as
+   * `resumeFrom` to continue the search from there. Not set for inputs
+   * with a byte order mark, which are searched transcoded.
+   */
+  resumeOffset?: number
+  /** The non-matching line at which `stopOnNonmatch` ended the search. */
+  stoppedAt?: StopPosition
+  /**
+   * The number of undecodable sequences in a transcoded input, when an
+   * encoding error policy is set.
+   */
+  encodingErrors?: number
+  /**
+   * The lines left out of the result because they contain undecodable
+   * sequences, with `EncodingErrorAction.SkipLine`.
+   */
+  undecodableLines?: Array<number>
+  /** The line endings in the input, if `lineEndingReport` is enabled. */
+  lineEndings?: LineEndings
+  /** The hash of the whole input as lowercase hex, if `contentHash` is set. */
+  contentHash?: string
+  /**
+   * Whether `timeoutMs` stopped the search, in which case the result is
+   * partial. Only set when a timeout is set.
+   */
+  timedOut?: boolean
+}
+
+/** Options for a job queued on a `SearchService`. */
+export interface SearchJobOptions {
+  /**
+   * Jobs with higher priorities run first, and jobs with the same priority
+   * run in the order they were queued. Defaults to 0.
+   */
+  priority?: number
+}
+
+/** The outcome of searching one input of `Searcher.searchMany`. */
+export interface SearchManyEntry {
+  /** The position of the input in the inputs. */
+  index: number
+  /** The path of the input, if it was given as a path. */
+  path?: string
+  /** The result of the search, unless it failed. */
+  result?: SearchResult
+  /** Why the search failed, if it did. */
+  error?: string
+  /** The code of `error`. */
+  errorCode?: ErrorCode
 }
 
 /** Represents a matching line found by the searcher. */
@@ -302,10 +2099,108 @@ export interface SearchMatch {
   absoluteByteOffset: number
   /** The matched line content. */
   line: string
-  /** The bytes of the matched line. */
+  /**
+   * The bytes of the matched line.
+   *
+   * Left out of plain results (see `SearcherBuilder.plainResults`).
+   */
   bytes: Buffer
   /** All match ranges within the line. */
   matches: Array<MatchRange>
+  /**
+   * Byte windows around each match, in the same order as `matches`.
+   *
+   * Only present when byte context is enabled on the searcher.
+   */
+  windows?: Array<ByteWindow>
+  /** Set when `maxMatchesPerLine` left out further matches on the line. */
+  moreMatches?: boolean
+}
+
+/**
+ * Counters of the searches run by the process so far, to export to a
+ * metrics system.
+ *
+ * The counters only grow, apart from `searchesRunning`, so take the
+ * difference between snapshots for rates. They count the searches of every
+ * thread of the process, worker threads included.
+ */
+export declare function searchMetrics(): SearchMetrics
+
+/**
+ * Counters of the searches run by the process, from `searchMetrics`.
+ *
+ * Searches are counted when they collect or stream results; counting
+ * matches, match matrices and other searches that only count are not.
+ */
+export interface SearchMetrics {
+  /** The number of searches started. */
+  searchesStarted: number
+  /**
+   * The number of searches that finished, including those that stopped
+   * early at a limit or a timeout.
+   */
+  searchesCompleted: number
+  /**
+   * The number of searches stopped by cancellation, including those
+   * cancelled before they started.
+   */
+  searchesCancelled: number
+  /** The number of searches that failed for any other reason. */
+  searchesFailed: number
+  /** The number of searches running now. */
+  searchesRunning: number
+  /** The number of bytes read by completed searches. */
+  bytesSearched: number
+  /** The number of matches produced by completed searches. */
+  matches: number
+}
+
+/** Options for a single search. */
+export interface SearchOptions {
+  /**
+   * Start the search at this absolute byte offset, usually the
+   * `finish.resumeOffset` of an earlier search of the same input. Offsets
+   * and line numbers in the result still count from the start of the
+   * input. Inputs with a byte order mark cannot be resumed, so any other
+   * offset is an error for them.
+   */
+  resumeFrom?: number
+  /**
+   * The line number of the first line of the input, when it is part of a
+   * larger document such as an editor viewport. Defaults to 1.
+   */
+  firstLineNumber?: number
+  /**
+   * The absolute byte offset of the start of the input within a larger
+   * document. Defaults to 0. Reported offsets and `resumeFrom` are
+   * offsets into the document.
+   */
+  baseOffset?: number
+  /**
+   * How many bytes a synchronous search reads between calls to its
+   * `onTick` callback. Defaults to 16 MiB.
+   */
+  tickBytes?: number
+}
+
+/** Time spent in each phase of a search, in milliseconds. */
+export interface SearchProfile {
+  /** Opening and reading the input. */
+  readMs: number
+  /**
+   * Normalizing or folding the input (see `normalization`). Decoding done
+   * by BOM sniffing is counted as matching.
+   */
+  transcodeMs: number
+  /** Running the matcher over the input. */
+  matchMs: number
+  /** Collecting matches and context lines into results. */
+  collectMs: number
+  /** Converting the results into JavaScript values. */
+  marshalMs: number
+  /** Total time, including marshalling. */
+  totalMs: number
 }
 
 /** Complete search result containing all matches and context. */
@@ -316,4 +2211,62 @@ export interface SearchResult {
   context: Array<SearchContext>
   /** Summary information. */
   finish: SearchFinish
+  /** Phase timings, if profiling is enabled on the searcher. */
+  profile?: SearchProfile
+}
+
+/** Options for creating a `SearchService`. */
+export interface SearchServiceOptions {
+  /** The number of search threads. Defaults to the number of CPUs. */
+  threads?: number
+  /**
+   * Raise the priority of a queued job by one for every `agingMs`
+   * milliseconds it waits, so lower priority jobs still run while higher
+   * priority ones keep arriving. Off by default.
+   */
+  agingMs?: number
+}
+
+/** How search results are ordered before they are returned. */
+export declare const enum SortBy {
+  /** By path, then by position within the file. */
+  Path = 'Path',
+  /** By number of matches, most first. */
+  MatchCount = 'MatchCount',
+  /** By the offset of the first match within its line, smallest first. */
+  FirstMatchOffset = 'FirstMatchOffset'
+}
+
+/** A line at which a search stopped. */
+export interface StopPosition {
+  /** The line number (if line numbers are enabled). */
+  lineNumber?: number
+  /** The absolute byte offset of the start of the line. */
+  absoluteByteOffset: number
+}
+
+/** Options for `Searcher.searchPathStreaming`. */
+export interface StreamingSearchOptions {
+  /**
+   * How many lines to queue for the callbacks before the search waits.
+   * Defaults to 64.
+   */
+  queueSize?: number
+  /** Called when binary data is first found, after the lines before it. */
+  onBinary?: (binary: BinaryEvent) => void
+}
+
+/** Options for creating a `StreamMatcher`. */
+export interface StreamMatcherOptions {
+  /**
+   * The length in bytes of the longest match to report in full. Longer
+   * matches may be reported cut short. Defaults to 64 KiB.
+   */
+  maxMatchLength?: number
+}
+
+/** Options for streaming searches. */
+export interface StreamSearchOptions {
+  /** How many lines to search ahead of the consumer. Defaults to 64. */
+  queueSize?: number
 }
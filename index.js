@@ -576,14 +576,46 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.CancellationToken = nativeBinding.CancellationToken
+module.exports.DirSearch = nativeBinding.DirSearch
+module.exports.DocumentSet = nativeBinding.DocumentSet
+module.exports.FileSearch = nativeBinding.FileSearch
+module.exports.Gitignore = nativeBinding.Gitignore
+module.exports.KeywordScanner = nativeBinding.KeywordScanner
+module.exports.RecordSearch = nativeBinding.RecordSearch
 module.exports.RegexMatcher = nativeBinding.RegexMatcher
 module.exports.RegexMatcherBuilder = nativeBinding.RegexMatcherBuilder
+module.exports.ResultCursor = nativeBinding.ResultCursor
+module.exports.RulePack = nativeBinding.RulePack
 module.exports.Searcher = nativeBinding.Searcher
 module.exports.SearcherBuilder = nativeBinding.SearcherBuilder
+module.exports.SearchService = nativeBinding.SearchService
+module.exports.StreamMatcher = nativeBinding.StreamMatcher
+module.exports.StreamSearch = nativeBinding.StreamSearch
+module.exports.StringPool = nativeBinding.StringPool
 module.exports.BinaryDetectionMode = nativeBinding.BinaryDetectionMode
+module.exports.BomHandling = nativeBinding.BomHandling
+module.exports.cancelSearch = nativeBinding.cancelSearch
+module.exports.ConfigConflict = nativeBinding.ConfigConflict
 module.exports.ContextKind = nativeBinding.ContextKind
+module.exports.EncodingErrorAction = nativeBinding.EncodingErrorAction
+module.exports.ErrorCode = nativeBinding.ErrorCode
 module.exports.find = nativeBinding.find
 module.exports.findAll = nativeBinding.findAll
+module.exports.HashAlgorithm = nativeBinding.HashAlgorithm
+module.exports.highlight = nativeBinding.highlight
+module.exports.HighlightPrecedence = nativeBinding.HighlightPrecedence
+module.exports.IgnoreMatch = nativeBinding.IgnoreMatch
 module.exports.isMatch = nativeBinding.isMatch
+module.exports.mergeResults = nativeBinding.mergeResults
+module.exports.NormalizationForm = nativeBinding.NormalizationForm
+module.exports.Preset = nativeBinding.Preset
+module.exports.ReadErrorAction = nativeBinding.ReadErrorAction
+module.exports.ResultLimitAction = nativeBinding.ResultLimitAction
 module.exports.search = nativeBinding.search
+module.exports.searchDir = nativeBinding.searchDir
+module.exports.searchDirIter = nativeBinding.searchDirIter
+module.exports.searchDirParallel = nativeBinding.searchDirParallel
 module.exports.searchFile = nativeBinding.searchFile
+module.exports.searchMetrics = nativeBinding.searchMetrics
+module.exports.SortBy = nativeBinding.SortBy
@@ -2,17 +2,49 @@
 import binding from './index.js'
 
 export const {
+  CancellationToken,
+  DirSearch,
+  DocumentSet,
+  FileSearch,
+  Gitignore,
+  KeywordScanner,
+  RecordSearch,
   RegexMatcher,
   RegexMatcherBuilder,
+  ResultCursor,
+  RulePack,
   Searcher,
   SearcherBuilder,
+  SearchService,
+  StreamMatcher,
+  StreamSearch,
+  StringPool,
   BinaryDetectionMode,
+  BomHandling,
+  cancelSearch,
+  ConfigConflict,
   ContextKind,
+  EncodingErrorAction,
+  ErrorCode,
   find,
   findAll,
+  HashAlgorithm,
+  highlight,
+  HighlightPrecedence,
+  IgnoreMatch,
   isMatch,
+  mergeResults,
+  NormalizationForm,
+  Preset,
+  ReadErrorAction,
+  ResultLimitAction,
   search,
+  searchDir,
+  searchDirIter,
+  searchDirParallel,
   searchFile,
+  searchMetrics,
+  SortBy,
 } = binding
 
 export default binding
@@ -1,5 +1,61 @@
 /* grep-js/matcher types */
 
+import type { Searcher, SearchResult } from './searcher'
+
+/** The kind of failure an error reports, set as the error's `code`. */
+export declare const enum ErrorCode {
+  /**
+   * A pattern failed to compile. The error has the `pattern`, its
+   * `patternIndex` among those built and, for syntax errors, the `span`
+   * of the error within it.
+   */
+  RegexSyntax = 'RegexSyntax',
+  /** An input could not be read. The error has the `path` of a file. */
+  Io = 'Io',
+  /**
+   * An input has data that is not valid in its encoding, and the
+   * searcher's encoding error policy is `Abort`.
+   */
+  Undecodable = 'Undecodable',
+  /** The search was cancelled by a token or an `onTick` callback. */
+  Cancelled = 'Cancelled',
+  /** An argument or option is invalid. */
+  InvalidArgument = 'InvalidArgument',
+  /**
+   * A search went past a limit: its results exceed `maxResultBytes` with
+   * the `Error` action, or a line does not fit in the heap limit.
+   */
+  LimitExceeded = 'LimitExceeded',
+}
+
+/** Unicode normalization form applied before matching. */
+export declare const enum NormalizationForm {
+  /**
+   * Canonical composition: composed and decomposed forms of the same
+   * character (e.g. `é` and `e` + U+0301) match each other.
+   */
+  Nfc = 'Nfc',
+  /**
+   * Compatibility composition: additionally folds compatibility variants
+   * such as ligatures and full-width forms (e.g. `ﬁ` matches `fi`).
+   */
+  Nfkc = 'Nfkc',
+}
+
+/** Which match wins where the matches of `highlight` overlap. */
+export declare const enum HighlightPrecedence {
+  /**
+   * The match of the earlier matcher, as a higher layer covers the ones
+   * below it.
+   */
+  Layer = 'Layer',
+  /**
+   * The longer match, or of two as long, the match of the earlier
+   * matcher.
+   */
+  Longest = 'Longest',
+}
+
 export interface MatchRange {
   /** Start byte offset within the line. */
   start: number
@@ -7,6 +63,218 @@ export interface MatchRange {
   end: number
 }
 
+/** Options for finding matches. */
+export interface FindOptions {
+  /** Report overlapping occurrences instead of resuming after each match. */
+  overlapping?: boolean
+}
+
+/** A range of text highlighted by `highlight`. */
+export interface HighlightRange {
+  /** Start byte offset within the text. */
+  start: number
+  /** End byte offset within the text. */
+  end: number
+  /** The index of the matcher that matched, in the order given. */
+  layer: number
+  /** The label of the matcher, if `labels` names it. */
+  label?: string
+}
+
+/** Options controlling replacement template expansion. */
+export interface ReplaceOptions {
+  /**
+   * Reject references to capture groups that do not exist in the pattern.
+   *
+   * By default such references expand to an empty string (or to their
+   * `${name:-default}` fallback).
+   */
+  strict?: boolean
+}
+
+/** Bytes surrounding a single match, taken without regard to line boundaries. */
+export interface ByteWindow {
+  /** The absolute byte offset of the first byte in the window. */
+  absoluteByteOffset: number
+  /**
+   * The bytes of the window.
+   *
+   * Left out of plain results (see `SearcherBuilder.plainResults`).
+   */
+  bytes: Buffer
+  /** The number of bytes in the window. */
+  length: number
+  /** Start byte offset of the match within the window. */
+  matchStart: number
+  /** End byte offset of the match within the window. */
+  matchEnd: number
+}
+
+/** A match found by a line-less binary scan. */
+export interface BinaryMatch {
+  /** The absolute byte offset of the start of the match. */
+  start: number
+  /** The absolute byte offset of the end of the match. */
+  end: number
+  /** Bytes around the match, if a window size was requested. */
+  window?: ByteWindow
+}
+
+/** Options for line-less binary scans. */
+export interface BinaryScanOptions {
+  /** Number of bytes to include before each match in its window. */
+  beforeBytes?: number
+  /** Number of bytes to include after each match in its window. */
+  afterBytes?: number
+  /** Stop after this many matches. */
+  maxMatches?: number
+}
+
+/**
+ * A pattern with its own flags, for use with `buildMany`.
+ *
+ * Unset flags fall back to the builder configuration.
+ */
+export interface PatternEntry {
+  pattern: string
+  /**
+   * Match this pattern case insensitively (or, when `false`, case
+   * sensitively even if the builder is case insensitive).
+   */
+  caseInsensitive?: boolean
+  /** Only match this pattern on word boundaries. */
+  word?: boolean
+  /** Treat this pattern as a literal string. */
+  fixedString?: boolean
+}
+
+/** Options for reading newline-separated pattern lists. */
+export interface PatternFileOptions {
+  /**
+   * Skip lines that are empty or contain only whitespace.
+   *
+   * By default an empty line is an empty pattern, which matches everything.
+   */
+  skipBlankLines?: boolean
+  /** Skip lines whose first non-whitespace character is `#`. */
+  skipComments?: boolean
+}
+
+/** Options for `RegexMatcher.searchAll`. */
+export interface SearchAllOptions {
+  /**
+   * Search the inputs on several threads, as many as the searcher's
+   * `threads`. Disabled by default.
+   */
+  parallel?: boolean
+}
+
+/** A capture group of a pattern, produced by `RegexMatcher.captureGroups`. */
+export interface CaptureGroup {
+  /** The index of the group, as in `$1`. */
+  index: number
+  /** The name of the group, as in `(?P<name>...)`, if it has one. */
+  name?: string
+  /** The index of the innermost group containing this one, if any. */
+  parent?: number
+  /**
+   * The position of the pattern the group is in, for matchers built from
+   * several patterns.
+   */
+  pattern: number
+}
+
+/**
+ * Literals every match of a matcher contains, produced by
+ * `RegexMatcher.prefilterLiterals`.
+ */
+export interface RequiredLiterals {
+  /**
+   * The alternatives: a text can only match if it contains every literal
+   * of at least one of them.
+   */
+  anyOf: Array<Array<string>>
+  /**
+   * Whether the matcher ignores case, so that the literals must be looked
+   * up case-insensitively.
+   */
+  caseInsensitive: boolean
+}
+
+/** Options for creating a `StreamMatcher`. */
+export interface StreamMatcherOptions {
+  /**
+   * The length in bytes of the longest match to report in full. Longer
+   * matches may be reported cut short. Defaults to 64 KiB.
+   */
+  maxMatchLength?: number
+}
+
+/**
+ * The part of a pattern that an error is about, in UTF-16 code units like
+ * JavaScript string indices.
+ */
+export interface PatternSpan {
+  start: number
+  end: number
+}
+
+/**
+ * Matches in a stream fed chunk by chunk, such as the chunks passing
+ * through a Node.js `Transform`.
+ *
+ * Matches are found without regard to lines, as by
+ * `RegexMatcher.scanBytes`, and may span chunks. A match is reported once
+ * enough input has been fed that no longer match could start where it
+ * does, so only the last `maxMatchLength` bytes are buffered.
+ */
+export declare class StreamMatcher {
+  /**
+   * Create a stream matcher. Matchers that normalize their input are not
+   * supported.
+   */
+  constructor(matcher: RegexMatcher, options?: StreamMatcherOptions | undefined | null)
+  /** The number of bytes fed so far. */
+  get position(): number
+  /**
+   * Feed the next chunk of the stream. Returns the matches that are
+   * complete, with absolute offsets.
+   */
+  push(chunk: string | Buffer): Array<BinaryMatch>
+  /** End the stream. Returns the remaining matches. */
+  end(): Array<BinaryMatch>
+}
+
+/**
+ * A bundle of defaults familiar from a command line tool, applied by
+ * `SearcherBuilder.preset` and `RegexMatcherBuilder.preset`.
+ */
+export declare const enum Preset {
+  /**
+   * As `rg`: line numbers, binary files stop at the first NUL byte, BOMs
+   * are sniffed, and patterns without uppercase letters ignore case.
+   */
+  Ripgrep = 'Ripgrep',
+  /**
+   * As `grep`: no line numbers, binary files stop at the first NUL byte,
+   * no BOM sniffing, and case-sensitive patterns.
+   */
+  Grep = 'Grep',
+  /**
+   * The least work per search: no line numbers, no binary detection, no
+   * BOM sniffing, and case-sensitive patterns.
+   */
+  Minimal = 'Minimal',
+}
+
+/** Options for `highlight`. */
+export interface HighlightOptions {
+  /** A label for each matcher, in the same order, copied to its ranges. */
+  labels?: Array<string>
+  /** Which match wins where matches overlap. Defaults to `Layer`. */
+  precedence?: HighlightPrecedence
+}
+
 /**
  * A compiled regex matcher.
  *
@@ -15,6 +283,29 @@ export interface MatchRange {
 export declare class RegexMatcher {
   /** Create a new matcher from a pattern with default options. */
   static fromPattern(pattern: string): RegexMatcher
+  /**
+   * Create a matcher sharing this one's compiled pattern, without
+   * compiling it again.
+   */
+  clone(): RegexMatcher
+  /**
+   * Share the compiled pattern with other worker threads.
+   *
+   * Returns a handle that can be posted to a worker and passed to
+   * `RegexMatcher.fromShared` there. The pattern stays in memory until
+   * the handle is passed to `RegexMatcher.releaseShared`.
+   */
+  share(): number
+  /**
+   * Create a matcher from a handle returned by `share`, in any thread of
+   * the process.
+   */
+  static fromShared(handle: number): RegexMatcher
+  /**
+   * Release a handle returned by `share`. Matchers already created from it
+   * keep working. Returns whether the handle was still shared.
+   */
+  static releaseShared(handle: number): boolean
   /** Check if the given text matches the pattern. */
   isMatch(text: string | Buffer): boolean
   /**
@@ -23,8 +314,70 @@ export declare class RegexMatcher {
    * Returns the start and end byte offsets, or null if no match.
    */
   find(text: string | Buffer): MatchRange | null
-  /** Find all matches in the given text. */
-  findAll(text: string | Buffer): Array<MatchRange>
+  /**
+   * Find all matches in the given text.
+   *
+   * With `overlapping` enabled, every occurrence is reported even when it
+   * overlaps a previous one (e.g. `aa` in `aaaa` at 0, 1 and 2).
+   */
+  findAll(text: string | Buffer, options?: FindOptions | undefined | null): Array<MatchRange>
+  /**
+   * Scan raw bytes for matches without any notion of lines.
+   *
+   * Reports absolute offsets of every match, optionally with a window of
+   * surrounding bytes. No line splitting or UTF-8 conversion is performed,
+   * which makes this suitable for firmware images and other blobs.
+   */
+  scanBytes(data: Buffer, options?: BinaryScanOptions | undefined | null): Array<BinaryMatch>
+  /**
+   * Scan a file's raw bytes for matches without any notion of lines.
+   *
+   * See `scanBytes`.
+   */
+  scanFile(path: string, options?: BinaryScanOptions | undefined | null): Array<BinaryMatch>
+  /**
+   * Replace the first match in the given text.
+   *
+   * See `replaceAll` for the replacement template syntax.
+   */
+  replace(text: string | Buffer, replacement: string, options?: ReplaceOptions | undefined | null): string | Buffer
+  /**
+   * Replace every match in the given text.
+   *
+   * The replacement may reference capture groups with `$1`, `$name` or
+   * `${name}`, and supply a fallback with `${name:-default}` that is used
+   * when the group did not participate in the match or matched nothing.
+   * Use `$$` for a literal `$`.
+   *
+   * Returns a string for string input and a Buffer for Buffer input.
+   */
+  replaceAll(text: string | Buffer, replacement: string, options?: ReplaceOptions | undefined | null): string | Buffer
+  /**
+   * The capture groups of the pattern, in index order, without group 0
+   * (the whole match).
+   *
+   * Each group has the index and name replacement templates refer to it
+   * by, and the group it is nested in, as parsed with the matcher's own
+   * syntax options. Useful to offer capture pickers for replacements.
+   */
+  captureGroups(): Array<CaptureGroup>
+  /**
+   * The literals every match contains, for narrowing down candidate
+   * documents in an external index before searching them, or null when
+   * matches require no literal.
+   *
+   * Texts that contain all literals of none of the alternatives cannot
+   * match. Texts that do may still not match.
+   */
+  prefilterLiterals(): RequiredLiterals | null
+  /**
+   * Search several independent inputs in one call.
+   *
+   * Each input is searched on its own, as by `searcher.searchSlice`, with
+   * `searcher` or a default searcher. Results are in the order of the
+   * inputs.
+   */
+  searchAll(haystacks: Array<string | Buffer>, options?: SearchAllOptions | undefined | null, searcher?: Searcher | undefined | null): Array<SearchResult>
 }
 
 /**
@@ -39,10 +392,80 @@ export declare class RegexMatcherBuilder {
   constructor()
   /** Build a new matcher for the provided pattern. */
   build(pattern: string): RegexMatcher
-  /** Build a new matcher from multiple patterns (joined as alternation). */
-  buildMany(patterns: Array<string>): RegexMatcher
+  /**
+   * Build a new matcher for the provided pattern on the libuv thread
+   * pool, without blocking the event loop.
+   *
+   * Resolves with the same matcher as `build`.
+   */
+  buildAsync(pattern: string): Promise<RegexMatcher>
+  /**
+   * Build a new matcher from multiple patterns (joined as alternation).
+   *
+   * Entries may be plain patterns or `PatternEntry` objects carrying their
+   * own flags, so a heterogeneous rule set compiles into a single matcher.
+   */
+  buildMany(patterns: Array<string | PatternEntry>): RegexMatcher
+  /**
+   * Build a new matcher from multiple patterns on the libuv thread pool,
+   * without blocking the event loop. Compiling alternations of many
+   * thousands of patterns, such as a word list, can take long enough to be
+   * noticed.
+   *
+   * Resolves with the same matcher as `buildMany`.
+   */
+  buildManyAsync(patterns: Array<string | PatternEntry>): Promise<RegexMatcher>
+  /**
+   * Build a new matcher from a file containing one pattern per line, like
+   * `rg -f`.
+   *
+   * Both `\n` and `\r\n` line endings are accepted. Every line is a
+   * pattern unless skipped by `options`.
+   */
+  buildFromPatternFile(path: string, options?: PatternFileOptions | undefined | null): RegexMatcher
+  /**
+   * Build a new matcher from a newline-separated list of patterns, such as
+   * the output of another process.
+   *
+   * Lines are handled exactly as in `buildFromPatternFile`.
+   */
+  buildFromPatternList(patterns: string | Buffer, options?: PatternFileOptions | undefined | null): RegexMatcher
   /** Build a new matcher from literal strings (optimized alternation). */
   buildLiterals(literals: Array<string>): RegexMatcher
+  /**
+   * Build a matcher for a literal query in which every run of whitespace
+   * matches any run of whitespace.
+   *
+   * Useful for finding a sentence or code snippet regardless of how it was
+   * wrapped or indented. Leading and trailing whitespace is ignored. Runs
+   * match line terminators too, so a query can span lines when the
+   * searcher is in multi-line mode. A query of only whitespace, which
+   * would match every line, is an error.
+   */
+  buildWhitespaceFlexible(literal: string): RegexMatcher
+  /**
+   * Build a matcher for an exact sequence of bytes.
+   *
+   * The bytes are matched verbatim regardless of whether they are valid
+   * UTF-8. Options like `caseInsensitive` and `word` still apply.
+   */
+  buildBytes(literal: Buffer): RegexMatcher
+  /**
+   * Build a matcher from a hex byte signature such as `"7F 45 4C 46 ?? 01"`.
+   *
+   * The signature is a sequence of two-digit hex bytes, optionally
+   * separated by whitespace, where `??` matches any single byte.
+   */
+  buildHex(signature: string): RegexMatcher
+  /**
+   * Treat patterns as matching raw bytes rather than Unicode text.
+   *
+   * This is the same as `unicode(false)`: `\xNN` escapes match the byte
+   * `NN` and `.` matches any byte, so patterns can match data that is not
+   * valid UTF-8. Unicode-aware parts of a pattern can still be written
+   * inside a `(?u:...)` group.
+   */
+  bytes(yes: boolean): this
   /**
    * Set the value for the case insensitive (`i`) flag.
    *
@@ -50,6 +473,26 @@ export declare class RegexMatcherBuilder {
    * lower case variants.
    */
   caseInsensitive(yes: boolean): this
+  /**
+   * Match letters case-insensitively by ASCII case folding alone, for data
+   * known to be ASCII.
+   *
+   * Matchers are built as with `caseInsensitive(true)` and
+   * `unicode(false)`: the pattern is compiled without the Unicode case
+   * tables and classes, which makes case-insensitive scans of large logs
+   * cheaper, particularly for patterns with classes like `\w` or `\s`.
+   * Only `A`-`Z` and `a`-`z` fold, so `k` no longer matches the Kelvin
+   * sign, and non-ASCII letters must match exactly. Classes match ASCII
+   * only and `.` matches any byte, as with `bytes(true)`. Turning it off
+   * again leaves `caseInsensitive` and `unicode` as they were set.
+   */
+  asciiCaseInsensitive(yes: boolean): this
+  /**
+   * Apply the matcher defaults of `preset`: smart case for `Ripgrep`,
+   * case-sensitive matching otherwise. Options set afterwards override
+   * them.
+   */
+  preset(preset: Preset): this
   /**
    * Whether to enable "smart case" or not.
    *
@@ -118,7 +561,12 @@ export declare class RegexMatcherBuilder {
   /**
    * Set CRLF mode for line terminators.
    *
-   * When enabled, `$` will match both `\r\n` and `\n`.
+   * When enabled, `^` and `$` treat both `\r\n` and `\n` as line endings
+   * and never match between a `\r` and a `\n`, and reported matches are
+   * widened so they never split a `\r\n` pair. Unlike grep's CRLF mode
+   * this does not set a line terminator, so the matcher works with any
+   * searcher and may match across lines in multi-line searches. Use
+   * `lineTerminator` to keep matches within lines.
    */
   crlf(yes: boolean): this
   /** Require that all matches occur on word boundaries. */
@@ -129,6 +577,51 @@ export declare class RegexMatcherBuilder {
    * When enabled, all regex meta characters are matched literally.
    */
   fixedStrings(yes: boolean): this
+  /**
+   * Whether to report the longest match at each position (POSIX
+   * leftmost-longest) instead of the first alternative that matches.
+   *
+   * For example, `a|ab` matches `ab` in `abc` instead of `a`. Which lines
+   * match is unaffected; only reported match ranges change. Capture groups
+   * used by `replace` keep leftmost-first semantics.
+   */
+  leftmostLongest(yes: boolean): this
+  /**
+   * Require matches to start at the beginning of the haystack.
+   *
+   * Unlike `^`, this is unaffected by `multiLine`. Intended for validating
+   * whole inputs with `isMatch`/`find`; in line-oriented searches use
+   * `wholeLine` instead.
+   */
+  anchoredStart(yes: boolean): this
+  /**
+   * Require matches to span the entire haystack.
+   *
+   * Equivalent to wrapping each pattern in `\A(?:...)\z`, so `isMatch`
+   * answers "does this whole buffer match?" regardless of `multiLine`.
+   * In line-oriented searches use `wholeLine` instead.
+   */
+  fullMatch(yes: boolean): this
+  /**
+   * Normalize text to the given Unicode form before matching.
+   *
+   * Patterns are normalized to the same form when the matcher is built.
+   * Reported offsets and lines refer to the original input; a match that
+   * covers part of a rewritten character sequence widens to the whole
+   * sequence. `scanBytes` and `scanFile` always match raw bytes. Pass
+   * `null` to disable (the default).
+   */
+  normalization(form?: NormalizationForm | undefined | null): this
+  /**
+   * Match letters regardless of diacritics, so `e` matches `é`, `ê` and
+   * `ë`.
+   *
+   * Combining marks are removed from both patterns and text, and a few
+   * letters without a decomposition are transliterated (`ø` to `o`, `ł` to
+   * `l`, `ß` to `ss`, ...). As with `normalization`, offsets refer to the
+   * original input.
+   */
+  diacriticInsensitive(yes: boolean): this
   /**
    * Whether each pattern should match the entire line.
    *
@@ -136,3 +629,14 @@ export declare class RegexMatcherBuilder {
    */
   wholeLine(yes: boolean): this
 }
+
+/**
+ * Highlight the matches of several matchers in a text at once, as ranges
+ * that do not overlap, ordered by start.
+ *
+ * Where matches overlap, the one that takes precedence keeps the
+ * overlapping bytes and the others keep only the bytes no stronger match
+ * covers, so a match may be split into several ranges. Empty matches are
+ * left out. Offsets are in bytes, as with `RegexMatcher.findAll`.
+ */
+export declare function highlight(matchers: Array<RegexMatcher>, text: string | Buffer, options?: HighlightOptions | undefined | null): Array<HighlightRange>
@@ -3,3 +3,9 @@ const binding = require('./index.js')
 
 module.exports.RegexMatcher = binding.RegexMatcher
 module.exports.RegexMatcherBuilder = binding.RegexMatcherBuilder
+module.exports.StreamMatcher = binding.StreamMatcher
+module.exports.NormalizationForm = binding.NormalizationForm
+module.exports.ErrorCode = binding.ErrorCode
+module.exports.Preset = binding.Preset
+module.exports.HighlightPrecedence = binding.HighlightPrecedence
+module.exports.highlight = binding.highlight
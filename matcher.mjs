@@ -1,4 +1,13 @@
 // ESM wrapper for grep-js/matcher
 import binding from './index.js'
 
-export const { RegexMatcher, RegexMatcherBuilder } = binding
+export const {
+  RegexMatcher,
+  RegexMatcherBuilder,
+  StreamMatcher,
+  NormalizationForm,
+  ErrorCode,
+  Preset,
+  HighlightPrecedence,
+  highlight,
+} = binding
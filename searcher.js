@@ -3,5 +3,29 @@ const binding = require('./index.js')
 
 module.exports.Searcher = binding.Searcher
 module.exports.SearcherBuilder = binding.SearcherBuilder
+module.exports.CancellationToken = binding.CancellationToken
+module.exports.DirSearch = binding.DirSearch
+module.exports.FileSearch = binding.FileSearch
+module.exports.StreamSearch = binding.StreamSearch
+module.exports.RecordSearch = binding.RecordSearch
+module.exports.SearchService = binding.SearchService
+module.exports.DocumentSet = binding.DocumentSet
+module.exports.StringPool = binding.StringPool
+module.exports.ResultCursor = binding.ResultCursor
 module.exports.BinaryDetectionMode = binding.BinaryDetectionMode
+module.exports.BomHandling = binding.BomHandling
+module.exports.ConfigConflict = binding.ConfigConflict
 module.exports.ContextKind = binding.ContextKind
+module.exports.EncodingErrorAction = binding.EncodingErrorAction
+module.exports.ErrorCode = binding.ErrorCode
+module.exports.HashAlgorithm = binding.HashAlgorithm
+module.exports.Preset = binding.Preset
+module.exports.ReadErrorAction = binding.ReadErrorAction
+module.exports.ResultLimitAction = binding.ResultLimitAction
+module.exports.SortBy = binding.SortBy
+module.exports.cancelSearch = binding.cancelSearch
+module.exports.mergeResults = binding.mergeResults
+module.exports.searchDir = binding.searchDir
+module.exports.searchDirIter = binding.searchDirIter
+module.exports.searchDirParallel = binding.searchDirParallel
+module.exports.searchMetrics = binding.searchMetrics
@@ -1,4 +1,33 @@
 // ESM wrapper for grep-js/searcher
 import binding from './index.js'
 
-export const { Searcher, SearcherBuilder, BinaryDetectionMode, ContextKind } = binding
+export const {
+  Searcher,
+  SearcherBuilder,
+  CancellationToken,
+  DirSearch,
+  FileSearch,
+  StreamSearch,
+  RecordSearch,
+  SearchService,
+  DocumentSet,
+  StringPool,
+  ResultCursor,
+  BinaryDetectionMode,
+  BomHandling,
+  ConfigConflict,
+  ContextKind,
+  EncodingErrorAction,
+  ErrorCode,
+  HashAlgorithm,
+  Preset,
+  ReadErrorAction,
+  ResultLimitAction,
+  SortBy,
+  cancelSearch,
+  mergeResults,
+  searchDir,
+  searchDirIter,
+  searchDirParallel,
+  searchMetrics,
+} = binding
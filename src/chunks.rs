@@ -0,0 +1,150 @@
+//! Parallel search of one large input.
+//!
+//! The input is split at line boundaries into one chunk per thread. Each
+//! chunk is searched as part of the whole input, with its line number and
+//! byte offset, and extended by the searcher's context lines so that
+//! context is not cut off at chunk boundaries. The results are merged as by
+//! `mergeResults`, which drops the lines reported by two chunks.
+
+use napi::bindgen_prelude::*;
+
+use crate::{
+  merge, sort_matches, transcode, Haystack, RegexMatcher, SearchOptions, SearchOutput, Searcher,
+};
+
+/// Inputs smaller than this are not worth splitting further.
+const MIN_CHUNK: usize = 1 << 20;
+
+/// Search `data` on up to `searcher.config.threads()` threads, or on the
+/// calling thread when its chunks could not be searched on their own.
+pub(crate) fn search(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  data: &[u8],
+) -> Result<SearchOutput> {
+  let threads = searcher.config.threads().min(data.len() / MIN_CHUNK).max(1);
+  if threads == 1 || !splittable(searcher, matcher, data) {
+    return searcher.search(matcher, Haystack::Slice(data), SearchOptions::default());
+  }
+  let terminator = searcher.inner.line_terminator().as_byte();
+  let before = searcher.inner.before_context();
+  let after = searcher.inner.after_context();
+  let mut bounds = vec![0];
+  for i in 1..threads {
+    let at = (data.len() / threads * i).max(*bounds.last().unwrap());
+    let end = line_end(data, at, terminator);
+    if end < data.len() && end > *bounds.last().unwrap() {
+      bounds.push(end);
+    }
+  }
+  bounds.push(data.len());
+  let chunks: Vec<(usize, usize)> = bounds.windows(2).map(|w| (w[0], w[1])).collect();
+  let count = |bytes: &[u8]| bytes.iter().filter(|&&b| b == terminator).count();
+  // The line number of the start of each chunk, from the lines in each.
+  let lines = parallel(chunks.clone(), |(start, end)| Ok(count(&data[start..end])))?;
+  let first_lines: Vec<usize> = lines
+    .iter()
+    .scan(1, |line, n| {
+      let first = *line;
+      *line += n;
+      Some(first)
+    })
+    .collect();
+  let parts: Vec<_> = chunks
+    .into_iter()
+    .zip(first_lines)
+    .map(|(chunk, line)| (chunk, line, searcher.clone()))
+    .collect();
+  let outputs = parallel(parts, |((start, end), line, searcher)| {
+    let from = lines_back(data, start, before, terminator);
+    let to = lines_forward(data, end, after, terminator);
+    let options = SearchOptions {
+      first_line_number: Some((line - count(&data[from..start])) as u32),
+      base_offset: Some(from as i64),
+      ..Default::default()
+    };
+    searcher.search(matcher, Haystack::Slice(&data[from..to]), options)
+  })?;
+  let mut result = merge(outputs.into_iter().map(|o| o.result).collect());
+  if let Some(order) = searcher.config.sort_by {
+    sort_matches(&mut result.matches, order);
+  }
+  Ok(SearchOutput {
+    result,
+    plain: searcher.config.plain,
+  })
+}
+
+/// Run `f` on every item on its own thread.
+fn parallel<T: Send, R: Send>(items: Vec<T>, f: impl Fn(T) -> Result<R> + Sync) -> Result<Vec<R>> {
+  std::thread::scope(|scope| {
+    let handles: Vec<_> = items
+      .into_iter()
+      .map(|item| {
+        let f = &f;
+        scope.spawn(move || f(item))
+      })
+      .collect();
+    handles
+      .into_iter()
+      .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+      .collect()
+  })
+}
+
+/// Whether searching the chunks of `data` separately gives the same result
+/// as searching it whole. Matches that span lines, limits that depend on
+/// what came before, binary detection, transcoding and whole-input reports
+/// such as hashes all need the input in one piece.
+fn splittable(searcher: &Searcher, matcher: &RegexMatcher, data: &[u8]) -> bool {
+  let inner = &searcher.inner;
+  let config = &searcher.config;
+  !inner.multi_line()
+    && !inner.stop_on_nonmatch()
+    && inner.max_matches().is_none()
+    && inner.binary_detection().quit_byte().is_none()
+    && inner.binary_detection().convert_byte().is_none()
+    && config.max_matches.is_none()
+    && config.max_match_count.is_none()
+    && config.max_result_bytes.is_none()
+    && config.after_context_until.is_none()
+    && !config.byte_context()
+    && !config.line_endings
+    && config.content_hash.is_none()
+    && config.timeout.is_none()
+    && matcher.inner.fold().is_none()
+    && !transcode::has_bom(data)
+}
+
+/// The offset just past the line terminator at or after `at`.
+fn line_end(data: &[u8], at: usize, terminator: u8) -> usize {
+  data[at..]
+    .iter()
+    .position(|&b| b == terminator)
+    .map_or(data.len(), |i| at + i + 1)
+}
+
+/// The start of the line `n` lines before the line starting at `start`.
+fn lines_back(data: &[u8], mut start: usize, n: usize, terminator: u8) -> usize {
+  for _ in 0..n {
+    if start == 0 {
+      break;
+    }
+    start = data[..start - 1]
+      .iter()
+      .rposition(|&b| b == terminator)
+      .map_or(0, |i| i + 1);
+  }
+  start
+}
+
+/// The end of the line `n` lines after the line ending at `end`.
+fn lines_forward(data: &[u8], mut end: usize, n: usize, terminator: u8) -> usize {
+  for _ in 0..n {
+    if end == data.len() {
+      break;
+    }
+    end = line_end(data, end, terminator);
+  }
+  end
+}
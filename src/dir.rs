@@ -0,0 +1,355 @@
+//! Directory search on a background thread.
+//!
+//! A walker thread searches the files below a directory one by one and
+//! queues every file with matches (see `iter`). `collect` walks the same
+//! files on the calling thread and returns them all at once, and
+//! `spawn_parallel` walks and searches them on many threads, passing files
+//! to a callback as they are found. Directories are walked by the `ignore`
+//! crate, filtering files as ripgrep does.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+  ThreadsafeFunction, ThreadsafeFunctionCallMode, UnknownReturnValue,
+};
+
+use crate::error::Failure;
+use crate::iter::{self, Queue};
+use crate::stream::{Pending, Slot};
+use crate::{
+  sort_files, CollectSink, DirSearchEntry, DirSearchFinish, DirSearchOptions, ErrorCode,
+  FileSearchResult, Haystack, ReadErrorAction, RegexMatcher, SearchOptions, SearchOutput, Searcher,
+};
+
+/// A callback that receives files with matches. Its own queue is unbounded;
+/// the search bounds it with `Pending`.
+pub type FileCallback =
+  ThreadsafeFunction<DirSearchEntry, UnknownReturnValue, DirSearchEntry, Status, false, false>;
+
+/// A callback that receives the outcome of a parallel directory search.
+pub type FinishCallback =
+  ThreadsafeFunction<DirSearchFinish, UnknownReturnValue, DirSearchFinish, ErrorCode, true>;
+
+/// A walker of the files below `dir` in a stable order, filtered as
+/// `options` ask.
+pub(crate) fn walker(dir: &str, options: &DirSearchOptions) -> Result<WalkBuilder> {
+  let ignore_files = options.ignore_files.unwrap_or(true);
+  let source = |enabled: Option<bool>| enabled.unwrap_or(ignore_files);
+  let mut builder = WalkBuilder::new(dir);
+  builder
+    .hidden(!options.hidden.unwrap_or(false))
+    .ignore(source(options.dot_ignore))
+    .parents(source(options.parents))
+    .git_ignore(source(options.git_ignore))
+    .git_global(source(options.git_global))
+    .git_exclude(source(options.git_exclude))
+    .require_git(options.require_git.unwrap_or(true))
+    .follow_links(options.follow_links.unwrap_or(false))
+    .max_depth(options.max_depth.map(|depth| depth as usize))
+    .max_filesize(options.max_filesize.map(|size| size.max(0) as u64))
+    .sort_by_file_name(|a, b| a.cmp(b));
+  if source(options.dot_ignore) {
+    for name in options.custom_ignore_filenames.iter().flatten() {
+      builder.add_custom_ignore_filename(name);
+    }
+  }
+  let invalid = |e: ignore::Error| Error::new(Status::InvalidArg, e.to_string());
+  if options.types.is_some() || options.types_not.is_some() {
+    let mut types = TypesBuilder::new();
+    types.add_defaults();
+    for name in options.types.iter().flatten() {
+      types.select(name);
+    }
+    for name in options.types_not.iter().flatten() {
+      types.negate(name);
+    }
+    builder.types(types.build().map_err(invalid)?);
+  }
+  if let Some(globs) = &options.globs {
+    let mut overrides = OverrideBuilder::new(dir);
+    for glob in globs {
+      overrides.add(glob).map_err(invalid)?;
+    }
+    builder.overrides(overrides.build().map_err(invalid)?);
+  }
+  Ok(builder)
+}
+
+/// Every file below `path`, or `path` itself if it is a file, in a stable
+/// order and with nothing filtered out, with the error walking to it if
+/// any. Symbolic links are followed, except those to one of their own
+/// ancestors.
+pub(crate) fn files(path: &str) -> Result<Vec<(PathBuf, Option<std::io::Error>)>> {
+  let options = DirSearchOptions {
+    hidden: Some(true),
+    ignore_files: Some(false),
+    follow_links: Some(true),
+    ..Default::default()
+  };
+  let mut files = Vec::new();
+  walk(
+    Path::new(path),
+    &walker(path, &options)?,
+    &mut |file, error| {
+      files.push((file.to_path_buf(), error));
+      true
+    },
+  );
+  Ok(files)
+}
+
+/// Start searching the files of `walker` with `searcher` and `matcher`,
+/// buffering at most `queue_size` files with matches.
+pub(crate) fn spawn(
+  searcher: Searcher,
+  matcher: RegexMatcher,
+  dir: String,
+  walker: WalkBuilder,
+  queue_size: usize,
+) -> Queue<DirSearchEntry> {
+  let (sender, queue) = iter::channel(queue_size);
+  std::thread::spawn(move || {
+    walk(Path::new(&dir), &walker, &mut |path, error| {
+      visit(&searcher, &matcher, &sender, path, error)
+    });
+  });
+  queue
+}
+
+/// Search the files of `walker`, below `dir`, returning every file with
+/// matches, and every file that could not be read when the read error
+/// policy skips them.
+pub(crate) fn collect(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  dir: &str,
+  walker: &WalkBuilder,
+) -> std::result::Result<Vec<FileSearchResult>, Failure> {
+  let mut files = Vec::new();
+  let mut failed = None;
+  walk(Path::new(dir), walker, &mut |path, error| {
+    let display = path.to_string_lossy().into_owned();
+    match search_file(searcher, matcher, &display, error) {
+      Ok(output) if output.result.matches.is_empty() => {}
+      Ok(output) => files.push(FileSearchResult::new(display, output, None)),
+      Err(e) if skips_unreadable(searcher) => {
+        let output = SearchOutput {
+          result: CollectSink::new(matcher.inner.clone(), &searcher.config).into_result(),
+          plain: searcher.config.plain,
+        };
+        files.push(FileSearchResult::new(display, output, Some(e)));
+      }
+      Err(e) => {
+        failed = Some(Failure::file(&display, e));
+        return false;
+      }
+    }
+    true
+  });
+  if let Some(e) = failed {
+    return Err(e);
+  }
+  if let Some(order) = searcher.config.sort_by {
+    sort_files(&mut files, order);
+  }
+  Ok(files)
+}
+
+/// Search the files of `walker`, below `dir`, on the walker's threads, each
+/// with its own copy of `searcher`, passing each file with matches to
+/// `on_file` and the outcome to `on_finish`. The search waits while
+/// `queue_size` calls are waiting for the JavaScript thread.
+pub(crate) fn spawn_parallel(
+  searcher: Searcher,
+  matcher: RegexMatcher,
+  dir: String,
+  walker: WalkBuilder,
+  queue_size: usize,
+  on_file: FileCallback,
+  on_finish: Option<FinishCallback>,
+) {
+  std::thread::spawn(move || {
+    let pending = Arc::new(Pending::default());
+    let failed = Mutex::new(None);
+    let files_searched = AtomicU32::new(0);
+    let files_with_matches = AtomicU32::new(0);
+    walker.build_parallel().run(|| {
+      let searcher = searcher.clone();
+      let matcher = RegexMatcher {
+        inner: matcher.inner.clone(),
+      };
+      let (pending, failed, on_file) = (&pending, &failed, &on_file);
+      let (files_searched, files_with_matches) = (&files_searched, &files_with_matches);
+      let dir = Path::new(&dir);
+      Box::new(move |entry| {
+        let (path, error) = match entry {
+          Ok(entry) if is_dir(&entry) => return WalkState::Continue,
+          Ok(entry) => (entry.into_path(), None),
+          Err(e) if is_loop(&e) => return WalkState::Continue,
+          Err(e) => {
+            let (path, error) = walk_error(dir, e);
+            (path, Some(error))
+          }
+        };
+        let display = path.to_string_lossy().into_owned();
+        let output = match search_file(&searcher, &matcher, &display, error) {
+          Ok(output) => output,
+          Err(_) if skips_unreadable(&searcher) => return WalkState::Continue,
+          Err(e) => {
+            failed
+              .lock()
+              .unwrap()
+              .get_or_insert_with(|| Failure::file(&display, e));
+            return WalkState::Quit;
+          }
+        };
+        files_searched.fetch_add(1, Ordering::Relaxed);
+        if output.result.matches.is_empty() {
+          return WalkState::Continue;
+        }
+        files_with_matches.fetch_add(1, Ordering::Relaxed);
+        pending.wait(queue_size.max(1) - 1);
+        let slot = Slot::new(pending);
+        let entry = DirSearchEntry {
+          path: display,
+          result: output,
+        };
+        let mode = ThreadsafeFunctionCallMode::Blocking;
+        let status = on_file.call_with_return_value(entry, mode, move |_, _| {
+          drop(slot);
+          Ok(())
+        });
+        // Anything but Ok means the environment is shutting down.
+        match status {
+          Status::Ok => WalkState::Continue,
+          _ => WalkState::Quit,
+        }
+      })
+    });
+    pending.wait(0);
+    if let Some(on_finish) = on_finish {
+      let result = match failed.into_inner().unwrap() {
+        Some(failure) => Err(failure.into_callback_error()),
+        None => Ok(DirSearchFinish {
+          files_searched: files_searched.into_inner(),
+          files_with_matches: files_with_matches.into_inner(),
+        }),
+      };
+      on_finish.call(result, ThreadsafeFunctionCallMode::Blocking);
+    }
+  });
+}
+
+/// Search the file at `path`, or report `error`, the error reaching it.
+fn search_file(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  path: &str,
+  error: Option<std::io::Error>,
+) -> Result<SearchOutput> {
+  match error {
+    Some(e) => Err(Error::new(Status::GenericFailure, e.to_string())),
+    None => searcher.search(matcher, Haystack::Path(path), SearchOptions::default()),
+  }
+}
+
+/// Whether files that cannot be read are skipped rather than ending the
+/// search. A cancelled search always ends.
+fn skips_unreadable(searcher: &Searcher) -> bool {
+  matches!(
+    searcher.config.read_error_policy.action,
+    Some(ReadErrorAction::Skip)
+  ) && !searcher.config.cancelled()
+}
+
+/// Search one file, or report the error reading it. Returns whether the
+/// walk should continue.
+fn visit(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  sender: &iter::Sender<DirSearchEntry>,
+  path: &Path,
+  error: Option<std::io::Error>,
+) -> bool {
+  let display = path.to_string_lossy().into_owned();
+  let entry = match search_file(searcher, matcher, &display, error) {
+    Ok(output) if output.result.matches.is_empty() => return true,
+    Ok(output) => Ok(DirSearchEntry {
+      path: display,
+      result: output,
+    }),
+    Err(_) if skips_unreadable(searcher) => return true,
+    Err(e) => Err(Failure::file(&display, e)),
+  };
+  let last = entry.is_err();
+  sender.send(entry).is_ok() && !last
+}
+
+/// Visit the files of `walker`, below `dir`, until `visit` returns false.
+/// Paths that cannot be walked are passed to `visit` with their error, but
+/// symbolic link loops are skipped.
+fn walk(
+  dir: &Path,
+  walker: &WalkBuilder,
+  visit: &mut dyn FnMut(&Path, Option<std::io::Error>) -> bool,
+) {
+  for entry in walker.build() {
+    let more = match entry {
+      Ok(entry) if is_dir(&entry) => true,
+      Ok(entry) => visit(entry.path(), None),
+      Err(e) if is_loop(&e) => true,
+      Err(e) => {
+        let (path, error) = walk_error(dir, e);
+        visit(&path, Some(error))
+      }
+    };
+    if !more {
+      break;
+    }
+  }
+}
+
+/// The path a walk error is about, defaulting to `dir`, and the error.
+fn walk_error(dir: &Path, error: ignore::Error) -> (PathBuf, std::io::Error) {
+  let path = error_path(&error).unwrap_or(dir).to_path_buf();
+  let message = error.to_string();
+  let error = error
+    .into_io_error()
+    .unwrap_or_else(|| std::io::Error::other(message));
+  (path, error)
+}
+
+/// The path a walk error is about, if it has one.
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+  match error {
+    ignore::Error::WithPath { path, .. } => Some(path),
+    ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+      error_path(err)
+    }
+    _ => None,
+  }
+}
+
+/// Whether an entry is a directory, or a symbolic link to one that was not
+/// followed, as ripgrep skips both.
+fn is_dir(entry: &DirEntry) -> bool {
+  entry.file_type().is_some_and(|t| t.is_dir()) || entry.path_is_symlink() && entry.path().is_dir()
+}
+
+/// Whether a walk error is a symbolic link to one of its own ancestors. The
+/// ancestor's files are searched anyway, so the link is skipped.
+fn is_loop(error: &ignore::Error) -> bool {
+  match error {
+    ignore::Error::Loop { .. } => true,
+    ignore::Error::WithPath { err, .. }
+    | ignore::Error::WithDepth { err, .. }
+    | ignore::Error::WithLineNumber { err, .. } => is_loop(err),
+    _ => false,
+  }
+}
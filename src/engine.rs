@@ -0,0 +1,687 @@
+//! The matcher behind `RegexMatcher`.
+//!
+//! `Engine` wraps grep's regex matcher and implements the same `Matcher`
+//! trait, so searchers and sinks can use it transparently. It exists to add
+//! matching semantics that grep-regex does not offer itself, such as
+//! leftmost-longest match reporting.
+
+use grep::matcher::{ByteSet, LineMatchKind, LineTerminator, Match, Matcher, NoError};
+use grep::regex::{RegexCaptures, RegexMatcher as GrepRegexMatcher};
+use regex_automata::meta::Regex;
+use regex_automata::{Anchored, Input, MatchKind};
+use regex_syntax::ast::{self, Ast};
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+use regex_syntax::hir::{self, Hir};
+
+use crate::normalize::Fold;
+use crate::NormalizationForm;
+
+/// The options set on a `RegexMatcherBuilder` that affect how patterns are
+/// interpreted.
+///
+/// grep's builder does not expose its configuration, so we mirror it here to
+/// build auxiliary regexes with the same semantics as the grep matcher.
+#[derive(Clone)]
+pub(crate) struct PatternOptions {
+  pub case_insensitive: bool,
+  pub case_smart: bool,
+  pub multi_line: bool,
+  pub dot_matches_new_line: bool,
+  pub swap_greed: bool,
+  pub ignore_whitespace: bool,
+  pub unicode: bool,
+  pub octal: bool,
+  pub nest_limit: u32,
+  pub line_terminator: Option<u8>,
+  pub crlf: bool,
+  pub word: bool,
+  pub fixed_strings: bool,
+  pub whole_line: bool,
+  pub leftmost_longest: bool,
+  pub anchored_start: bool,
+  pub full_match: bool,
+  pub normalization: Option<NormalizationForm>,
+  pub diacritic_insensitive: bool,
+}
+
+impl Default for PatternOptions {
+  fn default() -> Self {
+    Self {
+      case_insensitive: false,
+      case_smart: false,
+      multi_line: false,
+      dot_matches_new_line: false,
+      swap_greed: false,
+      ignore_whitespace: false,
+      unicode: true,
+      octal: false,
+      nest_limit: 250,
+      line_terminator: None,
+      crlf: false,
+      word: false,
+      fixed_strings: false,
+      whole_line: false,
+      leftmost_longest: false,
+      anchored_start: false,
+      full_match: false,
+      normalization: None,
+      diacritic_insensitive: false,
+    }
+  }
+}
+
+impl PatternOptions {
+  /// The fold applied to patterns and haystacks, if any.
+  pub(crate) fn fold(&self) -> Option<Fold> {
+    if self.normalization.is_none() && !self.diacritic_insensitive {
+      return None;
+    }
+    Some(Fold {
+      form: self.normalization,
+      diacritics: self.diacritic_insensitive,
+    })
+  }
+
+  /// Fold the literals of `pattern` so that it matches text folded by
+  /// `fold`. Folding the whole source instead would turn characters such
+  /// as `＋` into metacharacters. A pattern that does not parse is left for
+  /// the matcher to report.
+  pub(crate) fn fold_pattern(&self, pattern: &str, fold: Fold) -> String {
+    if self.fixed_strings {
+      return fold.apply(pattern);
+    }
+    let Ok(ast) = self.parse(pattern) else {
+      return pattern.to_string();
+    };
+    let mut edits = Vec::new();
+    fold_literals(&ast, fold, &mut edits);
+    let mut folded = pattern.to_string();
+    for (span, replacement) in edits.into_iter().rev() {
+      folded.replace_range(span, &replacement);
+    }
+    folded
+  }
+
+  /// Join `patterns` into the alternation grep-regex compiles.
+  fn join<P: AsRef<str>>(&self, patterns: &[P]) -> String {
+    patterns
+      .iter()
+      .map(|p| {
+        if self.fixed_strings {
+          format!("(?:{})", regex_syntax::escape(p.as_ref()))
+        } else {
+          format!("(?:{})", p.as_ref())
+        }
+      })
+      .collect::<Vec<_>>()
+      .join("|")
+  }
+
+  fn parse(&self, pattern: &str) -> std::result::Result<Ast, String> {
+    ast::parse::ParserBuilder::new()
+      .nest_limit(self.nest_limit)
+      .octal(self.octal)
+      .ignore_whitespace(self.ignore_whitespace)
+      .build()
+      .parse(pattern)
+      .map_err(|e| e.to_string())
+  }
+
+  /// Translate patterns into HIR the same way grep-regex does: the patterns
+  /// are joined into one alternation, smart case is decided over the whole
+  /// alternation and `wholeLine`/`word` wrap the result.
+  fn to_hir<P: AsRef<str>>(&self, patterns: &[P]) -> std::result::Result<Hir, String> {
+    let pattern = self.join(patterns);
+    let ast = self.parse(&pattern)?;
+    let case_insensitive = self.ignores_case(&ast);
+    let hir = hir::translate::TranslatorBuilder::new()
+      .utf8(false)
+      .case_insensitive(case_insensitive)
+      .multi_line(self.multi_line)
+      .dot_matches_new_line(self.dot_matches_new_line)
+      .crlf(self.crlf)
+      .swap_greed(self.swap_greed)
+      .unicode(self.unicode)
+      .build()
+      .translate(&pattern, &ast)
+      .map_err(|e| e.to_string())?;
+    Ok(if self.whole_line {
+      let (start, end) = if self.crlf {
+        (hir::Look::StartCRLF, hir::Look::EndCRLF)
+      } else {
+        (hir::Look::StartLF, hir::Look::EndLF)
+      };
+      Hir::concat(vec![Hir::look(start), hir, Hir::look(end)])
+    } else if self.word {
+      let (start, end) = if self.unicode {
+        (
+          hir::Look::WordStartHalfUnicode,
+          hir::Look::WordEndHalfUnicode,
+        )
+      } else {
+        (hir::Look::WordStartHalfAscii, hir::Look::WordEndHalfAscii)
+      };
+      Hir::concat(vec![Hir::look(start), hir, Hir::look(end)])
+    } else {
+      hir
+    })
+  }
+
+  /// The literals every match of `patterns` starts with, if there is a
+  /// small set of them and none is empty.
+  fn prefixes<P: AsRef<str>>(&self, patterns: &[P]) -> Option<Vec<Vec<u8>>> {
+    let hir = self.to_hir(patterns).ok()?;
+    let seq = Extractor::new().kind(ExtractKind::Prefix).extract(&hir);
+    let literals = seq.literals()?;
+    if literals.is_empty() || literals.iter().any(|l| l.as_bytes().is_empty()) {
+      return None;
+    }
+    Some(literals.iter().map(|l| l.as_bytes().to_vec()).collect())
+  }
+
+  /// The capture groups of `patterns`, in index order. Fixed strings have
+  /// none.
+  fn groups<P: AsRef<str>>(&self, patterns: &[P]) -> Vec<Group> {
+    let Ok(ast) = self.parse(&self.join(patterns)) else {
+      return Vec::new();
+    };
+    let mut groups = Vec::new();
+    match &ast {
+      // Each pattern is one branch of the alternation.
+      Ast::Alternation(x) if x.asts.len() == patterns.len() => {
+        for (pattern, ast) in x.asts.iter().enumerate() {
+          collect_groups(ast, pattern, None, &mut groups);
+        }
+      }
+      ast => collect_groups(ast, 0, None, &mut groups),
+    }
+    groups
+  }
+
+  /// Whether the patterns parsed into `ast` match regardless of case.
+  fn ignores_case(&self, ast: &Ast) -> bool {
+    self.case_insensitive || (self.case_smart && is_lowercase_only(ast))
+  }
+
+  /// The literals a match of `patterns` requires (see `Required`). They
+  /// are extracted with case folding turned off, and the flag says whether
+  /// the matcher folds case. Folded patterns require literals in folded
+  /// form, which say nothing about the original text.
+  fn required<P: AsRef<str>>(&self, patterns: &[P]) -> Option<Required> {
+    if self.fold().is_some() {
+      return None;
+    }
+    let case_insensitive = self.ignores_case(&self.parse(&self.join(patterns)).ok()?);
+    let mut exact = self.clone();
+    exact.case_insensitive = false;
+    exact.case_smart = false;
+    let any_of = required_literals(&exact.to_hir(patterns).ok()?)?;
+    Some(Required {
+      any_of,
+      case_insensitive,
+    })
+  }
+
+  /// Build the regex used to extend matches to their longest form.
+  fn longest_regex<P: AsRef<str>>(&self, patterns: &[P]) -> std::result::Result<Regex, String> {
+    let hir = self.to_hir(patterns)?;
+    Regex::builder()
+      .configure(Regex::config().match_kind(MatchKind::All).utf8_empty(false))
+      .build_from_hir(&hir)
+      .map_err(|e| e.to_string())
+  }
+}
+
+/// The most alternatives `Required` lists before giving up.
+const MAX_ALTERNATIVES: usize = 32;
+
+/// Literals every match contains: a text can only match if it contains all
+/// literals of at least one of the alternatives.
+pub(crate) struct Required {
+  pub any_of: Vec<Vec<Vec<u8>>>,
+  pub case_insensitive: bool,
+}
+
+/// The alternatives of literals every match of `hir` contains, or `None`
+/// when a match requires no literal.
+fn required_literals(hir: &Hir) -> Option<Vec<Vec<Vec<u8>>>> {
+  match hir.kind() {
+    hir::HirKind::Literal(lit) => Some(vec![vec![lit.0.to_vec()]]),
+    hir::HirKind::Class(class) => {
+      class_literals(class).map(|lits| lits.into_iter().map(|l| vec![l]).collect())
+    }
+    hir::HirKind::Capture(cap) => required_literals(&cap.sub),
+    hir::HirKind::Repetition(rep) if rep.min > 0 => required_literals(&rep.sub),
+    hir::HirKind::Concat(subs) => {
+      // Every part is required. Keep the product of the parts while it is
+      // small, and otherwise the most selective part.
+      let parts: Vec<_> = subs.iter().filter_map(required_literals).collect();
+      let mut all: Vec<Vec<Vec<u8>>> = vec![Vec::new()];
+      for part in &parts {
+        if all.len() * part.len() > MAX_ALTERNATIVES {
+          return parts.into_iter().max_by_key(|p| selectivity(p));
+        }
+        all = all
+          .iter()
+          .flat_map(|a| part.iter().map(move |b| [a.clone(), b.clone()].concat()))
+          .collect();
+      }
+      (!parts.is_empty()).then_some(all)
+    }
+    hir::HirKind::Alternation(subs) => {
+      let mut all = Vec::new();
+      for sub in subs {
+        all.extend(required_literals(sub)?);
+        if all.len() > MAX_ALTERNATIVES {
+          return None;
+        }
+      }
+      Some(all)
+    }
+    _ => None,
+  }
+}
+
+/// The members of a class of a few characters, as literals.
+fn class_literals(class: &hir::Class) -> Option<Vec<Vec<u8>>> {
+  const MAX_MEMBERS: u32 = 4;
+  match class {
+    hir::Class::Unicode(class) => {
+      let size: u32 = class
+        .iter()
+        .map(|r| r.end() as u32 - r.start() as u32 + 1)
+        .sum();
+      (size <= MAX_MEMBERS).then(|| {
+        class
+          .iter()
+          .flat_map(|r| r.start()..=r.end())
+          .map(|c| c.to_string().into_bytes())
+          .collect()
+      })
+    }
+    hir::Class::Bytes(class) => {
+      let size: u32 = class
+        .iter()
+        .map(|r| r.end() as u32 - r.start() as u32 + 1)
+        .sum();
+      (size <= MAX_MEMBERS).then(|| {
+        class
+          .iter()
+          .flat_map(|r| r.start()..=r.end())
+          .map(|b| vec![b])
+          .collect()
+      })
+    }
+  }
+}
+
+/// How much the alternatives narrow down texts: the length of the shortest
+/// of the longest literals of each alternative.
+fn selectivity(alternatives: &[Vec<Vec<u8>>]) -> usize {
+  alternatives
+    .iter()
+    .map(|a| a.iter().map(|l| l.len()).max().unwrap_or(0))
+    .min()
+    .unwrap_or(0)
+}
+
+/// A capture group of the patterns of a matcher.
+pub(crate) struct Group {
+  /// The index of the group; 0 is the whole match.
+  pub index: usize,
+  pub name: Option<String>,
+  /// The index of the innermost group containing this one.
+  pub parent: Option<usize>,
+  /// The position of the pattern the group is in.
+  pub pattern: usize,
+}
+
+fn collect_groups(ast: &Ast, pattern: usize, parent: Option<usize>, groups: &mut Vec<Group>) {
+  match ast {
+    Ast::Group(x) => {
+      let (index, name) = match &x.kind {
+        ast::GroupKind::CaptureIndex(index) => (*index as usize, None),
+        ast::GroupKind::CaptureName { name, .. } => (name.index as usize, Some(name.name.clone())),
+        ast::GroupKind::NonCapturing(_) => return collect_groups(&x.ast, pattern, parent, groups),
+      };
+      groups.push(Group {
+        index,
+        name,
+        parent,
+        pattern,
+      });
+      collect_groups(&x.ast, pattern, Some(index), groups);
+    }
+    Ast::Repetition(x) => collect_groups(&x.ast, pattern, parent, groups),
+    Ast::Alternation(x) => x
+      .asts
+      .iter()
+      .for_each(|a| collect_groups(a, pattern, parent, groups)),
+    Ast::Concat(x) => x
+      .asts
+      .iter()
+      .for_each(|a| collect_groups(a, pattern, parent, groups)),
+    _ => {}
+  }
+}
+
+/// Smart case: true if the pattern has at least one literal and none of its
+/// literals are uppercase. Mirrors grep-regex's analysis.
+fn is_lowercase_only(ast: &Ast) -> bool {
+  fn literal(lit: &ast::Literal, any: &mut bool, upper: &mut bool) {
+    *any = true;
+    *upper |= lit.c.is_uppercase();
+  }
+  fn class(set: &ast::ClassSet, any: &mut bool, upper: &mut bool) {
+    match set {
+      ast::ClassSet::Item(item) => class_item(item, any, upper),
+      ast::ClassSet::BinaryOp(op) => {
+        class(&op.lhs, any, upper);
+        class(&op.rhs, any, upper);
+      }
+    }
+  }
+  fn class_item(item: &ast::ClassSetItem, any: &mut bool, upper: &mut bool) {
+    match item {
+      ast::ClassSetItem::Literal(x) => literal(x, any, upper),
+      ast::ClassSetItem::Range(x) => {
+        literal(&x.start, any, upper);
+        literal(&x.end, any, upper);
+      }
+      ast::ClassSetItem::Bracketed(x) => class(&x.kind, any, upper),
+      ast::ClassSetItem::Union(x) => {
+        for item in &x.items {
+          class_item(item, any, upper);
+        }
+      }
+      _ => {}
+    }
+  }
+  fn walk(ast: &Ast, any: &mut bool, upper: &mut bool) {
+    match ast {
+      Ast::Literal(x) => literal(x, any, upper),
+      Ast::ClassBracketed(x) => class(&x.kind, any, upper),
+      Ast::Repetition(x) => walk(&x.ast, any, upper),
+      Ast::Group(x) => walk(&x.ast, any, upper),
+      Ast::Alternation(x) => x.asts.iter().for_each(|a| walk(a, any, upper)),
+      Ast::Concat(x) => x.asts.iter().for_each(|a| walk(a, any, upper)),
+      _ => {}
+    }
+  }
+  let (mut any, mut upper) = (false, false);
+  walk(ast, &mut any, &mut upper);
+  any && !upper
+}
+
+/// The byte range of a pattern's source and the text that replaces it.
+type Edit = (std::ops::Range<usize>, String);
+
+/// Collect the literals of `ast` that `fold` rewrites, ordered by position,
+/// with the escaped folded text that replaces them. Adjacent literals are
+/// folded together, so that a letter and a combining mark written apart
+/// fold as one character.
+fn fold_literals(ast: &Ast, fold: Fold, edits: &mut Vec<Edit>) {
+  fn run(literals: &[&ast::Literal], fold: Fold, edits: &mut Vec<Edit>) {
+    let (Some(first), Some(last)) = (literals.first(), literals.last()) else {
+      return;
+    };
+    let text: String = literals.iter().map(|l| l.c).collect();
+    let folded = fold.apply(&text);
+    if folded != text {
+      let escaped = escape_folded(&folded);
+      let replacement = match folded.chars().count() {
+        1 => escaped,
+        _ => format!("(?:{})", escaped),
+      };
+      edits.push((first.span.start.offset..last.span.end.offset, replacement));
+    }
+  }
+  fn class(set: &ast::ClassSet, fold: Fold, edits: &mut Vec<Edit>) {
+    match set {
+      ast::ClassSet::Item(item) => class_item(item, fold, edits),
+      ast::ClassSet::BinaryOp(op) => {
+        class(&op.lhs, fold, edits);
+        class(&op.rhs, fold, edits);
+      }
+    }
+  }
+  // A class can only hold literals that fold to a single character.
+  fn class_item(item: &ast::ClassSetItem, fold: Fold, edits: &mut Vec<Edit>) {
+    match item {
+      ast::ClassSetItem::Literal(x) => {
+        let folded = fold.apply(x.c.encode_utf8(&mut [0; 4]));
+        let mut chars = folded.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+          if c != x.c {
+            edits.push((
+              x.span.start.offset..x.span.end.offset,
+              escape_folded(&folded),
+            ));
+          }
+        }
+      }
+      ast::ClassSetItem::Bracketed(x) => class(&x.kind, fold, edits),
+      ast::ClassSetItem::Union(x) => x
+        .items
+        .iter()
+        .for_each(|item| class_item(item, fold, edits)),
+      _ => {}
+    }
+  }
+  match ast {
+    Ast::Literal(x) => run(&[x], fold, edits),
+    Ast::ClassBracketed(x) => class(&x.kind, fold, edits),
+    Ast::Repetition(x) => fold_literals(&x.ast, fold, edits),
+    Ast::Group(x) => fold_literals(&x.ast, fold, edits),
+    Ast::Alternation(x) => x.asts.iter().for_each(|a| fold_literals(a, fold, edits)),
+    Ast::Concat(x) => {
+      let mut literals = Vec::new();
+      for a in &x.asts {
+        match a {
+          Ast::Literal(x) => literals.push(&**x),
+          _ => {
+            run(&literals, fold, edits);
+            literals.clear();
+            fold_literals(a, fold, edits);
+          }
+        }
+      }
+      run(&literals, fold, edits);
+    }
+    _ => {}
+  }
+}
+
+/// Escape folded text for use as literals in a pattern. Whitespace is
+/// written as a code point so that the `x` flag keeps it.
+fn escape_folded(text: &str) -> String {
+  let mut escaped = String::new();
+  for c in text.chars() {
+    if c.is_whitespace() {
+      escaped.push_str(&format!(r"\x{{{:X}}}", c as u32));
+    } else {
+      regex_syntax::escape_into(c.encode_utf8(&mut [0; 4]), &mut escaped);
+    }
+  }
+  escaped
+}
+
+/// A grep regex matcher with optional extended semantics.
+pub(crate) struct Engine {
+  regex: GrepRegexMatcher,
+  /// When set, matches are extended to the longest match starting at the
+  /// same position (POSIX leftmost-longest).
+  longest: Option<Regex>,
+  line_terminator: Option<u8>,
+  /// When set, callers match against text folded this way.
+  fold: Option<Fold>,
+  /// Whether matches are widened so they never split a `\r\n` pair.
+  crlf: bool,
+  /// The literals every match starts with, when known (see `prefixes`).
+  prefixes: Option<Vec<Vec<u8>>>,
+  /// The capture groups of the patterns.
+  groups: Vec<Group>,
+  /// The literals every match contains, when known.
+  required: Option<Required>,
+}
+
+impl Engine {
+  pub(crate) fn new(regex: GrepRegexMatcher) -> Self {
+    Self {
+      regex,
+      longest: None,
+      line_terminator: None,
+      fold: None,
+      crlf: false,
+      prefixes: None,
+      groups: Vec::new(),
+      required: None,
+    }
+  }
+
+  /// Wrap a grep matcher built from `patterns` with `options`, adding the
+  /// extended semantics those options ask for.
+  pub(crate) fn with_options<P: AsRef<str>>(
+    regex: GrepRegexMatcher,
+    patterns: &[P],
+    options: &PatternOptions,
+  ) -> std::result::Result<Self, String> {
+    let mut engine = Self::new(regex);
+    if options.leftmost_longest {
+      engine.longest = Some(options.longest_regex(patterns)?);
+      engine.line_terminator = options.line_terminator;
+    }
+    engine.fold = options.fold();
+    engine.crlf = options.crlf;
+    // Folded haystacks differ from the original bytes, so their literals
+    // say nothing about the input.
+    if engine.fold.is_none() {
+      engine.prefixes = options.prefixes(patterns);
+    }
+    engine.groups = options.groups(patterns);
+    engine.required = options.required(patterns);
+    Ok(engine)
+  }
+
+  /// The fold haystacks must go through before they are searched, if any.
+  pub(crate) fn fold(&self) -> Option<Fold> {
+    self.fold
+  }
+
+  /// The capture groups of the patterns, in index order.
+  pub(crate) fn groups(&self) -> &[Group] {
+    &self.groups
+  }
+
+  /// The literals every match contains, when known.
+  pub(crate) fn required(&self) -> Option<&Required> {
+    self.required.as_ref()
+  }
+
+  /// Whether a haystack containing only the bytes in `present` could
+  /// match. Without known prefixes, any haystack could.
+  pub(crate) fn could_match(&self, present: &ByteSet) -> bool {
+    match &self.prefixes {
+      Some(prefixes) => prefixes
+        .iter()
+        .any(|p| p.iter().all(|&b| present.contains(b))),
+      None => true,
+    }
+  }
+
+  /// Extend `m` to the longest match that starts at the same position.
+  fn extend(&self, haystack: &[u8], m: Match) -> Match {
+    let Some(re) = &self.longest else {
+      return m;
+    };
+    // grep never reports matches containing the line terminator, so neither
+    // may the extension.
+    let end = match self.line_terminator {
+      Some(b) => haystack[m.end()..]
+        .iter()
+        .position(|&c| c == b)
+        .map_or(haystack.len(), |i| m.end() + i),
+      None => haystack.len(),
+    };
+    let input = Input::new(haystack)
+      .range(m.start()..end)
+      .anchored(Anchored::Yes);
+    match re.search(&input) {
+      Some(longest) if longest.end() > m.end() => m.with_end(longest.end()),
+      _ => m,
+    }
+  }
+
+  /// In CRLF mode, widen `m` so that neither end falls between a `\r` and
+  /// the `\n` after it. A start that cannot move back past `at` moves
+  /// forward instead.
+  fn keep_crlf(&self, haystack: &[u8], at: usize, m: Match) -> Match {
+    if !self.crlf {
+      return m;
+    }
+    let splits = |i: usize| i > 0 && haystack[i - 1] == b'\r' && haystack.get(i) == Some(&b'\n');
+    let mut m = m;
+    if splits(m.start()) {
+      m = if m.start() > at {
+        m.with_start(m.start() - 1)
+      } else {
+        Match::new(m.start() + 1, m.end().max(m.start() + 1))
+      };
+    }
+    if splits(m.end()) {
+      m = m.with_end(m.end() + 1);
+    }
+    m
+  }
+}
+
+impl Matcher for Engine {
+  type Captures = RegexCaptures;
+  type Error = NoError;
+
+  fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+    Ok(
+      self
+        .regex
+        .find_at(haystack, at)?
+        .map(|m| self.keep_crlf(haystack, at, self.extend(haystack, m))),
+    )
+  }
+
+  fn new_captures(&self) -> Result<RegexCaptures, NoError> {
+    self.regex.new_captures()
+  }
+
+  fn capture_count(&self) -> usize {
+    self.regex.capture_count()
+  }
+
+  fn capture_index(&self, name: &str) -> Option<usize> {
+    self.regex.capture_index(name)
+  }
+
+  fn captures_at(
+    &self,
+    haystack: &[u8],
+    at: usize,
+    caps: &mut RegexCaptures,
+  ) -> Result<bool, NoError> {
+    self.regex.captures_at(haystack, at, caps)
+  }
+
+  fn shortest_match_at(&self, haystack: &[u8], at: usize) -> Result<Option<usize>, NoError> {
+    self.regex.shortest_match_at(haystack, at)
+  }
+
+  fn non_matching_bytes(&self) -> Option<&ByteSet> {
+    self.regex.non_matching_bytes()
+  }
+
+  fn line_terminator(&self) -> Option<LineTerminator> {
+    self.regex.line_terminator()
+  }
+
+  fn find_candidate_line(&self, haystack: &[u8]) -> Result<Option<LineMatchKind>, NoError> {
+    self.regex.find_candidate_line(haystack)
+  }
+}
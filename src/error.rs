@@ -0,0 +1,173 @@
+//! Codes and details of thrown errors.
+//!
+//! Errors are thrown as plain `Error`s whose `code` is an `ErrorCode`, with
+//! the path or the pattern and span that caused them as properties, so
+//! callers can tell a bad pattern from an unreadable file without parsing
+//! messages. Properties can only be set with the environment at hand, so a
+//! `Failure` is turned into an error where a call returns to JavaScript.
+
+use napi::bindgen_prelude::*;
+
+use crate::{ErrorCode, PatternSpan};
+
+/// The message of searches stopped by a cancellation token or `onTick`.
+pub(crate) const CANCELLED: &str = "search cancelled";
+
+/// The start of the message of searches aborted at undecodable data.
+pub(crate) const UNDECODABLE: &str = "undecodable ";
+
+/// The start of the message of searches whose results exceed
+/// `maxResultBytes`.
+pub(crate) const RESULT_LIMIT: &str = "search results exceed maxResultBytes";
+
+/// The start of grep's message for lines that do not fit in the heap limit.
+const HEAP_LIMIT: &str = "configured allocation limit";
+
+/// The code of an error of reading or searching an input.
+pub(crate) fn code(error: &Error) -> ErrorCode {
+  if error.status == Status::InvalidArg {
+    ErrorCode::InvalidArgument
+  } else if error.reason.ends_with(CANCELLED) {
+    ErrorCode::Cancelled
+  } else if error.reason.starts_with(UNDECODABLE) {
+    ErrorCode::Undecodable
+  } else if error.reason.contains(RESULT_LIMIT) || error.reason.contains(HEAP_LIMIT) {
+    ErrorCode::LimitExceeded
+  } else {
+    ErrorCode::Io
+  }
+}
+
+/// An error with its code and details.
+pub(crate) struct Failure {
+  code: ErrorCode,
+  message: String,
+  path: Option<String>,
+  pattern: Option<(u32, String, Option<PatternSpan>)>,
+}
+
+impl Failure {
+  /// Classify an error of reading or searching `path`, or a slice.
+  pub(crate) fn classify(error: Error, path: Option<&str>) -> Self {
+    Failure {
+      code: code(&error),
+      message: error.reason,
+      path: path.map(str::to_string),
+      pattern: None,
+    }
+  }
+
+  /// Classify an error of reading or searching one of many files, naming
+  /// the file in the message as well.
+  pub(crate) fn file(path: &str, error: Error) -> Self {
+    Failure {
+      code: code(&error),
+      message: format!("{}: {}", path, error.reason),
+      path: Some(path.to_string()),
+      pattern: None,
+    }
+  }
+
+  /// A pattern that failed to compile: the `index`th of those built, with
+  /// the span of the error within it if it could be located.
+  pub(crate) fn pattern(
+    error: Error,
+    index: u32,
+    pattern: &str,
+    span: Option<PatternSpan>,
+  ) -> Self {
+    Failure {
+      code: ErrorCode::RegexSyntax,
+      message: error.reason,
+      path: None,
+      pattern: Some((index, pattern.to_string(), span)),
+    }
+  }
+
+  /// A pattern that failed to compile without being located.
+  pub(crate) fn patterns(error: Error) -> Self {
+    Failure {
+      code: ErrorCode::RegexSyntax,
+      message: error.reason,
+      path: None,
+      pattern: None,
+    }
+  }
+
+  /// The error to pass to a callback from another thread, which has the
+  /// code but none of the other details.
+  pub(crate) fn into_callback_error(self) -> Error<ErrorCode> {
+    Error::new(self.code, self.message)
+  }
+
+  /// The error to throw.
+  pub(crate) fn into_error(self, env: &Env) -> Error {
+    let error = Error::new(Status::GenericFailure, self.message);
+    let mut object = match env.create_error(error) {
+      Ok(object) => object,
+      Err(e) => return e,
+    };
+    let details = (|| {
+      object.set("code", self.code)?;
+      if let Some(path) = self.path {
+        object.set("path", path)?;
+      }
+      if let Some((index, pattern, span)) = self.pattern {
+        object.set("patternIndex", index)?;
+        object.set("pattern", pattern)?;
+        if let Some(span) = span {
+          object.set("span", span)?;
+        }
+      }
+      Ok::<_, Error>(())
+    })();
+    match details {
+      Ok(()) => Error::from(object.to_unknown()),
+      Err(e) => e,
+    }
+  }
+}
+
+impl AsRef<str> for ErrorCode {
+  fn as_ref(&self) -> &str {
+    match self {
+      ErrorCode::RegexSyntax => "RegexSyntax",
+      ErrorCode::Io => "Io",
+      ErrorCode::Undecodable => "Undecodable",
+      ErrorCode::Cancelled => "Cancelled",
+      ErrorCode::InvalidArgument => "InvalidArgument",
+      ErrorCode::LimitExceeded => "LimitExceeded",
+    }
+  }
+}
+
+/// The code of an error napi-rs raises itself, such as a callback that
+/// cannot be called.
+impl From<Status> for ErrorCode {
+  fn from(status: Status) -> Self {
+    match status {
+      Status::InvalidArg => ErrorCode::InvalidArgument,
+      _ => ErrorCode::Io,
+    }
+  }
+}
+
+/// The span of the syntax error in `pattern`, if it does not parse.
+pub(crate) fn syntax_error(pattern: &str, unicode: bool) -> Option<PatternSpan> {
+  let error = regex_syntax::ParserBuilder::new()
+    .unicode(unicode)
+    .utf8(false)
+    .build()
+    .parse(pattern)
+    .err()?;
+  let span = match &error {
+    regex_syntax::Error::Parse(e) => e.span(),
+    regex_syntax::Error::Translate(e) => e.span(),
+    _ => return None,
+  };
+  let utf16 = |offset: usize| pattern[..offset].encode_utf16().count() as u32;
+  Some(PatternSpan {
+    start: utf16(span.start.offset),
+    end: utf16(span.end.offset),
+  })
+}
@@ -0,0 +1,179 @@
+//! Parallel search of a list of files.
+//!
+//! Worker threads take the next file from a shared index when they finish
+//! the previous one and send its result to the coordinating thread, which
+//! passes results on as they arrive or, for ordered results, holds each one
+//! back until all files before it are in. Files are only taken while the
+//! results waiting to be passed on stay within a bound, so a slow file or a
+//! slow consumer does not let results pile up.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+
+use crate::error::Failure;
+use crate::iter::{self, Item, Queue};
+use crate::{
+  sort_files, CollectSink, FileSearchResult, Haystack, ReadErrorAction, RegexMatcher,
+  SearchOptions, SearchOutput, Searcher,
+};
+
+/// How to search a list of files.
+#[derive(Clone, Copy)]
+pub(crate) struct Plan {
+  pub threads: usize,
+  pub ordered: bool,
+}
+
+/// Search `paths` on a background thread, buffering at most `queue_size`
+/// results.
+pub(crate) fn spawn(
+  searcher: Searcher,
+  matcher: RegexMatcher,
+  paths: Vec<String>,
+  plan: Plan,
+  queue_size: usize,
+) -> Queue<FileSearchResult> {
+  // Results wait in `search`, so the queue only hands them over.
+  let (sender, queue) = iter::channel(0);
+  std::thread::spawn(move || {
+    search(&searcher, &matcher, &paths, plan, queue_size, &mut |file| {
+      let last = file.is_err();
+      sender.send(file).is_ok() && !last
+    });
+  });
+  queue
+}
+
+/// Search `paths`, returning the result of every file, or the first error
+/// when the read error policy does not skip unreadable files.
+pub(crate) fn collect(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  paths: &[String],
+  plan: Plan,
+) -> std::result::Result<Vec<FileSearchResult>, Failure> {
+  let mut files = Vec::with_capacity(paths.len());
+  let mut failed = None;
+  search(
+    searcher,
+    matcher,
+    paths,
+    plan,
+    paths.len(),
+    &mut |file| match file {
+      Ok(file) => {
+        files.push(file);
+        true
+      }
+      Err(e) => {
+        failed = Some(e);
+        false
+      }
+    },
+  );
+  if let Some(e) = failed {
+    return Err(e);
+  }
+  if let Some(order) = searcher.config.sort_by {
+    sort_files(&mut files, order);
+  }
+  Ok(files)
+}
+
+/// Search `paths` on `plan.threads` threads, passing each result to
+/// `deliver` until it returns false. At most `buffer` results wait to be
+/// passed on: they are sent through a channel of that size and, for ordered
+/// results, no file is taken more than `buffer` files past the next one due.
+fn search(
+  searcher: &Searcher,
+  matcher: &RegexMatcher,
+  paths: &[String],
+  plan: Plan,
+  buffer: usize,
+  deliver: &mut dyn FnMut(Item<FileSearchResult>) -> bool,
+) {
+  let buffer = buffer.max(1);
+  let next = AtomicUsize::new(0);
+  let stop = AtomicBool::new(false);
+  // The index of the next result due, for ordered results.
+  let due = (Mutex::new(0), Condvar::new());
+  let halt = || {
+    stop.store(true, Ordering::Relaxed);
+    let _lock = due.0.lock().unwrap();
+    due.1.notify_all();
+  };
+  let (sender, receiver) = mpsc::sync_channel(buffer);
+  std::thread::scope(|scope| {
+    for _ in 0..plan.threads.max(1).min(paths.len()) {
+      let searcher = searcher.clone();
+      let sender = sender.clone();
+      let (next, stop, due) = (&next, &stop, &due);
+      scope.spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+          let i = next.fetch_add(1, Ordering::Relaxed);
+          let Some(path) = paths.get(i) else {
+            break;
+          };
+          if plan.ordered {
+            let waiting = |due: &mut usize| i >= *due + buffer && !stop.load(Ordering::Relaxed);
+            drop(due.1.wait_while(due.0.lock().unwrap(), waiting).unwrap());
+            if stop.load(Ordering::Relaxed) {
+              break;
+            }
+          }
+          if sender
+            .send((i, search_file(&searcher, matcher, path)))
+            .is_err()
+          {
+            break;
+          }
+        }
+      });
+    }
+    drop(sender);
+    let mut pending = HashMap::new();
+    let mut next_due = 0;
+    for (i, file) in receiver {
+      let more = if plan.ordered {
+        pending.insert(i, file);
+        let mut more = true;
+        while let Some(file) = pending.remove(&next_due) {
+          next_due += 1;
+          *due.0.lock().unwrap() = next_due;
+          due.1.notify_all();
+          more = deliver(file);
+          if !more {
+            break;
+          }
+        }
+        more
+      } else {
+        deliver(file)
+      };
+      if !more {
+        halt();
+        break;
+      }
+    }
+  });
+}
+
+fn search_file(searcher: &Searcher, matcher: &RegexMatcher, path: &str) -> Item<FileSearchResult> {
+  match searcher.search(matcher, Haystack::Path(path), SearchOptions::default()) {
+    Ok(output) => Ok(FileSearchResult::new(path.to_string(), output, None)),
+    Err(e)
+      if matches!(
+        searcher.config.read_error_policy.action,
+        Some(ReadErrorAction::Skip)
+      ) && !searcher.config.cancelled() =>
+    {
+      let output = SearchOutput {
+        result: CollectSink::new(matcher.inner.clone(), &searcher.config).into_result(),
+        plain: searcher.config.plain,
+      };
+      Ok(FileSearchResult::new(path.to_string(), output, Some(e)))
+    }
+    Err(e) => Err(Failure::file(path, e)),
+  }
+}
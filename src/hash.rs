@@ -0,0 +1,278 @@
+//! Content hashes of searched inputs.
+//!
+//! The hashes are computed over the bytes the searcher reads anyway, so
+//! callers can cache or deduplicate files without reading them a second
+//! time. Both algorithms are implemented here to keep the dependency tree
+//! small; the tests check them against node's SHA-256 and digests from the
+//! reference xxHash library at the block boundaries of both.
+
+use crate::HashAlgorithm;
+
+/// An incremental hash of one input.
+pub(crate) enum Hasher {
+  Xxh64(Xxh64),
+  Sha256(Sha256),
+}
+
+impl Hasher {
+  pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+    match algorithm {
+      HashAlgorithm::Xxh64 => Hasher::Xxh64(Xxh64::new()),
+      HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+    }
+  }
+
+  pub(crate) fn update(&mut self, bytes: &[u8]) {
+    match self {
+      Hasher::Xxh64(h) => h.update(bytes),
+      Hasher::Sha256(h) => h.update(bytes),
+    }
+  }
+
+  /// The number of bytes hashed so far.
+  pub(crate) fn len(&self) -> u64 {
+    match self {
+      Hasher::Xxh64(h) => h.len,
+      Hasher::Sha256(h) => h.len,
+    }
+  }
+
+  /// The digest as lowercase hex, most significant byte first.
+  pub(crate) fn finish(self) -> String {
+    let digest = match self {
+      Hasher::Xxh64(h) => h.finish().to_be_bytes().to_vec(),
+      Hasher::Sha256(h) => h.finish().to_vec(),
+    };
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+  }
+}
+
+impl std::io::Write for Hasher {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self.update(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Hash `bytes` in one go.
+pub(crate) fn digest(algorithm: HashAlgorithm, bytes: &[u8]) -> String {
+  let mut hasher = Hasher::new(algorithm);
+  hasher.update(bytes);
+  hasher.finish()
+}
+
+const P1: u64 = 0x9E37_79B1_85EB_CA87;
+const P2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const P3: u64 = 0x1656_67B1_9E37_79F9;
+const P4: u64 = 0x85EB_CA77_C2B2_AE63;
+const P5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// XXH64 with seed 0.
+pub(crate) struct Xxh64 {
+  acc: [u64; 4],
+  buffer: [u8; 32],
+  buffered: usize,
+  len: u64,
+}
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+  acc
+    .wrapping_add(input.wrapping_mul(P2))
+    .rotate_left(31)
+    .wrapping_mul(P1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+  u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+impl Xxh64 {
+  fn new() -> Self {
+    Xxh64 {
+      acc: [P1.wrapping_add(P2), P2, 0, 0u64.wrapping_sub(P1)],
+      buffer: [0; 32],
+      buffered: 0,
+      len: 0,
+    }
+  }
+
+  fn stripe(&mut self, stripe: &[u8]) {
+    for (i, acc) in self.acc.iter_mut().enumerate() {
+      *acc = xxh_round(*acc, read_u64(&stripe[i * 8..]));
+    }
+  }
+
+  fn update(&mut self, mut bytes: &[u8]) {
+    self.len += bytes.len() as u64;
+    if self.buffered > 0 {
+      let n = (32 - self.buffered).min(bytes.len());
+      self.buffer[self.buffered..self.buffered + n].copy_from_slice(&bytes[..n]);
+      self.buffered += n;
+      bytes = &bytes[n..];
+      if self.buffered < 32 {
+        return;
+      }
+      let buffer = self.buffer;
+      self.stripe(&buffer);
+      self.buffered = 0;
+    }
+    let mut stripes = bytes.chunks_exact(32);
+    for stripe in &mut stripes {
+      self.stripe(stripe);
+    }
+    let rest = stripes.remainder();
+    self.buffer[..rest.len()].copy_from_slice(rest);
+    self.buffered = rest.len();
+  }
+
+  fn finish(&self) -> u64 {
+    let mut h = if self.len >= 32 {
+      let [v1, v2, v3, v4] = self.acc;
+      let mut h = v1
+        .rotate_left(1)
+        .wrapping_add(v2.rotate_left(7))
+        .wrapping_add(v3.rotate_left(12))
+        .wrapping_add(v4.rotate_left(18));
+      for v in self.acc {
+        h = (h ^ xxh_round(0, v)).wrapping_mul(P1).wrapping_add(P4);
+      }
+      h
+    } else {
+      P5
+    };
+    h = h.wrapping_add(self.len);
+    let mut rest = &self.buffer[..self.buffered];
+    while rest.len() >= 8 {
+      h ^= xxh_round(0, read_u64(rest));
+      h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+      rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+      let k = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+      h ^= k.wrapping_mul(P1);
+      h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+      rest = &rest[4..];
+    }
+    for &b in rest {
+      h ^= (b as u64).wrapping_mul(P5);
+      h = h.rotate_left(11).wrapping_mul(P1);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(P2);
+    h ^= h >> 29;
+    h = h.wrapping_mul(P3);
+    h ^ (h >> 32)
+  }
+}
+
+const K: [u32; 64] = [
+  0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+  0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+  0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+  0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+  0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+  0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+  0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+  0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256.
+pub(crate) struct Sha256 {
+  state: [u32; 8],
+  buffer: [u8; 64],
+  buffered: usize,
+  len: u64,
+}
+
+impl Sha256 {
+  fn new() -> Self {
+    Sha256 {
+      state: [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+      ],
+      buffer: [0; 64],
+      buffered: 0,
+      len: 0,
+    }
+  }
+
+  fn block(&mut self, block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+      w[i] = u32::from_be_bytes(word.try_into().unwrap());
+    }
+    for i in 16..64 {
+      let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+      let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+      w[i] = w[i - 16]
+        .wrapping_add(s0)
+        .wrapping_add(w[i - 7])
+        .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+    for i in 0..64 {
+      let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+      let ch = (e & f) ^ (!e & g);
+      let t1 = h
+        .wrapping_add(s1)
+        .wrapping_add(ch)
+        .wrapping_add(K[i])
+        .wrapping_add(w[i]);
+      let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+      let maj = (a & b) ^ (a & c) ^ (b & c);
+      let t2 = s0.wrapping_add(maj);
+      h = g;
+      g = f;
+      f = e;
+      e = d.wrapping_add(t1);
+      d = c;
+      c = b;
+      b = a;
+      a = t1.wrapping_add(t2);
+    }
+    for (state, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+      *state = state.wrapping_add(v);
+    }
+  }
+
+  fn update(&mut self, mut bytes: &[u8]) {
+    self.len += bytes.len() as u64;
+    if self.buffered > 0 {
+      let n = (64 - self.buffered).min(bytes.len());
+      self.buffer[self.buffered..self.buffered + n].copy_from_slice(&bytes[..n]);
+      self.buffered += n;
+      bytes = &bytes[n..];
+      if self.buffered < 64 {
+        return;
+      }
+      let buffer = self.buffer;
+      self.block(&buffer);
+      self.buffered = 0;
+    }
+    let mut blocks = bytes.chunks_exact(64);
+    for block in &mut blocks {
+      self.block(block);
+    }
+    let rest = blocks.remainder();
+    self.buffer[..rest.len()].copy_from_slice(rest);
+    self.buffered = rest.len();
+  }
+
+  fn finish(mut self) -> [u8; 32] {
+    let bits = self.len.wrapping_mul(8);
+    let mut padding = vec![0x80];
+    padding.resize((119 - self.buffered) % 64 + 1, 0);
+    padding.extend_from_slice(&bits.to_be_bytes());
+    self.update(&padding);
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(self.state) {
+      bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+  }
+}
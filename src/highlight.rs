@@ -0,0 +1,41 @@
+//! Layering the matches of several matchers into ranges that do not overlap.
+//!
+//! Matches are painted one by one, strongest first. Each covers the parts
+//! of the text that no stronger match covered, so a match partly hidden by
+//! a stronger one keeps its visible parts, as layers of highlighting do.
+
+use std::collections::BTreeMap;
+
+/// A match of the matcher at `layer`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Layered {
+  pub layer: u32,
+  pub start: u32,
+  pub end: u32,
+}
+
+/// The visible parts of `matches`, strongest first, ordered by start.
+pub(crate) fn paint(matches: &[Layered]) -> Vec<Layered> {
+  // Painted ranges, by start. They never overlap.
+  let mut painted: BTreeMap<u32, Layered> = BTreeMap::new();
+  for m in matches {
+    let mut at = m.start;
+    if let Some((_, before)) = painted.range(..m.start).next_back() {
+      at = at.max(before.end);
+    }
+    let mut visible = Vec::new();
+    for (&start, covered) in painted.range(m.start..m.end) {
+      if start > at {
+        visible.push((at, start));
+      }
+      at = at.max(covered.end);
+    }
+    if at < m.end {
+      visible.push((at, m.end));
+    }
+    for (start, end) in visible {
+      painted.insert(start, Layered { start, end, ..*m });
+    }
+  }
+  painted.into_values().collect()
+}
@@ -0,0 +1,168 @@
+//! Replacement template parsing and expansion.
+//!
+//! Templates follow the same `$name` / `${name}` conventions as the regex
+//! crate, extended with `${name:-default}` fallbacks. A template is parsed
+//! once against a matcher so that group names are resolved (and, in strict
+//! mode, validated) before any searching happens.
+
+use grep::matcher::{Captures, Matcher};
+
+enum Piece {
+  /// Bytes copied verbatim into the output.
+  Literal(Vec<u8>),
+  /// A reference to a capture group. `index` is `None` when the group does
+  /// not exist in the pattern (only possible in non-strict mode).
+  Group {
+    index: Option<usize>,
+    default: Option<Vec<u8>>,
+  },
+}
+
+/// A parsed replacement template.
+pub(crate) struct Template {
+  pieces: Vec<Piece>,
+}
+
+impl Template {
+  /// Parse a replacement template, resolving group references against the
+  /// given matcher.
+  ///
+  /// Supported syntax:
+  /// - `$$` is a literal `$`.
+  /// - `$name` / `$1` reference a group by name or index. The name is the
+  ///   longest run of `[_0-9a-zA-Z]` following the `$`.
+  /// - `${name}` references a group with an explicit end.
+  /// - `${name:-default}` expands to `default` when the group did not
+  ///   participate in the match or matched the empty string.
+  ///
+  /// A `$` not followed by one of the above is copied literally. When
+  /// `strict` is enabled, references to groups that do not exist and
+  /// malformed `${...}` references are reported as errors instead.
+  pub(crate) fn parse<M: Matcher>(
+    matcher: &M,
+    replacement: &[u8],
+    strict: bool,
+  ) -> std::result::Result<Template, String> {
+    let mut pieces = Vec::new();
+    let mut literal = Vec::new();
+    let mut i = 0;
+    while i < replacement.len() {
+      let b = replacement[i];
+      if b != b'$' {
+        literal.push(b);
+        i += 1;
+        continue;
+      }
+      let rest = &replacement[i + 1..];
+      match rest.first() {
+        Some(b'$') => {
+          literal.push(b'$');
+          i += 2;
+        }
+        Some(b'{') => {
+          let Some(close) = rest.iter().position(|&b| b == b'}') else {
+            if strict {
+              return Err("unterminated `${` in replacement".to_string());
+            }
+            literal.push(b'$');
+            i += 1;
+            continue;
+          };
+          let inner = &rest[1..close];
+          let (name, default) = match find_subslice(inner, b":-") {
+            Some(pos) => (&inner[..pos], Some(inner[pos + 2..].to_vec())),
+            None => (inner, None),
+          };
+          if name.is_empty() || !name.iter().all(|&b| is_name_byte(b)) {
+            if strict {
+              return Err(format!(
+                "invalid capture group reference `${{{}}}` in replacement",
+                String::from_utf8_lossy(inner)
+              ));
+            }
+            literal.push(b'$');
+            i += 1;
+            continue;
+          }
+          flush(&mut pieces, &mut literal);
+          pieces.push(Piece::Group {
+            index: resolve(matcher, name, strict)?,
+            default,
+          });
+          i += close + 2;
+        }
+        _ => {
+          let len = rest.iter().take_while(|&&b| is_name_byte(b)).count();
+          if len == 0 {
+            literal.push(b'$');
+            i += 1;
+            continue;
+          }
+          flush(&mut pieces, &mut literal);
+          pieces.push(Piece::Group {
+            index: resolve(matcher, &rest[..len], strict)?,
+            default: None,
+          });
+          i += len + 1;
+        }
+      }
+    }
+    flush(&mut pieces, &mut literal);
+    Ok(Template { pieces })
+  }
+
+  /// Append the expansion of this template for the given captures to `dst`.
+  pub(crate) fn expand<C: Captures>(&self, haystack: &[u8], caps: &C, dst: &mut Vec<u8>) {
+    for piece in &self.pieces {
+      match piece {
+        Piece::Literal(bytes) => dst.extend_from_slice(bytes),
+        Piece::Group { index, default } => {
+          let value = index
+            .and_then(|i| caps.get(i))
+            .map(|m| &haystack[m])
+            .filter(|v| !v.is_empty());
+          match (value, default) {
+            (Some(v), _) => dst.extend_from_slice(v),
+            (None, Some(d)) => dst.extend_from_slice(d),
+            (None, None) => {}
+          }
+        }
+      }
+    }
+  }
+}
+
+fn flush(pieces: &mut Vec<Piece>, literal: &mut Vec<u8>) {
+  if !literal.is_empty() {
+    pieces.push(Piece::Literal(std::mem::take(literal)));
+  }
+}
+
+fn resolve<M: Matcher>(
+  matcher: &M,
+  name: &[u8],
+  strict: bool,
+) -> std::result::Result<Option<usize>, String> {
+  // Names are validated to be ASCII before we get here.
+  let name = std::str::from_utf8(name).unwrap_or_default();
+  let index = match name.parse::<usize>() {
+    Ok(i) if i < matcher.capture_count() => Some(i),
+    Ok(_) => None,
+    Err(_) => matcher.capture_index(name),
+  };
+  if index.is_none() && strict {
+    return Err(format!(
+      "replacement references unknown capture group `{}`",
+      name
+    ));
+  }
+  Ok(index)
+}
+
+fn is_name_byte(b: u8) -> bool {
+  b == b'_' || b.is_ascii_alphanumeric()
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|w| w == needle)
+}
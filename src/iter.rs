@@ -0,0 +1,261 @@
+//! Async iterators over values produced on a background thread.
+//!
+//! The producing thread hands values to a bounded queue. `next()` on the
+//! JavaScript side takes a queued value right away or leaves a deferred
+//! promise for the producer to settle with its next value, so no thread
+//! waits on the consumer's behalf. A consumer that stops pulling values stops
+//! the producer once the queue is full, and dropping the queue makes the
+//! producer's next send fail, which ends it.
+
+use std::collections::VecDeque;
+use std::ffi::CStr;
+use std::ptr;
+use std::sync::mpsc::SendError;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use napi::bindgen_prelude::*;
+use napi::{check_status, sys, JsDeferred};
+
+use crate::error::Failure;
+
+type Deferred<T> = JsDeferred<Step<T>, Box<dyn FnOnce(Env) -> Result<Step<T>>>>;
+
+/// A value, or the error that ended the values.
+pub(crate) type Item<T> = std::result::Result<T, Failure>;
+
+struct State<T: ToNapiValue + 'static> {
+  values: VecDeque<Item<T>>,
+  /// The promises of `next()` calls made while no value was queued.
+  waiting: VecDeque<Deferred<T>>,
+  /// Set once the producer is gone.
+  ended: bool,
+  /// Set once the consumer is gone.
+  closed: bool,
+}
+
+struct Shared<T: ToNapiValue + 'static> {
+  state: Mutex<State<T>>,
+  /// Signalled when a value is taken or the consumer goes.
+  taken: Condvar,
+  size: usize,
+}
+
+impl<T: ToNapiValue + 'static> Shared<T> {
+  fn lock(&self) -> MutexGuard<'_, State<T>> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+/// Create a queue that buffers at most `size` values. With a size of 0,
+/// each send waits for a `next()` call to take the value.
+pub(crate) fn channel<T: ToNapiValue + 'static>(size: usize) -> (Sender<T>, Queue<T>) {
+  let shared = Arc::new(Shared {
+    state: Mutex::new(State {
+      values: VecDeque::new(),
+      waiting: VecDeque::new(),
+      ended: false,
+      closed: false,
+    }),
+    taken: Condvar::new(),
+    size,
+  });
+  (Sender(shared.clone()), Queue(shared))
+}
+
+/// The producing end of a queue.
+pub(crate) struct Sender<T: ToNapiValue + 'static>(Arc<Shared<T>>);
+
+impl<T: ToNapiValue + 'static> Sender<T> {
+  /// Pass `value` to a waiting `next()` call, or queue it, waiting for room
+  /// while the queue is full. Fails once the queue is dropped.
+  pub(crate) fn send(&self, value: Item<T>) -> std::result::Result<(), SendError<Item<T>>> {
+    let mut state = self.0.lock();
+    loop {
+      if state.closed {
+        return Err(SendError(value));
+      }
+      if let Some(deferred) = state.waiting.pop_front() {
+        drop(state);
+        settle(deferred, Some(value));
+        return Ok(());
+      }
+      if state.values.len() < self.0.size {
+        state.values.push_back(value);
+        return Ok(());
+      }
+      state = self.0.taken.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+  }
+}
+
+impl<T: ToNapiValue + 'static> Drop for Sender<T> {
+  fn drop(&mut self) {
+    let mut state = self.0.lock();
+    state.ended = true;
+    let waiting = std::mem::take(&mut state.waiting);
+    drop(state);
+    for deferred in waiting {
+      deferred.resolve(Box::new(|_| Ok(Step(None))));
+    }
+  }
+}
+
+/// The consuming end of a queue.
+pub(crate) struct Queue<T: ToNapiValue + 'static>(Arc<Shared<T>>);
+
+impl<T: ToNapiValue + 'static> Queue<T> {
+  /// A promise of the next value of `queue`. A missing queue has ended.
+  pub(crate) fn next<'env>(queue: Option<&Self>, env: &'env Env) -> Result<Object<'env>> {
+    let (deferred, promise) = env.create_deferred()?;
+    let Some(queue) = queue else {
+      settle(deferred, None);
+      return Ok(promise);
+    };
+    let mut state = queue.0.lock();
+    if let Some(value) = state.values.pop_front() {
+      drop(state);
+      queue.0.taken.notify_all();
+      settle(deferred, Some(value));
+    } else if state.ended {
+      drop(state);
+      settle(deferred, None);
+    } else {
+      state.waiting.push_back(deferred);
+      // A producer waiting to hand over a value without queueing it can now.
+      queue.0.taken.notify_all();
+    }
+    Ok(promise)
+  }
+}
+
+impl<T: ToNapiValue + 'static> Drop for Queue<T> {
+  fn drop(&mut self) {
+    let mut state = self.0.lock();
+    state.closed = true;
+    let values = std::mem::take(&mut state.values);
+    let waiting = std::mem::take(&mut state.waiting);
+    drop(state);
+    self.0.taken.notify_all();
+    drop(values);
+    for deferred in waiting {
+      deferred.resolve(Box::new(|_| Ok(Step(None))));
+    }
+  }
+}
+
+/// Settle the promise of a `next()` call with `value`, or as done.
+fn settle<T: ToNapiValue + 'static>(deferred: Deferred<T>, value: Option<Item<T>>) {
+  match value.transpose() {
+    Ok(value) => deferred.resolve(Box::new(move |_| Ok(Step(value)))),
+    Err(failure) => deferred.resolve(Box::new(move |env| Err(failure.into_error(&env)))),
+  }
+}
+
+/// An iterator result: `{ value, done }`.
+pub struct Step<T>(pub(crate) Option<T>);
+
+impl<T> TypeName for Step<T> {
+  fn type_name() -> &'static str {
+    "IteratorResult"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl<T: ToNapiValue> ToNapiValue for Step<T> {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+    let mut object = Object::new(&Env::from_raw(env))?;
+    let done = val.0.is_none();
+    object.set("value", val.0)?;
+    object.set("done", done)?;
+    ToNapiValue::to_napi_value(env, object)
+  }
+}
+
+/// Define `[Symbol.asyncIterator]` on `object`, returning the object itself,
+/// so it can be used with `for await`, and `[Symbol.dispose]` and
+/// `[Symbol.asyncDispose]`, calling its `close` method, where the runtime
+/// defines them, so it can be used with `using` and `await using`.
+pub(crate) unsafe fn make_async_iterable(
+  env: sys::napi_env,
+  object: sys::napi_value,
+) -> Result<()> {
+  let mut global = ptr::null_mut();
+  check_status!(sys::napi_get_global(env, &mut global))?;
+  let symbol = named_property(env, global, c"Symbol")?;
+  define_method(env, object, symbol, c"asyncIterator", this)?;
+  define_method(env, object, symbol, c"dispose", close)?;
+  define_method(env, object, symbol, c"asyncDispose", close)
+}
+
+/// Define `callback` on `object` under the well-known symbol `Symbol[name]`,
+/// unless the runtime does not define that symbol.
+unsafe fn define_method(
+  env: sys::napi_env,
+  object: sys::napi_value,
+  symbol: sys::napi_value,
+  name: &CStr,
+  callback: unsafe extern "C" fn(sys::napi_env, sys::napi_callback_info) -> sys::napi_value,
+) -> Result<()> {
+  let key = named_property(env, symbol, name)?;
+  let mut kind = 0;
+  check_status!(sys::napi_typeof(env, key, &mut kind))?;
+  if kind != sys::ValueType::napi_symbol {
+    return Ok(());
+  }
+  let mut function = ptr::null_mut();
+  check_status!(sys::napi_create_function(
+    env,
+    name.as_ptr(),
+    name.count_bytes() as isize,
+    Some(callback),
+    ptr::null_mut(),
+    &mut function
+  ))?;
+  check_status!(sys::napi_set_property(env, object, key, function))
+}
+
+unsafe fn named_property(
+  env: sys::napi_env,
+  object: sys::napi_value,
+  name: &CStr,
+) -> Result<sys::napi_value> {
+  let mut value = ptr::null_mut();
+  check_status!(sys::napi_get_named_property(
+    env,
+    object,
+    name.as_ptr(),
+    &mut value
+  ))?;
+  Ok(value)
+}
+
+unsafe extern "C" fn this(env: sys::napi_env, info: sys::napi_callback_info) -> sys::napi_value {
+  let mut this = ptr::null_mut();
+  let mut argc = 0;
+  sys::napi_get_cb_info(
+    env,
+    info,
+    &mut argc,
+    ptr::null_mut(),
+    &mut this,
+    ptr::null_mut(),
+  );
+  this
+}
+
+/// Call `this.close()`, returning undefined.
+unsafe extern "C" fn close(env: sys::napi_env, info: sys::napi_callback_info) -> sys::napi_value {
+  let object = this(env, info);
+  let mut undefined = ptr::null_mut();
+  sys::napi_get_undefined(env, &mut undefined);
+  if let Ok(method) = named_property(env, object, c"close") {
+    // An exception thrown by `close` stays pending and is thrown to the
+    // caller.
+    let mut result = ptr::null_mut();
+    sys::napi_call_function(env, object, method, 0, ptr::null(), &mut result);
+  }
+  undefined
+}
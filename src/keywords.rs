@@ -0,0 +1,380 @@
+//! Compiled keyword scanners saved to files and loaded back.
+//!
+//! The `aho-corasick` crate cannot serialize its automata, so a saved
+//! scanner holds an Aho-Corasick automaton of its own, laid out as flat
+//! tables that are searched in place from the bytes read from the file.
+//! Loading only reads and checks the file, without building anything, but
+//! the tables are searched a byte at a time through failure links, which
+//! takes about one and a half times as long as the `aho-corasick` crate.
+//! Its nodes are numbered breadth first, so edges always lead to a later
+//! node and failure and output links to an earlier one, which `parse`
+//! checks so that searching any file ends. Indexes into the tables are
+//! checked too, so a damaged file cannot make a search panic, though it may
+//! report wrong matches.
+//!
+//! A file is a header, the keywords and the tables, all integers `u32` and
+//! little endian:
+//!
+//! ```text
+//! magic       8 bytes, "gjskw\0\0\x01"
+//! flags       bit 0 for word boundaries, bit 1 for case insensitivity
+//! keywords    a count, then a byte length and UTF-8 bytes for each
+//! sizes       the number of nodes, edges and outputs
+//! edge_start  per node and one more, where its edges start
+//! fail        per node, the node of its longest proper suffix
+//! dict        per node, the nearest node along `fail` with outputs, or NONE
+//! out_start   per node and one more, where its outputs start
+//! edge_byte   per edge, a single byte, sorted within a node
+//! edge_node   per edge, the node it leads to
+//! output      per output, the index of a keyword ending at its node
+//! ```
+
+use aho_corasick::Match;
+use napi::bindgen_prelude::*;
+
+const MAGIC: &[u8; 8] = b"gjskw\0\0\x01";
+const WORD: u32 = 1;
+const CASE_INSENSITIVE: u32 = 2;
+const NONE: u32 = u32::MAX;
+const ROOT: u32 = 0;
+
+/// A scanner's automaton, read from a file.
+pub(crate) struct Stored {
+  bytes: Vec<u8>,
+  case_insensitive: bool,
+  layout: Layout,
+}
+
+/// Where each table starts in a file.
+#[derive(Clone, Copy)]
+struct Layout {
+  edge_start: usize,
+  fail: usize,
+  dict: usize,
+  out_start: usize,
+  edge_byte: usize,
+  edge_node: usize,
+  output: usize,
+}
+
+/// The tables of an automaton, borrowed from the bytes of its file.
+struct Tables<'a> {
+  bytes: &'a [u8],
+  layout: Layout,
+}
+
+impl Tables<'_> {
+  /// Entry `index` of the table starting at `table`.
+  fn at(&self, table: usize, index: u32) -> u32 {
+    let at = table + 4 * index as usize;
+    u32::from_le_bytes(self.bytes[at..at + 4].try_into().unwrap())
+  }
+
+  /// The range of entries of `node` in a table indexed by `edge_start` or
+  /// `out_start`.
+  fn range(&self, table: usize, node: u32) -> (u32, u32) {
+    (self.at(table, node), self.at(table, node + 1))
+  }
+
+  /// The node reached from `node` on `byte`, if any.
+  fn edge(&self, node: u32, byte: u8) -> Option<u32> {
+    let (start, end) = self.range(self.layout.edge_start, node);
+    let bytes = &self.bytes[self.layout.edge_byte + start as usize..][..(end - start) as usize];
+    let found = bytes.binary_search(&byte).ok()?;
+    Some(self.at(self.layout.edge_node, start + found as u32))
+  }
+}
+
+impl Stored {
+  /// Find every occurrence of every keyword in `bytes`, in no particular
+  /// order.
+  pub(crate) fn find_overlapping(&self, bytes: &[u8], keywords: &[String]) -> Vec<Match> {
+    let tables = Tables {
+      bytes: &self.bytes,
+      layout: self.layout,
+    };
+    let mut found = Vec::new();
+    let mut node = ROOT;
+    for (i, &byte) in bytes.iter().enumerate() {
+      let byte = fold(byte, self.case_insensitive);
+      node = loop {
+        match tables.edge(node, byte) {
+          Some(next) => break next,
+          None if node == ROOT => break ROOT,
+          None => node = tables.at(self.layout.fail, node),
+        }
+      };
+      let mut matched = node;
+      while matched != NONE {
+        let (start, end) = tables.range(self.layout.out_start, matched);
+        for output in start..end {
+          let pattern = tables.at(self.layout.output, output) as usize;
+          let start = (i + 1).saturating_sub(keywords[pattern].len());
+          found.push(Match::must(pattern, start..i + 1));
+        }
+        matched = tables.at(self.layout.dict, matched);
+      }
+    }
+    found
+  }
+
+  /// The bytes of the file.
+  pub(crate) fn bytes(&self) -> &[u8] {
+    &self.bytes
+  }
+}
+
+/// A scanner read from a file.
+pub(crate) struct Loaded {
+  pub keywords: Vec<String>,
+  pub word: bool,
+  pub automaton: Stored,
+}
+
+/// A trie node while building.
+#[derive(Default)]
+struct Node {
+  /// Sorted by byte.
+  edges: Vec<(u8, u32)>,
+  outputs: Vec<u32>,
+}
+
+impl Node {
+  fn edge(&self, byte: u8) -> std::result::Result<usize, usize> {
+    self.edges.binary_search_by_key(&byte, |&(b, _)| b)
+  }
+}
+
+/// The byte a keyword or text byte is matched as.
+fn fold(byte: u8, case_insensitive: bool) -> u8 {
+  if case_insensitive {
+    byte.to_ascii_lowercase()
+  } else {
+    byte
+  }
+}
+
+/// Write a scanner for `keywords` to `path`, building its automaton. Empty
+/// keywords are kept in the list but never match.
+pub(crate) fn save(
+  path: &str,
+  keywords: &[String],
+  case_insensitive: bool,
+  word: bool,
+) -> Result<()> {
+  let mut trie = vec![Node::default()];
+  for (pattern, keyword) in keywords.iter().enumerate() {
+    if keyword.is_empty() {
+      continue;
+    }
+    let mut node = 0;
+    for byte in keyword.bytes().map(|b| fold(b, case_insensitive)) {
+      node = match trie[node].edge(byte) {
+        Ok(found) => trie[node].edges[found].1 as usize,
+        Err(at) => {
+          let next = trie.len();
+          trie[node].edges.insert(at, (byte, next as u32));
+          trie.push(Node::default());
+          next
+        }
+      };
+    }
+    trie[node].outputs.push(pattern as u32);
+  }
+
+  // Number the nodes breadth first, then link each node after its parent
+  // and its suffixes, which are all nearer the root.
+  let mut order = vec![ROOT];
+  let mut i = 0;
+  while i < order.len() {
+    order.extend(trie[order[i] as usize].edges.iter().map(|&(_, next)| next));
+    i += 1;
+  }
+  let mut number = vec![0u32; trie.len()];
+  for (new, &old) in order.iter().enumerate() {
+    number[old as usize] = new as u32;
+  }
+  let node = |new: u32| &trie[order[new as usize] as usize];
+  let mut fail = vec![ROOT; trie.len()];
+  let mut dict = vec![NONE; trie.len()];
+  for parent in 0..order.len() as u32 {
+    for &(byte, child) in &node(parent).edges {
+      let mut suffix = fail[parent as usize];
+      let target = loop {
+        if parent == ROOT {
+          break ROOT;
+        }
+        if let Ok(found) = node(suffix).edge(byte) {
+          break number[node(suffix).edges[found].1 as usize];
+        }
+        if suffix == ROOT {
+          break ROOT;
+        }
+        suffix = fail[suffix as usize];
+      };
+      let child = number[child as usize] as usize;
+      fail[child] = target;
+      dict[child] = if node(target).outputs.is_empty() {
+        dict[target as usize]
+      } else {
+        target
+      };
+    }
+  }
+
+  let mut out = MAGIC.to_vec();
+  let mut flags = 0;
+  if word {
+    flags |= WORD;
+  }
+  if case_insensitive {
+    flags |= CASE_INSENSITIVE;
+  }
+  push(&mut out, flags);
+  push(&mut out, keywords.len() as u32);
+  for keyword in keywords {
+    push(&mut out, keyword.len() as u32);
+    out.extend_from_slice(keyword.as_bytes());
+  }
+  let nodes = || (0..order.len() as u32).map(node);
+  let edges = nodes().map(|n| n.edges.len() as u32).sum();
+  let outputs = nodes().map(|n| n.outputs.len() as u32).sum();
+  push(&mut out, order.len() as u32);
+  push(&mut out, edges);
+  push(&mut out, outputs);
+  let mut start = 0;
+  for n in nodes() {
+    push(&mut out, start);
+    start += n.edges.len() as u32;
+  }
+  push(&mut out, start);
+  fail.iter().for_each(|&f| push(&mut out, f));
+  dict.iter().for_each(|&d| push(&mut out, d));
+  let mut start = 0;
+  for n in nodes() {
+    push(&mut out, start);
+    start += n.outputs.len() as u32;
+  }
+  push(&mut out, start);
+  nodes().for_each(|n| out.extend(n.edges.iter().map(|&(byte, _)| byte)));
+  for n in nodes() {
+    n.edges
+      .iter()
+      .for_each(|&(_, next)| push(&mut out, number[next as usize]));
+  }
+  nodes().for_each(|n| n.outputs.iter().for_each(|&p| push(&mut out, p)));
+  write(path, &out)
+}
+
+/// Append `value` to `out`, little endian.
+fn push(out: &mut Vec<u8>, value: u32) {
+  out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Write `bytes` to `path`, through a temporary file renamed into place so
+/// that a failed write leaves any earlier file whole.
+pub(crate) fn write(path: &str, bytes: &[u8]) -> Result<()> {
+  let temp = format!("{}.{}.tmp", path, std::process::id());
+  let written = std::fs::write(&temp, bytes).and_then(|()| std::fs::rename(&temp, path));
+  written.map_err(|e| {
+    let _ = std::fs::remove_file(&temp);
+    Error::new(Status::GenericFailure, format!("{}: {}", path, e))
+  })
+}
+
+/// Read the scanner saved at `path`.
+pub(crate) fn load(path: &str) -> Result<Loaded> {
+  let bytes = std::fs::read(path)
+    .map_err(|e| Error::new(Status::GenericFailure, format!("{}: {}", path, e)))?;
+  let Some((keywords, flags, layout)) = parse(&bytes) else {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("{}: not a saved KeywordScanner", path),
+    ));
+  };
+  Ok(Loaded {
+    keywords,
+    word: flags & WORD != 0,
+    automaton: Stored {
+      bytes,
+      case_insensitive: flags & CASE_INSENSITIVE != 0,
+      layout,
+    },
+  })
+}
+
+/// Read the keywords, flags and table layout of a saved scanner, checking
+/// that every index is in range, that edges lead to later nodes and that
+/// links lead to earlier ones.
+fn parse(bytes: &[u8]) -> Option<(Vec<String>, u32, Layout)> {
+  let mut rest = bytes.strip_prefix(MAGIC)?;
+  let flags = read_u32(&mut rest)?;
+  let count = read_u32(&mut rest)?;
+  let mut keywords = Vec::new();
+  for _ in 0..count {
+    let len = read_u32(&mut rest)? as usize;
+    let keyword = std::str::from_utf8(take(&mut rest, len)?).ok()?;
+    keywords.push(keyword.to_string());
+  }
+  let nodes = read_u32(&mut rest)?;
+  let edges = read_u32(&mut rest)?;
+  let outputs = read_u32(&mut rest)?;
+  let mut at = bytes.len() - rest.len();
+  let mut table = |len: u32, width: usize| {
+    let start = at;
+    at = at.checked_add(len as usize * width)?;
+    Some(start)
+  };
+  let layout = Layout {
+    edge_start: table(nodes.checked_add(1)?, 4)?,
+    fail: table(nodes, 4)?,
+    dict: table(nodes, 4)?,
+    out_start: table(nodes.checked_add(1)?, 4)?,
+    edge_byte: table(edges, 1)?,
+    edge_node: table(edges, 4)?,
+    output: table(outputs, 4)?,
+  };
+  if nodes == 0 || at != bytes.len() {
+    return None;
+  }
+
+  let tables = Tables { bytes, layout };
+  let indexes = |table: usize, len: u32| {
+    tables.at(table, 0) == 0
+      && tables.at(table, nodes) == len
+      && (0..nodes).all(|n| tables.at(table, n) <= tables.at(table, n + 1))
+  };
+  if !indexes(layout.edge_start, edges) || !indexes(layout.out_start, outputs) {
+    return None;
+  }
+  for n in 0..nodes {
+    let (start, end) = tables.range(layout.edge_start, n);
+    for edge in start..end {
+      let next = tables.at(layout.edge_node, edge);
+      if next <= n || next >= nodes {
+        return None;
+      }
+    }
+    let dict = tables.at(layout.dict, n);
+    if n != ROOT && tables.at(layout.fail, n) >= n || dict != NONE && dict >= n {
+      return None;
+    }
+    let (start, end) = tables.range(layout.out_start, n);
+    if (start..end).any(|output| tables.at(layout.output, output) >= count) {
+      return None;
+    }
+  }
+  Some((keywords, flags, layout))
+}
+
+/// Take `len` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+  let (taken, rest) = bytes.split_at_checked(len)?;
+  *bytes = rest;
+  Some(taken)
+}
+
+/// Take a little endian `u32` off the front of `bytes`.
+fn read_u32(bytes: &mut &[u8]) -> Option<u32> {
+  Some(u32::from_le_bytes(take(bytes, 4)?.try_into().ok()?))
+}
@@ -0,0 +1,579 @@
+//! Conversion of search results to JavaScript with lazily created lines.
+//!
+//! Creating the `line` string and `bytes` buffer of every match and context
+//! line is a large share of the cost of returning a `SearchResult`, yet many
+//! callers only read line numbers and match ranges. Matches and context
+//! lines are therefore instances of `SearchMatch` and `SearchContext`
+//! classes whose prototypes expose `line` and `bytes` as getters over the
+//! Rust values, which each instance references through hidden properties.
+//! The first read (or assignment) stores the value on the instance itself,
+//! so later reads are plain property reads.
+//!
+//! Because the two properties live on the prototype until first read,
+//! `Object.keys` and object spread do not see them before then.
+//! `JSON.stringify` does, through `toJSON`.
+//!
+//! Plain results are built as ordinary objects with `line` set up front and
+//! without `bytes`, so that they can be structured cloned.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr};
+use std::ptr;
+
+use napi::bindgen_prelude::*;
+use napi::{check_status, sys};
+
+use crate::{ByteWindow, SearchContext, SearchMatch, SearchResult};
+
+/// Hidden property holding the pending lines an instance belongs to.
+const LINES: &CStr = c"__lines";
+/// Hidden property holding an instance's index into its pending lines.
+const INDEX: &CStr = c"__line";
+
+const NOT_A_LINE: &str = "line and bytes are only available on search results";
+
+thread_local! {
+  /// The classes defined in each environment on this thread, by name.
+  /// Classes are defined once per environment, as V8 never frees a class
+  /// definition, and forgotten when the environment is torn down, as a
+  /// later one may reuse its address.
+  static CLASSES: RefCell<HashMap<(usize, &'static CStr), sys::napi_ref>> =
+    RefCell::default();
+}
+
+/// The `line` and `bytes` of an instance that have not been read yet.
+struct Pending {
+  line: Option<String>,
+  bytes: Option<Buffer>,
+}
+
+/// Convert `result`, leaving out its profile.
+pub(crate) unsafe fn search_result(
+  env: sys::napi_env,
+  result: SearchResult,
+  plain: bool,
+) -> Result<sys::napi_value> {
+  let raw = create_object(env)?;
+  let mut object = Object::from_raw(env, raw);
+  if plain {
+    object.set("matches", plain_matches(env, result.matches)?)?;
+    object.set("context", plain_context(env, result.context)?)?;
+  } else {
+    object.set("matches", search_matches(env, result.matches)?)?;
+    object.set("context", search_context(env, result.context)?)?;
+  }
+  object.set("finish", result.finish)?;
+  Ok(raw)
+}
+
+/// Convert a single match or context line.
+pub(crate) unsafe fn search_line(
+  env: sys::napi_env,
+  line: Either<SearchMatch, SearchContext>,
+  plain: bool,
+) -> Result<sys::napi_value> {
+  let array = match line {
+    Either::A(m) if plain => plain_matches(env, vec![m])?,
+    Either::A(m) => search_matches(env, vec![m])?,
+    Either::B(c) if plain => plain_context(env, vec![c])?,
+    Either::B(c) => search_context(env, vec![c])?,
+  };
+  let mut value = ptr::null_mut();
+  check_status!(sys::napi_get_element(env, array.raw(), 0, &mut value))?;
+  Ok(value)
+}
+
+unsafe fn search_matches(env: sys::napi_env, matches: Vec<SearchMatch>) -> Result<Object<'static>> {
+  let (pending, rest): (Vec<_>, Vec<_>) = matches
+    .into_iter()
+    .map(|m| {
+      let pending = Pending {
+        line: Some(m.line),
+        bytes: Some(m.bytes),
+      };
+      (
+        pending,
+        (
+          m.line_number,
+          m.absolute_byte_offset,
+          m.matches,
+          m.windows,
+          m.more_matches,
+        ),
+      )
+    })
+    .unzip();
+  let class = class(env, c"SearchMatch")?;
+  let lines = external(env, pending)?;
+  let array = create_array(env, rest.len())?;
+  for (i, (line_number, absolute_byte_offset, matches, windows, more_matches)) in
+    rest.into_iter().enumerate()
+  {
+    let mut fields = Vec::with_capacity(5);
+    if let Some(line_number) = line_number {
+      fields.push((c"lineNumber", u32::to_napi_value(env, line_number)?));
+    }
+    fields.push((
+      c"absoluteByteOffset",
+      i64::to_napi_value(env, absolute_byte_offset)?,
+    ));
+    fields.push((c"matches", ToNapiValue::to_napi_value(env, matches)?));
+    if let Some(windows) = windows {
+      fields.push((c"windows", ToNapiValue::to_napi_value(env, windows)?));
+    }
+    if let Some(more_matches) = more_matches {
+      fields.push((c"moreMatches", bool::to_napi_value(env, more_matches)?));
+    }
+    let instance = instance(env, class, lines, i, &fields)?;
+    check_status!(sys::napi_set_element(env, array, i as u32, instance))?;
+  }
+  Ok(Object::from_raw(env, array))
+}
+
+unsafe fn search_context(
+  env: sys::napi_env,
+  context: Vec<SearchContext>,
+) -> Result<Object<'static>> {
+  let (pending, rest): (Vec<_>, Vec<_>) = context
+    .into_iter()
+    .map(|c| {
+      let pending = Pending {
+        line: Some(c.line),
+        bytes: Some(c.bytes),
+      };
+      (pending, (c.line_number, c.absolute_byte_offset, c.kind))
+    })
+    .unzip();
+  let class = class(env, c"SearchContext")?;
+  let lines = external(env, pending)?;
+  let array = create_array(env, rest.len())?;
+  for (i, (line_number, absolute_byte_offset, kind)) in rest.into_iter().enumerate() {
+    let mut fields = Vec::with_capacity(3);
+    if let Some(line_number) = line_number {
+      fields.push((c"lineNumber", u32::to_napi_value(env, line_number)?));
+    }
+    fields.push((
+      c"absoluteByteOffset",
+      i64::to_napi_value(env, absolute_byte_offset)?,
+    ));
+    fields.push((c"kind", ToNapiValue::to_napi_value(env, kind)?));
+    let instance = instance(env, class, lines, i, &fields)?;
+    check_status!(sys::napi_set_element(env, array, i as u32, instance))?;
+  }
+  Ok(Object::from_raw(env, array))
+}
+
+unsafe fn plain_matches(env: sys::napi_env, matches: Vec<SearchMatch>) -> Result<Object<'static>> {
+  let array = create_array(env, matches.len())?;
+  for (i, m) in matches.into_iter().enumerate() {
+    let mut fields = Vec::with_capacity(6);
+    if let Some(line_number) = m.line_number {
+      fields.push((c"lineNumber", u32::to_napi_value(env, line_number)?));
+    }
+    fields.push((
+      c"absoluteByteOffset",
+      i64::to_napi_value(env, m.absolute_byte_offset)?,
+    ));
+    fields.push((c"line", String::to_napi_value(env, m.line)?));
+    fields.push((c"matches", ToNapiValue::to_napi_value(env, m.matches)?));
+    if let Some(windows) = m.windows {
+      fields.push((c"windows", plain_windows(env, windows)?));
+    }
+    if let Some(more_matches) = m.more_matches {
+      fields.push((c"moreMatches", bool::to_napi_value(env, more_matches)?));
+    }
+    let object = plain_object(env, &fields)?;
+    check_status!(sys::napi_set_element(env, array, i as u32, object))?;
+  }
+  Ok(Object::from_raw(env, array))
+}
+
+unsafe fn plain_context(
+  env: sys::napi_env,
+  context: Vec<SearchContext>,
+) -> Result<Object<'static>> {
+  let array = create_array(env, context.len())?;
+  for (i, c) in context.into_iter().enumerate() {
+    let mut fields = Vec::with_capacity(4);
+    if let Some(line_number) = c.line_number {
+      fields.push((c"lineNumber", u32::to_napi_value(env, line_number)?));
+    }
+    fields.push((
+      c"absoluteByteOffset",
+      i64::to_napi_value(env, c.absolute_byte_offset)?,
+    ));
+    fields.push((c"line", String::to_napi_value(env, c.line)?));
+    fields.push((c"kind", ToNapiValue::to_napi_value(env, c.kind)?));
+    let object = plain_object(env, &fields)?;
+    check_status!(sys::napi_set_element(env, array, i as u32, object))?;
+  }
+  Ok(Object::from_raw(env, array))
+}
+
+unsafe fn plain_windows(env: sys::napi_env, windows: Vec<ByteWindow>) -> Result<sys::napi_value> {
+  let array = create_array(env, windows.len())?;
+  for (i, w) in windows.into_iter().enumerate() {
+    let fields = [
+      (
+        c"absoluteByteOffset",
+        i64::to_napi_value(env, w.absolute_byte_offset)?,
+      ),
+      (c"length", u32::to_napi_value(env, w.length)?),
+      (c"matchStart", u32::to_napi_value(env, w.match_start)?),
+      (c"matchEnd", u32::to_napi_value(env, w.match_end)?),
+    ];
+    let object = plain_object(env, &fields)?;
+    check_status!(sys::napi_set_element(env, array, i as u32, object))?;
+  }
+  Ok(array)
+}
+
+/// Create an ordinary object with `fields` as its properties.
+unsafe fn plain_object(
+  env: sys::napi_env,
+  fields: &[(&'static CStr, sys::napi_value)],
+) -> Result<sys::napi_value> {
+  let raw = create_object(env)?;
+  let properties = fields
+    .iter()
+    .map(|&(name, value)| descriptor(name, value, VALUE))
+    .collect::<Vec<_>>();
+  check_status!(sys::napi_define_properties(
+    env,
+    raw,
+    properties.len(),
+    properties.as_ptr()
+  ))?;
+  Ok(raw)
+}
+
+unsafe fn create_object(env: sys::napi_env) -> Result<sys::napi_value> {
+  let mut raw = ptr::null_mut();
+  check_status!(sys::napi_create_object(env, &mut raw))?;
+  Ok(raw)
+}
+
+unsafe fn create_array(env: sys::napi_env, len: usize) -> Result<sys::napi_value> {
+  let mut raw = ptr::null_mut();
+  check_status!(sys::napi_create_array_with_length(env, len, &mut raw))?;
+  Ok(raw)
+}
+
+/// Move `lines` into a JavaScript value that frees them once no instance
+/// refers to it.
+unsafe fn external(env: sys::napi_env, lines: Vec<Pending>) -> Result<sys::napi_value> {
+  let lines = Box::into_raw(Box::new(lines));
+  let mut raw = ptr::null_mut();
+  let status = sys::napi_create_external(
+    env,
+    lines.cast(),
+    Some(drop_lines),
+    ptr::null_mut(),
+    &mut raw,
+  );
+  if let Err(e) = check_status!(status) {
+    drop(Box::from_raw(lines));
+    return Err(e);
+  }
+  Ok(raw)
+}
+
+unsafe extern "C" fn drop_lines(_env: sys::napi_env, data: *mut c_void, _hint: *mut c_void) {
+  drop(Box::from_raw(data.cast::<Vec<Pending>>()));
+}
+
+/// The class called `name` with lazy `line` and `bytes`, defined on first
+/// use in each environment.
+unsafe fn class(env: sys::napi_env, name: &'static CStr) -> Result<sys::napi_value> {
+  let key = (env as usize, name);
+  let mut class = ptr::null_mut();
+  if let Some(reference) = CLASSES.with(|classes| classes.borrow().get(&key).copied()) {
+    check_status!(sys::napi_get_reference_value(env, reference, &mut class))?;
+    return Ok(class);
+  }
+  let properties = [
+    accessor(c"line", get_line, set_line),
+    accessor(c"bytes", get_bytes, set_bytes),
+    sys::napi_property_descriptor {
+      method: Some(to_json),
+      ..descriptor(
+        c"toJSON",
+        ptr::null_mut(),
+        sys::PropertyAttributes::writable,
+      )
+    },
+  ];
+  check_status!(sys::napi_define_class(
+    env,
+    name.as_ptr(),
+    -1,
+    Some(construct),
+    ptr::null_mut(),
+    properties.len(),
+    properties.as_ptr(),
+    &mut class
+  ))?;
+  let first = CLASSES.with(|classes| !classes.borrow().keys().any(|&(e, _)| e == key.0));
+  if first {
+    check_status!(sys::napi_add_env_cleanup_hook(
+      env,
+      Some(forget_classes),
+      env.cast()
+    ))?;
+  }
+  let mut reference = ptr::null_mut();
+  check_status!(sys::napi_create_reference(env, class, 1, &mut reference))?;
+  CLASSES.with(|classes| classes.borrow_mut().insert(key, reference));
+  Ok(class)
+}
+
+/// Delete the references to the classes of an environment being torn down.
+unsafe extern "C" fn forget_classes(env: *mut c_void) {
+  let env: sys::napi_env = env.cast();
+  CLASSES.with(|classes| {
+    classes.borrow_mut().retain(|&(e, _), &mut reference| {
+      if e != env as usize {
+        return true;
+      }
+      sys::napi_delete_reference(env, reference);
+      false
+    })
+  });
+}
+
+/// Create the instance of `class` for line `index` of `lines`, with
+/// `fields` as its own properties.
+unsafe fn instance(
+  env: sys::napi_env,
+  class: sys::napi_value,
+  lines: sys::napi_value,
+  index: usize,
+  fields: &[(&'static CStr, sys::napi_value)],
+) -> Result<sys::napi_value> {
+  let mut raw = ptr::null_mut();
+  check_status!(sys::napi_new_instance(env, class, 0, ptr::null(), &mut raw))?;
+  let mut properties = fields
+    .iter()
+    .map(|&(name, value)| descriptor(name, value, VALUE))
+    .collect::<Vec<_>>();
+  let index = u32::to_napi_value(env, index as u32)?;
+  properties.push(descriptor(LINES, lines, sys::PropertyAttributes::default));
+  properties.push(descriptor(INDEX, index, sys::PropertyAttributes::default));
+  check_status!(sys::napi_define_properties(
+    env,
+    raw,
+    properties.len(),
+    properties.as_ptr()
+  ))?;
+  Ok(raw)
+}
+
+/// Attributes of an ordinary data property.
+const VALUE: sys::napi_property_attributes = sys::PropertyAttributes::writable
+  | sys::PropertyAttributes::enumerable
+  | sys::PropertyAttributes::configurable;
+
+fn descriptor(
+  name: &'static CStr,
+  value: sys::napi_value,
+  attributes: sys::napi_property_attributes,
+) -> sys::napi_property_descriptor {
+  sys::napi_property_descriptor {
+    utf8name: name.as_ptr(),
+    name: ptr::null_mut(),
+    method: None,
+    getter: None,
+    setter: None,
+    value,
+    attributes,
+    data: ptr::null_mut(),
+  }
+}
+
+type Callback = unsafe extern "C" fn(sys::napi_env, sys::napi_callback_info) -> sys::napi_value;
+
+fn accessor(
+  name: &'static CStr,
+  getter: Callback,
+  setter: Callback,
+) -> sys::napi_property_descriptor {
+  sys::napi_property_descriptor {
+    getter: Some(getter),
+    setter: Some(setter),
+    ..descriptor(name, ptr::null_mut(), sys::PropertyAttributes::configurable)
+  }
+}
+
+unsafe extern "C" fn construct(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  or_throw(env, call_info(env, info).map(|(this, _)| this))
+}
+
+unsafe extern "C" fn get_line(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  materialize(env, info, c"line", |pending| {
+    pending
+      .line
+      .take()
+      .map(|line| String::to_napi_value(env, line))
+  })
+}
+
+unsafe extern "C" fn get_bytes(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  materialize(env, info, c"bytes", |pending| {
+    pending
+      .bytes
+      .take()
+      .map(|bytes| Buffer::to_napi_value(env, bytes))
+  })
+}
+
+unsafe extern "C" fn set_line(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  overwrite(env, info, c"line", |pending| pending.line = None)
+}
+
+unsafe extern "C" fn set_bytes(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> sys::napi_value {
+  overwrite(env, info, c"bytes", |pending| pending.bytes = None)
+}
+
+/// `toJSON`: read both lazy properties so they are serialized with the
+/// instance.
+unsafe extern "C" fn to_json(env: sys::napi_env, info: sys::napi_callback_info) -> sys::napi_value {
+  let result = (|| {
+    let (this, _) = call_info(env, info)?;
+    for name in [c"line", c"bytes"] {
+      let mut value = ptr::null_mut();
+      check_status!(sys::napi_get_named_property(
+        env,
+        this,
+        name.as_ptr(),
+        &mut value
+      ))?;
+    }
+    Ok(this)
+  })();
+  or_throw(env, result)
+}
+
+/// Getter body: convert the pending value and store it on the instance.
+unsafe fn materialize(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+  name: &'static CStr,
+  take: impl FnOnce(&mut Pending) -> Option<Result<sys::napi_value>>,
+) -> sys::napi_value {
+  let result = (|| {
+    let (this, _) = call_info(env, info)?;
+    let Some(value) = take(pending(env, this)?) else {
+      return Ok(ptr::null_mut());
+    };
+    let value = value?;
+    define_value(env, this, name, value)?;
+    Ok(value)
+  })();
+  or_throw(env, result)
+}
+
+/// Setter body: store the assigned value on the instance and drop the
+/// pending one.
+unsafe fn overwrite(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+  name: &'static CStr,
+  clear: impl FnOnce(&mut Pending),
+) -> sys::napi_value {
+  let result = (|| {
+    let (this, value) = call_info(env, info)?;
+    clear(pending(env, this)?);
+    define_value(env, this, name, value)?;
+    Ok(ptr::null_mut())
+  })();
+  or_throw(env, result)
+}
+
+/// The receiver and first argument of a callback.
+unsafe fn call_info(
+  env: sys::napi_env,
+  info: sys::napi_callback_info,
+) -> Result<(sys::napi_value, sys::napi_value)> {
+  let mut argc = 1;
+  let mut arg = ptr::null_mut();
+  let mut this = ptr::null_mut();
+  check_status!(sys::napi_get_cb_info(
+    env,
+    info,
+    &mut argc,
+    &mut arg,
+    &mut this,
+    ptr::null_mut()
+  ))?;
+  if argc == 0 {
+    check_status!(sys::napi_get_undefined(env, &mut arg))?;
+  }
+  Ok((this, arg))
+}
+
+/// The pending values of instance `this`.
+unsafe fn pending<'a>(env: sys::napi_env, this: sys::napi_value) -> Result<&'a mut Pending> {
+  let mut lines = ptr::null_mut();
+  check_status!(sys::napi_get_named_property(
+    env,
+    this,
+    LINES.as_ptr(),
+    &mut lines
+  ))?;
+  let mut data = ptr::null_mut();
+  check_status!(
+    sys::napi_get_value_external(env, lines, &mut data),
+    "{}",
+    NOT_A_LINE
+  )?;
+  let mut index = ptr::null_mut();
+  check_status!(sys::napi_get_named_property(
+    env,
+    this,
+    INDEX.as_ptr(),
+    &mut index
+  ))?;
+  let mut i = 0;
+  check_status!(
+    sys::napi_get_value_uint32(env, index, &mut i),
+    "{}",
+    NOT_A_LINE
+  )?;
+  let lines = &mut *data.cast::<Vec<Pending>>();
+  lines
+    .get_mut(i as usize)
+    .ok_or_else(|| Error::new(Status::InvalidArg, NOT_A_LINE))
+}
+
+unsafe fn define_value(
+  env: sys::napi_env,
+  object: sys::napi_value,
+  name: &'static CStr,
+  value: sys::napi_value,
+) -> Result<()> {
+  let property = descriptor(name, value, VALUE);
+  check_status!(sys::napi_define_properties(env, object, 1, &property))
+}
+
+unsafe fn or_throw(env: sys::napi_env, result: Result<sys::napi_value>) -> sys::napi_value {
+  result.unwrap_or_else(|e| {
+    JsError::from(e).throw_into(env);
+    ptr::null_mut()
+  })
+}
@@ -1,21 +1,51 @@
 #![deny(clippy::all)]
 
-use std::io::Cursor;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use grep::matcher::Matcher;
+use aho_corasick::AhoCorasick;
+use grep::matcher::{ByteSet, Captures, Matcher, NoError};
 use grep::regex::{
   RegexMatcher as GrepRegexMatcher, RegexMatcherBuilder as GrepRegexMatcherBuilder,
 };
 use grep::searcher::{
-  BinaryDetection as GrepBinaryDetection, Searcher as GrepSearcher,
+  BinaryDetection as GrepBinaryDetection, MmapChoice, Searcher as GrepSearcher,
   SearcherBuilder as GrepSearcherBuilder, Sink, SinkContext, SinkContextKind, SinkFinish,
   SinkMatch,
 };
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+mod chunks;
+mod dir;
+mod engine;
+mod error;
+mod files;
+mod hash;
+mod highlight;
+mod interpolate;
+mod iter;
+mod keywords;
+mod lazy;
+mod metrics;
+mod normalize;
+mod registry;
+mod sarif;
+mod service;
+mod shared;
+mod stream;
+mod transcode;
+
+use engine::{Engine, PatternOptions};
+use error::Failure;
+use interpolate::Template;
+use normalize::{Fold, Normalized};
+
 // ============================================================================
 // Enums
 // ============================================================================
@@ -52,6 +82,187 @@ pub enum BinaryDetectionMode {
   Convert,
 }
 
+/// What to do when a file cannot be read during a multi-file search.
+#[napi(string_enum)]
+#[derive(Clone, Copy, Default)]
+pub enum ReadErrorAction {
+  /// Fail the whole search with the read error.
+  #[default]
+  Abort,
+  /// Skip the file and record the error in the result.
+  Skip,
+}
+
+/// What to do with byte sequences that cannot be decoded while an input
+/// with a byte order mark is transcoded.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum EncodingErrorAction {
+  /// Replace them with U+FFFD, as without a policy, and count them.
+  Replace,
+  /// Leave the lines that contain them out of the result and list them in
+  /// `finish.undecodableLines`.
+  SkipLine,
+  /// Fail the search.
+  Abort,
+}
+
+/// How a byte order mark is reported when BOM sniffing is enabled.
+#[napi(string_enum)]
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum BomHandling {
+  /// Leave the BOM out of the first line, and count offsets from just
+  /// after it.
+  #[default]
+  Strip,
+  /// Keep the BOM at the start of the first line as U+FEFF, and count
+  /// offsets from the start of the input including it. For UTF-8 inputs
+  /// offsets are then byte offsets into the input.
+  Preserve,
+}
+
+/// The algorithm of `contentHash`.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum HashAlgorithm {
+  /// 64-bit xxHash, fast but not cryptographic.
+  Xxh64,
+  /// SHA-256.
+  Sha256,
+}
+
+/// What to do when a search collects more than `maxResultBytes`.
+#[napi(string_enum)]
+#[derive(Clone, Copy, Default)]
+pub enum ResultLimitAction {
+  /// Stop the search and return what was collected, with
+  /// `finish.truncated` set.
+  #[default]
+  Truncate,
+  /// Fail the search.
+  Error,
+}
+
+/// Unicode normalization form applied before matching.
+#[napi(string_enum)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum NormalizationForm {
+  /// Canonical composition: composed and decomposed forms of the same
+  /// character (e.g. `é` and `e` + U+0301) match each other.
+  Nfc,
+  /// Compatibility composition: additionally folds compatibility variants
+  /// such as ligatures and full-width forms (e.g. `ﬁ` matches `fi`).
+  Nfkc,
+}
+
+/// How a path matched a gitignore.
+#[napi(string_enum)]
+pub enum IgnoreMatch {
+  /// No rule matched the path.
+  None,
+  /// The last matching rule ignores the path.
+  Ignore,
+  /// The last matching rule is a `!` rule that re-includes the path.
+  Whitelist,
+}
+
+/// How search results are ordered before they are returned.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum SortBy {
+  /// By path, then by position within the file.
+  Path,
+  /// By number of matches, most first.
+  MatchCount,
+  /// By the offset of the first match within its line, smallest first.
+  FirstMatchOffset,
+}
+
+/// Which match wins where the matches of `highlight` overlap.
+#[napi(string_enum)]
+#[derive(Clone, Copy, Default)]
+pub enum HighlightPrecedence {
+  /// The match of the earlier matcher, as a higher layer covers the ones
+  /// below it.
+  #[default]
+  Layer,
+  /// The longer match, or of two as long, the match of the earlier
+  /// matcher.
+  Longest,
+}
+
+/// A combination of `SearcherBuilder` options that cannot work together,
+/// reported by `SearcherBuilder.validate` and as the `code` of the error
+/// thrown by `SearcherBuilder.build`.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum ConfigConflict {
+  /// Passthru reports every line, so context lines would be ignored.
+  PassthruWithContext,
+  /// A heap limit of 0 leaves no way to search without memory maps.
+  HeapLimitWithoutMemoryMap,
+  /// Stopping at the first non-matching line after a match does not
+  /// combine with reporting non-matching lines.
+  StopOnNonmatchWithInvert,
+}
+
+impl ConfigConflict {
+  /// The name of the conflict in JavaScript.
+  fn name(self) -> &'static str {
+    match self {
+      ConfigConflict::PassthruWithContext => "PassthruWithContext",
+      ConfigConflict::HeapLimitWithoutMemoryMap => "HeapLimitWithoutMemoryMap",
+      ConfigConflict::StopOnNonmatchWithInvert => "StopOnNonmatchWithInvert",
+    }
+  }
+}
+
+/// The kind of failure an error reports, set as the error's `code`.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum ErrorCode {
+  /// A pattern failed to compile. The error has the `pattern`, its
+  /// `patternIndex` among those built and, for syntax errors, the `span`
+  /// of the error within it.
+  RegexSyntax,
+  /// An input could not be read. The error has the `path` of a file.
+  Io,
+  /// An input has data that is not valid in its encoding, and the
+  /// searcher's encoding error policy is `Abort`.
+  Undecodable,
+  /// The search was cancelled by a token or an `onTick` callback.
+  Cancelled,
+  /// An argument or option is invalid.
+  InvalidArgument,
+  /// A search went past a limit: its results exceed `maxResultBytes` with
+  /// the `Error` action, or a line does not fit in the heap limit.
+  LimitExceeded,
+}
+
+/// The part of a pattern that an error is about, in UTF-16 code units like
+/// JavaScript string indices.
+#[napi(object)]
+pub struct PatternSpan {
+  pub start: u32,
+  pub end: u32,
+}
+
+/// A bundle of defaults familiar from a command line tool, applied by
+/// `SearcherBuilder.preset` and `RegexMatcherBuilder.preset`.
+#[napi(string_enum)]
+#[derive(Clone, Copy)]
+pub enum Preset {
+  /// As `rg`: line numbers, binary files stop at the first NUL byte, BOMs
+  /// are sniffed, and patterns without uppercase letters ignore case.
+  Ripgrep,
+  /// As `grep`: no line numbers, binary files stop at the first NUL byte,
+  /// no BOM sniffing, and case-sensitive patterns.
+  Grep,
+  /// The least work per search: no line numbers, no binary detection, no
+  /// BOM sniffing, and case-sensitive patterns.
+  Minimal,
+}
+
 // ============================================================================
 // Result types
 // ============================================================================
@@ -65,6 +276,47 @@ pub struct MatchRange {
   pub end: u32,
 }
 
+/// A range of text highlighted by `highlight`.
+#[napi(object, object_from_js = false)]
+pub struct HighlightRange {
+  /// Start byte offset within the text.
+  pub start: u32,
+  /// End byte offset within the text.
+  pub end: u32,
+  /// The index of the matcher that matched, in the order given.
+  pub layer: u32,
+  /// The label of the matcher, if `labels` names it.
+  pub label: Option<String>,
+}
+
+/// Bytes surrounding a single match, taken without regard to line boundaries.
+#[napi(object)]
+pub struct ByteWindow {
+  /// The absolute byte offset of the first byte in the window.
+  pub absolute_byte_offset: i64,
+  /// The bytes of the window.
+  ///
+  /// Left out of plain results (see `SearcherBuilder.plainResults`).
+  pub bytes: Buffer,
+  /// The number of bytes in the window.
+  pub length: u32,
+  /// Start byte offset of the match within the window.
+  pub match_start: u32,
+  /// End byte offset of the match within the window.
+  pub match_end: u32,
+}
+
+/// A match found by a line-less binary scan.
+#[napi(object)]
+pub struct BinaryMatch {
+  /// The absolute byte offset of the start of the match.
+  pub start: i64,
+  /// The absolute byte offset of the end of the match.
+  pub end: i64,
+  /// Bytes around the match, if a window size was requested.
+  pub window: Option<ByteWindow>,
+}
+
 /// Represents a matching line found by the searcher.
 #[napi(object)]
 pub struct SearchMatch {
@@ -75,9 +327,17 @@ pub struct SearchMatch {
   /// The matched line content.
   pub line: String,
   /// The bytes of the matched line.
+  ///
+  /// Left out of plain results (see `SearcherBuilder.plainResults`).
   pub bytes: Buffer,
   /// All match ranges within the line.
   pub matches: Vec<MatchRange>,
+  /// Byte windows around each match, in the same order as `matches`.
+  ///
+  /// Only present when byte context is enabled on the searcher.
+  pub windows: Option<Vec<ByteWindow>>,
+  /// Set when `maxMatchesPerLine` left out further matches on the line.
+  pub more_matches: Option<bool>,
 }
 
 /// Represents a context line (before/after a match).
@@ -90,6 +350,8 @@ pub struct SearchContext {
   /// The context line content.
   pub line: String,
   /// The bytes of the context line.
+  ///
+  /// Left out of plain results (see `SearcherBuilder.plainResults`).
   pub bytes: Buffer,
   /// The kind of context (before, after, other).
   pub kind: ContextKind,
@@ -102,6 +364,119 @@ pub struct SearchFinish {
   pub byte_count: i64,
   /// Whether binary data was detected (if binary detection is enabled).
   pub binary_byte_offset: Option<i64>,
+  /// Approximate peak size in bytes of the buffer holding the input while
+  /// it was searched. Compare with `heapLimit` to see how close a search
+  /// came to the limit. For slices and memory maps this is the input size.
+  pub peak_buffer_bytes: i64,
+  /// Approximate size in bytes of the collected results.
+  pub result_bytes: i64,
+  /// Approximate peak memory used by the search in bytes: the buffer,
+  /// collected results and any normalized copy of the input.
+  pub peak_memory_bytes: i64,
+  /// Whether `maxMatches`, `maxMatchCount` or `maxResultBytes` was
+  /// reached, so the search may have stopped before the end of the input.
+  pub truncated: bool,
+  /// Where the search stopped, if it stopped before the end of the input
+  /// because of a match limit, `stopOnNonmatch` or binary data. Pass it as
+  /// `resumeFrom` to continue the search from there. Not set for inputs
+  /// with a byte order mark, which are searched transcoded.
+  pub resume_offset: Option<i64>,
+  /// The non-matching line at which `stopOnNonmatch` ended the search.
+  pub stopped_at: Option<StopPosition>,
+  /// The number of undecodable sequences in a transcoded input, when an
+  /// encoding error policy is set.
+  pub encoding_errors: Option<u32>,
+  /// The lines left out of the result because they contain undecodable
+  /// sequences, with `EncodingErrorAction.SkipLine`.
+  pub undecodable_lines: Option<Vec<u32>>,
+  /// The line endings in the input, if `lineEndingReport` is enabled.
+  pub line_endings: Option<LineEndings>,
+  /// The hash of the whole input as lowercase hex, if `contentHash` is set.
+  pub content_hash: Option<String>,
+  /// Whether `timeoutMs` stopped the search, in which case the result is
+  /// partial. Only set when a timeout is set.
+  pub timed_out: Option<bool>,
+}
+
+/// The line endings counted in a searched input.
+#[napi(object)]
+pub struct LineEndings {
+  /// Lines ended by `\n` alone.
+  pub lf: i64,
+  /// Lines ended by `\r\n`.
+  pub crlf: i64,
+  /// Whether both kinds occur.
+  pub mixed: bool,
+}
+
+/// A line at which a search stopped.
+#[napi(object)]
+pub struct StopPosition {
+  /// The line number (if line numbers are enabled).
+  pub line_number: Option<u32>,
+  /// The absolute byte offset of the start of the line.
+  pub absolute_byte_offset: i64,
+}
+
+/// A file that could not be read during a multi-file search.
+#[napi(object)]
+pub struct FileError {
+  /// The path of the file.
+  pub path: String,
+  /// The error message of the last failed read.
+  pub message: String,
+}
+
+/// Match counts for every (matcher, file) pair.
+#[napi(object)]
+pub struct MatchMatrix {
+  /// The searched paths, in input order.
+  pub paths: Vec<String>,
+  /// `counts[m][f]` is the number of matches of matcher `m` in `paths[f]`.
+  pub counts: Vec<Vec<u32>>,
+  /// Files skipped because of read errors (see `readErrorPolicy`).
+  pub errors: Vec<FileError>,
+}
+
+/// A keyword found by a `KeywordScanner`.
+#[napi(object)]
+pub struct KeywordMatch {
+  /// The keyword that matched.
+  pub keyword: String,
+  /// The index of the keyword in the word list.
+  pub index: u32,
+  /// The 1-based line number of the match.
+  pub line_number: u32,
+  /// The absolute byte offset of the start of the match.
+  pub start: i64,
+  /// The absolute byte offset of the end of the match.
+  pub end: i64,
+}
+
+/// The keywords found in one file by `KeywordScanner.scanPaths`.
+#[napi(object)]
+pub struct KeywordFileMatches {
+  pub path: String,
+  pub matches: Vec<KeywordMatch>,
+}
+
+/// Time spent in each phase of a search, in milliseconds.
+#[napi(object)]
+#[derive(Clone)]
+pub struct SearchProfile {
+  /// Opening and reading the input.
+  pub read_ms: f64,
+  /// Normalizing or folding the input (see `normalization`). Decoding done
+  /// by BOM sniffing is counted as matching.
+  pub transcode_ms: f64,
+  /// Running the matcher over the input.
+  pub match_ms: f64,
+  /// Collecting matches and context lines into results.
+  pub collect_ms: f64,
+  /// Converting the results into JavaScript values.
+  pub marshal_ms: f64,
+  /// Total time, including marshalling.
+  pub total_ms: f64,
 }
 
 /// Complete search result containing all matches and context.
@@ -113,6 +488,559 @@ pub struct SearchResult {
   pub context: Vec<SearchContext>,
   /// Summary information.
   pub finish: SearchFinish,
+  /// Phase timings, if profiling is enabled on the searcher.
+  pub profile: Option<SearchProfile>,
+}
+
+/// A file with matches, produced by a directory search.
+#[napi(object, object_from_js = false)]
+pub struct DirSearchEntry {
+  /// The path of the file.
+  pub path: String,
+  /// The result of searching the file.
+  #[napi(ts_type = "SearchResult")]
+  pub result: SearchOutput,
+}
+
+/// Counters of the searches run by the process, from `searchMetrics`.
+///
+/// Searches are counted when they collect or stream results; counting
+/// matches, match matrices and other searches that only count are not.
+#[napi(object, object_from_js = false)]
+pub struct SearchMetrics {
+  /// The number of searches started.
+  pub searches_started: i64,
+  /// The number of searches that finished, including those that stopped
+  /// early at a limit or a timeout.
+  pub searches_completed: i64,
+  /// The number of searches stopped by cancellation, including those
+  /// cancelled before they started.
+  pub searches_cancelled: i64,
+  /// The number of searches that failed for any other reason.
+  pub searches_failed: i64,
+  /// The number of searches running now.
+  pub searches_running: i64,
+  /// The number of bytes read by completed searches.
+  pub bytes_searched: i64,
+  /// The number of matches produced by completed searches.
+  pub matches: i64,
+}
+
+/// How long the jobs of one priority waited for a thread of a
+/// `SearchService`.
+#[napi(object, object_from_js = false)]
+pub struct QueueWaits {
+  /// The priority the jobs were queued with.
+  pub priority: i32,
+  /// The number of jobs that have started.
+  pub started: u32,
+  /// The mean time the jobs waited.
+  pub mean_wait_ms: f64,
+  /// The longest time a job waited.
+  pub max_wait_ms: f64,
+}
+
+/// The counts of a finished `searchDirParallel`.
+#[napi(object, object_from_js = false)]
+pub struct DirSearchFinish {
+  /// The number of files searched.
+  pub files_searched: u32,
+  /// The number of files with at least one match.
+  pub files_with_matches: u32,
+}
+
+/// Binary data found by a streaming search.
+#[napi(object, object_from_js = false)]
+pub struct BinaryEvent {
+  /// The absolute byte offset of the binary data.
+  pub offset: i64,
+  /// What the searcher did: `Quit` ends the search here, and `Convert`
+  /// goes on searching with NUL bytes replaced.
+  pub action: BinaryDetectionMode,
+}
+
+/// An incoherent combination of options, found by
+/// `SearcherBuilder.validate`.
+#[napi(object)]
+pub struct ConfigProblem {
+  /// Which options conflict.
+  pub code: ConfigConflict,
+  /// A description of the conflict.
+  pub message: String,
+}
+
+/// The outcome of searching one input of `Searcher.searchMany`.
+#[napi(object, object_from_js = false)]
+pub struct SearchManyEntry {
+  /// The position of the input in the inputs.
+  pub index: u32,
+  /// The path of the input, if it was given as a path.
+  pub path: Option<String>,
+  /// The result of the search, unless it failed.
+  #[napi(ts_type = "SearchResult")]
+  pub result: Option<SearchOutput>,
+  /// Why the search failed, if it did.
+  pub error: Option<String>,
+  /// The code of `error`.
+  pub error_code: Option<ErrorCode>,
+}
+
+/// A file searched by `Searcher.searchDir`.
+#[napi(object, object_to_js = false, object_from_js = false)]
+pub struct FileSearchResult {
+  /// The path of the file.
+  pub path: String,
+  /// All matching lines.
+  pub matches: Vec<SearchMatch>,
+  /// All context lines.
+  pub context: Vec<SearchContext>,
+  /// Summary information.
+  pub finish: SearchFinish,
+  /// Phase timings, if profiling is enabled on the searcher.
+  pub profile: Option<SearchProfile>,
+  /// Why the file could not be read, if the read error policy skipped it.
+  pub error: Option<String>,
+  /// The code of `error`.
+  pub error_code: Option<ErrorCode>,
+  /// Whether lines are converted to plain objects (see `SearchOutput`).
+  #[napi(skip)]
+  pub plain: bool,
+}
+
+/// A rule of a `RulePack`.
+#[napi(object)]
+pub struct Rule {
+  /// The name findings are grouped under. Names must be unique.
+  pub name: String,
+  /// The regular expression to search for.
+  pub pattern: String,
+  /// Match the pattern case insensitively.
+  pub case_insensitive: Option<bool>,
+  /// Only match the pattern on word boundaries.
+  pub word: Option<bool>,
+  /// Treat the pattern as a literal string.
+  pub fixed_string: Option<bool>,
+  /// A label passed through to the findings, such as `"error"`.
+  pub severity: Option<String>,
+  /// Globs of the files the rule applies to, relative to the searched
+  /// directory, as with `rg -g`: a file must match one of them unless all
+  /// start with `!`, which excludes. All files by default.
+  pub globs: Option<Vec<String>>,
+}
+
+/// The findings of one rule, produced by `RulePack.run`.
+#[napi(object, object_from_js = false)]
+pub struct RuleFindings {
+  /// The name of the rule.
+  pub name: String,
+  /// The severity of the rule, if it has one.
+  pub severity: Option<String>,
+  /// The files the rule matches, in a stable order.
+  pub files: Vec<FileSearchResult>,
+}
+
+/// The result of `RulePack.run`.
+#[napi(object, object_from_js = false)]
+pub struct RuleReport {
+  /// The findings of every rule, in rule order.
+  pub rules: Vec<RuleFindings>,
+  /// Files skipped because of read errors (see `readErrorPolicy`).
+  pub errors: Vec<FileError>,
+}
+
+/// A document with matches, produced by `DocumentSet.search`.
+#[napi(object, object_from_js = false)]
+pub struct DocumentMatch {
+  /// The name the document was added under.
+  pub name: String,
+  /// The result of searching the document.
+  #[napi(ts_type = "SearchResult")]
+  pub result: SearchOutput,
+}
+
+/// Where the lines of a `SearchResult` start, read from the result passed
+/// to `Searcher.resultCursor`. Only these fields are read, so results
+/// without line bytes can be passed too.
+#[napi(object, object_to_js = false)]
+pub struct ResultLines {
+  pub matches: Vec<LinePosition>,
+  pub context: Vec<LinePosition>,
+}
+
+/// Where a line of a `SearchResult` starts.
+#[napi(object, object_to_js = false)]
+pub struct LinePosition {
+  pub line_number: Option<u32>,
+  pub absolute_byte_offset: i64,
+}
+
+/// A `SearchResult` passed to `mergeResults`. Lines and windows may come
+/// without their `bytes`, as in plain results.
+#[napi(object, object_to_js = false)]
+pub struct MergeInput {
+  pub matches: Vec<MergeMatch>,
+  pub context: Vec<MergeContext>,
+  pub finish: SearchFinish,
+}
+
+/// A matching line of a `MergeInput`.
+#[napi(object, object_to_js = false)]
+pub struct MergeMatch {
+  pub line_number: Option<u32>,
+  pub absolute_byte_offset: i64,
+  pub line: String,
+  pub bytes: Option<Buffer>,
+  pub matches: Vec<MatchRange>,
+  pub windows: Option<Vec<MergeWindow>>,
+  pub more_matches: Option<bool>,
+}
+
+/// A byte window of a `MergeMatch`.
+#[napi(object, object_to_js = false)]
+pub struct MergeWindow {
+  pub absolute_byte_offset: i64,
+  pub bytes: Option<Buffer>,
+  pub length: u32,
+  pub match_start: u32,
+  pub match_end: u32,
+}
+
+/// A context line of a `MergeInput`.
+#[napi(object, object_to_js = false)]
+pub struct MergeContext {
+  pub line_number: Option<u32>,
+  pub absolute_byte_offset: i64,
+  pub line: String,
+  pub bytes: Option<Buffer>,
+  pub kind: ContextKind,
+}
+
+// ============================================================================
+// Options
+// ============================================================================
+
+/// Options for finding matches.
+#[napi(object)]
+#[derive(Default)]
+pub struct FindOptions {
+  /// Report overlapping occurrences instead of resuming after each match.
+  pub overlapping: Option<bool>,
+}
+
+/// Options for `highlight`.
+#[napi(object)]
+#[derive(Default)]
+pub struct HighlightOptions {
+  /// A label for each matcher, in the same order, copied to its ranges.
+  pub labels: Option<Vec<String>>,
+  /// Which match wins where matches overlap. Defaults to `Layer`.
+  pub precedence: Option<HighlightPrecedence>,
+}
+
+/// A capture group of a pattern, produced by `RegexMatcher.captureGroups`.
+#[napi(object)]
+pub struct CaptureGroup {
+  /// The index of the group, as in `$1`.
+  pub index: u32,
+  /// The name of the group, as in `(?P<name>...)`, if it has one.
+  pub name: Option<String>,
+  /// The index of the innermost group containing this one, if any.
+  pub parent: Option<u32>,
+  /// The position of the pattern the group is in, for matchers built from
+  /// several patterns.
+  pub pattern: u32,
+}
+
+/// Literals every match of a matcher contains, produced by
+/// `RegexMatcher.prefilterLiterals`.
+#[napi(object)]
+pub struct RequiredLiterals {
+  /// The alternatives: a text can only match if it contains every literal
+  /// of at least one of them.
+  pub any_of: Vec<Vec<String>>,
+  /// Whether the matcher ignores case, so that the literals must be looked
+  /// up case-insensitively.
+  pub case_insensitive: bool,
+}
+
+/// Options controlling replacement template expansion.
+#[napi(object)]
+#[derive(Default)]
+pub struct ReplaceOptions {
+  /// Reject references to capture groups that do not exist in the pattern.
+  ///
+  /// By default such references expand to an empty string (or to their
+  /// `${name:-default}` fallback).
+  pub strict: Option<bool>,
+}
+
+/// Policy for read failures during multi-file searches.
+///
+/// Useful on network filesystems, where a single busy file would otherwise
+/// fail an entire scan.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct ReadErrorPolicy {
+  /// What to do once all retries have failed. Defaults to `Abort`.
+  pub action: Option<ReadErrorAction>,
+  /// How many times to retry a read that failed with an error that may
+  /// pass: one that was interrupted, would have blocked, or found the file
+  /// busy. Other errors, such as a missing file, fail at once. Only
+  /// searches off the JavaScript thread retry, such as async searches and
+  /// those that produce their results through iterators or callbacks.
+  /// Defaults to 0.
+  pub retries: Option<u32>,
+  /// Delay before the first retry in milliseconds, doubled for every
+  /// further retry. Defaults to 0.
+  pub backoff_ms: Option<u32>,
+}
+
+/// A pattern with its own flags, for use with `buildMany`.
+///
+/// Unset flags fall back to the builder configuration.
+#[napi(object)]
+pub struct PatternEntry {
+  pub pattern: String,
+  /// Match this pattern case insensitively (or, when `false`, case
+  /// sensitively even if the builder is case insensitive).
+  pub case_insensitive: Option<bool>,
+  /// Only match this pattern on word boundaries.
+  pub word: Option<bool>,
+  /// Treat this pattern as a literal string.
+  pub fixed_string: Option<bool>,
+}
+
+/// Options for reading newline-separated pattern lists.
+#[napi(object)]
+#[derive(Default)]
+pub struct PatternFileOptions {
+  /// Skip lines that are empty or contain only whitespace.
+  ///
+  /// By default an empty line is an empty pattern, which matches everything.
+  pub skip_blank_lines: Option<bool>,
+  /// Skip lines whose first non-whitespace character is `#`.
+  pub skip_comments: Option<bool>,
+}
+
+/// Options for building a `KeywordScanner`.
+#[napi(object)]
+#[derive(Default)]
+pub struct KeywordScannerOptions {
+  /// Match keywords ASCII case insensitively.
+  pub case_insensitive: Option<bool>,
+  /// Only report keywords that start and end on word boundaries. Defaults
+  /// to true.
+  pub word: Option<bool>,
+  /// When loading from a file, skip lines whose first non-whitespace
+  /// character is `#`.
+  pub skip_comments: Option<bool>,
+}
+
+/// Options for creating a `DocumentSet`.
+#[napi(object)]
+#[derive(Default)]
+pub struct DocumentSetOptions {
+  /// Skip documents that lack the bytes of every literal a match could
+  /// start with, without searching them. Defaults to true.
+  pub prefilter: Option<bool>,
+}
+
+/// Options for building a `Gitignore`.
+#[napi(object)]
+#[derive(Default)]
+pub struct GitignoreOptions {
+  /// The directory the rules are relative to, as the directory containing
+  /// a `.gitignore` file. Defaults to the current directory.
+  pub root: Option<String>,
+  /// Match paths case insensitively.
+  pub case_insensitive: Option<bool>,
+}
+
+/// Options for line-less binary scans.
+#[napi(object)]
+#[derive(Default)]
+pub struct BinaryScanOptions {
+  /// Number of bytes to include before each match in its window.
+  pub before_bytes: Option<u32>,
+  /// Number of bytes to include after each match in its window.
+  pub after_bytes: Option<u32>,
+  /// Stop after this many matches.
+  pub max_matches: Option<u32>,
+}
+
+/// Options for creating a `StreamMatcher`.
+#[napi(object)]
+#[derive(Default)]
+pub struct StreamMatcherOptions {
+  /// The length in bytes of the longest match to report in full. Longer
+  /// matches may be reported cut short. Defaults to 64 KiB.
+  pub max_match_length: Option<u32>,
+}
+
+/// Options for `RegexMatcher.searchAll`.
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchAllOptions {
+  /// Search the inputs on several threads, as many as the searcher's
+  /// `threads`. Disabled by default.
+  pub parallel: Option<bool>,
+}
+
+/// Options for a single search.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SearchOptions {
+  /// Start the search at this absolute byte offset, usually the
+  /// `finish.resumeOffset` of an earlier search of the same input. Offsets
+  /// and line numbers in the result still count from the start of the
+  /// input. Inputs with a byte order mark cannot be resumed, so any other
+  /// offset is an error for them.
+  pub resume_from: Option<i64>,
+  /// The line number of the first line of the input, when it is part of a
+  /// larger document such as an editor viewport. Defaults to 1.
+  pub first_line_number: Option<u32>,
+  /// The absolute byte offset of the start of the input within a larger
+  /// document. Defaults to 0. Reported offsets and `resumeFrom` are
+  /// offsets into the document.
+  pub base_offset: Option<i64>,
+  /// How many bytes a synchronous search reads between calls to its
+  /// `onTick` callback. Defaults to 16 MiB.
+  pub tick_bytes: Option<u32>,
+}
+
+/// Options for streaming searches.
+#[napi(object)]
+#[derive(Default)]
+pub struct StreamSearchOptions {
+  /// How many lines to search ahead of the consumer. Defaults to 64.
+  pub queue_size: Option<u32>,
+}
+
+/// Options for `Searcher.searchPathStreaming`.
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct StreamingSearchOptions {
+  /// How many lines to queue for the callbacks before the search waits.
+  /// Defaults to 64.
+  pub queue_size: Option<u32>,
+  /// Called when binary data is first found, after the lines before it.
+  #[napi(ts_type = "(binary: BinaryEvent) => void")]
+  pub on_binary: Option<stream::BinaryCallback>,
+}
+
+/// Options for directory searches.
+///
+/// Files are filtered as ripgrep does by default: hidden files, those below
+/// hidden directories, and those ignored by `.gitignore`, `.ignore` and
+/// git's exclude files are skipped, and symbolic links to directories are
+/// not followed.
+#[napi(object)]
+#[derive(Default)]
+pub struct DirSearchOptions {
+  /// How many files with matches to search ahead of the consumer. Defaults
+  /// to 16. Only used by `searchDirIter` and `searchDirParallel`.
+  pub queue_size: Option<u32>,
+  /// The number of threads `searchDirParallel` walks and searches on.
+  /// Defaults to the searcher's `threads`.
+  pub threads: Option<u32>,
+  /// Search hidden files and directories, those whose names start with a
+  /// dot, as `rg --hidden` does. Defaults to false.
+  pub hidden: Option<bool>,
+  /// Skip files ignored by `.gitignore` (within git repositories),
+  /// `.ignore` and git's exclude files, in the directory and its parents.
+  /// Defaults to true. The options below turn each source on or off.
+  pub ignore_files: Option<bool>,
+  /// Respect `.gitignore` files. Defaults to `ignoreFiles`.
+  pub git_ignore: Option<bool>,
+  /// Respect `.ignore` files, which work like `.gitignore` files outside
+  /// git too. Defaults to `ignoreFiles`.
+  pub dot_ignore: Option<bool>,
+  /// Names of further ignore files to respect in every directory, such as
+  /// `.searchignore` or `.eslintignore`, written like `.gitignore` files and
+  /// taking precedence over all other ignore rules. Respected along with
+  /// `.ignore` files, so `dotIgnore: false` turns them off too.
+  pub custom_ignore_filenames: Option<Vec<String>>,
+  /// Respect the global gitignore file, from git's `core.excludesFile`.
+  /// Defaults to `ignoreFiles`.
+  pub git_global: Option<bool>,
+  /// Respect `.git/info/exclude`. Defaults to `ignoreFiles`.
+  pub git_exclude: Option<bool>,
+  /// Respect the ignore files of the directory's parents too. Defaults to
+  /// `ignoreFiles`.
+  pub parents: Option<bool>,
+  /// Only respect git's ignore rules within git repositories. Defaults to
+  /// true.
+  pub require_git: Option<bool>,
+  /// Follow symbolic links to directories. Links back to a directory being
+  /// walked are skipped rather than followed forever. Defaults to false.
+  pub follow_links: Option<bool>,
+  /// How deep to descend, where 1 searches only the files directly in the
+  /// directory. Unlimited by default.
+  pub max_depth: Option<u32>,
+  /// Skip files larger than this many bytes, as ripgrep's `--max-filesize`.
+  /// Unlimited by default.
+  pub max_filesize: Option<i64>,
+  /// Globs, relative to the directory, that select the files to search, as
+  /// ripgrep's `--glob`: a file must match one of them, unless they all
+  /// start with `!` to exclude files instead. Where several match a file,
+  /// the last one decides. Files matching a glob are searched even if
+  /// hidden or ignored.
+  pub globs: Option<Vec<String>>,
+  /// File types to search, from ripgrep's built-in definitions such as
+  /// `"rust"`, `"js"` or `"markdown"` (see `rg --type-list`), as ripgrep's
+  /// `--type`. Files of other types are skipped.
+  pub types: Option<Vec<String>>,
+  /// File types not to search, as ripgrep's `--type-not`. These take
+  /// precedence over `types`.
+  pub types_not: Option<Vec<String>>,
+}
+
+/// Options for `Searcher.searchFiles`.
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchFilesOptions {
+  /// The number of threads to search on. Defaults to the searcher's
+  /// `threads`.
+  pub threads: Option<u32>,
+  /// Produce results in the order of the paths, holding back each one until
+  /// those before it are done. Otherwise results are produced as soon as
+  /// they are found. Enabled by default.
+  pub ordered: Option<bool>,
+  /// How many results `searchFilesIter` buffers ahead of the consumer.
+  /// Defaults to 16.
+  pub queue_size: Option<u32>,
+}
+
+impl SearchFilesOptions {
+  fn plan(&self, config: &SearchConfig) -> files::Plan {
+    files::Plan {
+      threads: self
+        .threads
+        .map_or_else(|| config.threads(), |n| n as usize),
+      ordered: self.ordered.unwrap_or(true),
+    }
+  }
+}
+
+/// Options for creating a `SearchService`.
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchServiceOptions {
+  /// The number of search threads. Defaults to the number of CPUs.
+  pub threads: Option<u32>,
+  /// Raise the priority of a queued job by one for every `agingMs`
+  /// milliseconds it waits, so lower priority jobs still run while higher
+  /// priority ones keep arriving. Off by default.
+  pub aging_ms: Option<u32>,
+}
+
+/// Options for a job queued on a `SearchService`.
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchJobOptions {
+  /// Jobs with higher priorities run first, and jobs with the same priority
+  /// run in the order they were queued. Defaults to 0.
+  pub priority: Option<i32>,
 }
 
 // ============================================================================
@@ -125,8 +1053,13 @@ pub struct SearchResult {
 /// builder, in addition to options like smart case, word matching, and line
 /// terminator settings.
 #[napi]
+#[derive(Clone)]
 pub struct RegexMatcherBuilder {
   inner: GrepRegexMatcherBuilder,
+  options: PatternOptions,
+  /// Set by `asciiCaseInsensitive`, which overrides `caseInsensitive` and
+  /// `unicode` when a matcher is built without changing them.
+  ascii_case_insensitive: bool,
 }
 
 #[napi]
@@ -136,75 +1069,255 @@ impl RegexMatcherBuilder {
   pub fn new() -> Self {
     Self {
       inner: GrepRegexMatcherBuilder::new(),
+      options: PatternOptions::default(),
+      ascii_case_insensitive: false,
     }
   }
 
   /// Build a new matcher for the provided pattern.
   #[napi]
-  pub fn build(&self, pattern: String) -> Result<RegexMatcher> {
-    let matcher = self
-      .inner
-      .build(&pattern)
-      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
-    Ok(RegexMatcher {
-      inner: Arc::new(matcher),
+  pub fn build(&self, env: &Env, pattern: String) -> Result<RegexMatcher> {
+    self.build_reporting(env, &[pattern], self.options.fixed_strings)
+  }
+
+  /// Build a new matcher for the provided pattern on the libuv thread
+  /// pool, without blocking the event loop.
+  ///
+  /// Resolves with the same matcher as `build`.
+  #[napi(ts_return_type = "Promise<RegexMatcher>")]
+  pub fn build_async(&self, pattern: String) -> AsyncTask<BuildTask> {
+    AsyncTask::new(BuildTask {
+      builder: self.clone(),
+      patterns: vec![pattern],
+      fixed_strings: self.options.fixed_strings,
     })
   }
 
   /// Build a new matcher from multiple patterns (joined as alternation).
-  #[napi]
-  pub fn build_many(&self, patterns: Vec<String>) -> Result<RegexMatcher> {
-    let matcher = self
-      .inner
-      .build_many(&patterns)
-      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
-    Ok(RegexMatcher {
-      inner: Arc::new(matcher),
-    })
+  ///
+  /// Entries may be plain patterns or `PatternEntry` objects carrying their
+  /// own flags, so a heterogeneous rule set compiles into a single matcher.
+  #[napi(ts_args_type = "patterns: Array<string | PatternEntry>")]
+  pub fn build_many(
+    &self,
+    env: &Env,
+    patterns: Vec<Either<String, PatternEntry>>,
+  ) -> Result<RegexMatcher> {
+    let (patterns, fixed_strings) = self.many_patterns(patterns);
+    self.build_reporting(env, &patterns, fixed_strings)
   }
 
-  /// Build a new matcher from literal strings (optimized alternation).
-  #[napi]
-  pub fn build_literals(&self, literals: Vec<String>) -> Result<RegexMatcher> {
-    let matcher = self
-      .inner
-      .build_literals(&literals)
-      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
-    Ok(RegexMatcher {
-      inner: Arc::new(matcher),
+  /// Build a new matcher from multiple patterns on the libuv thread pool,
+  /// without blocking the event loop. Compiling alternations of many
+  /// thousands of patterns, such as a word list, can take long enough to be
+  /// noticed.
+  ///
+  /// Resolves with the same matcher as `buildMany`.
+  #[napi(
+    ts_args_type = "patterns: Array<string | PatternEntry>",
+    ts_return_type = "Promise<RegexMatcher>"
+  )]
+  pub fn build_many_async(
+    &self,
+    patterns: Vec<Either<String, PatternEntry>>,
+  ) -> AsyncTask<BuildTask> {
+    let (patterns, fixed_strings) = self.many_patterns(patterns);
+    AsyncTask::new(BuildTask {
+      builder: self.clone(),
+      patterns,
+      fixed_strings,
     })
   }
 
-  /// Set the value for the case insensitive (`i`) flag.
+  /// Build a new matcher from a file containing one pattern per line, like
+  /// `rg -f`.
   ///
-  /// When enabled, letters in the pattern will match both upper case and
-  /// lower case variants.
+  /// Both `\n` and `\r\n` line endings are accepted. Every line is a
+  /// pattern unless skipped by `options`.
   #[napi]
-  pub fn case_insensitive(&mut self, yes: bool) -> &Self {
-    self.inner.case_insensitive(yes);
-    self
+  pub fn build_from_pattern_file(
+    &self,
+    env: &Env,
+    path: String,
+    options: Option<PatternFileOptions>,
+  ) -> Result<RegexMatcher> {
+    let data = std::fs::read(&path).map_err(|e| {
+      let error = Error::new(Status::GenericFailure, format!("{}: {}", path, e));
+      Failure::classify(error, Some(&path)).into_error(env)
+    })?;
+    let patterns = pattern_lines(&data, &options.unwrap_or_default())
+      .map_err(|e| Error::new(Status::InvalidArg, format!("{}:{}", path, e)))?;
+    self.build_reporting(env, &patterns, self.options.fixed_strings)
   }
 
-  /// Whether to enable "smart case" or not.
+  /// Build a new matcher from a newline-separated list of patterns, such as
+  /// the output of another process.
   ///
-  /// When smart case is enabled, the builder will automatically enable
-  /// case insensitive matching based on how the pattern is written.
-  /// Specifically, case insensitive mode is enabled when:
-  /// 1. The pattern contains at least one literal character.
-  /// 2. Of the literals in the pattern, none of them are uppercase.
+  /// Lines are handled exactly as in `buildFromPatternFile`.
   #[napi]
-  pub fn case_smart(&mut self, yes: bool) -> &Self {
-    self.inner.case_smart(yes);
-    self
+  pub fn build_from_pattern_list(
+    &self,
+    env: &Env,
+    patterns: Either<String, Buffer>,
+    options: Option<PatternFileOptions>,
+  ) -> Result<RegexMatcher> {
+    let data = match &patterns {
+      Either::A(s) => s.as_bytes(),
+      Either::B(b) => b.as_ref(),
+    };
+    let patterns = pattern_lines(data, &options.unwrap_or_default())
+      .map_err(|e| Error::new(Status::InvalidArg, format!("line {}", e)))?;
+    self.build_reporting(env, &patterns, self.options.fixed_strings)
   }
 
-  /// Set the value for the multi-line matching (`m`) flag.
-  ///
+  /// Build a new matcher from literal strings (optimized alternation).
+  #[napi]
+  pub fn build_literals(&self, env: &Env, literals: Vec<String>) -> Result<RegexMatcher> {
+    self.build_reporting(env, &literals, self.options.fixed_strings)
+  }
+
+  /// Build a matcher for a literal query in which every run of whitespace
+  /// matches any run of whitespace.
+  ///
+  /// Useful for finding a sentence or code snippet regardless of how it was
+  /// wrapped or indented. Leading and trailing whitespace is ignored. Runs
+  /// match line terminators too, so a query can span lines when the
+  /// searcher is in multi-line mode. A query of only whitespace, which
+  /// would match every line, is an error.
+  #[napi]
+  pub fn build_whitespace_flexible(&self, env: &Env, literal: String) -> Result<RegexMatcher> {
+    if literal.trim().is_empty() {
+      let error = Error::new(
+        Status::InvalidArg,
+        "whitespace-flexible query is empty or only whitespace",
+      );
+      return Err(Failure::classify(error, None).into_error(env));
+    }
+    let pattern = literal
+      .split_whitespace()
+      .map(regex_syntax::escape)
+      .collect::<Vec<_>>()
+      .join(r"\s+");
+    self.build_reporting(env, &[pattern], false)
+  }
+
+  /// Build a matcher for an exact sequence of bytes.
+  ///
+  /// The bytes are matched verbatim regardless of whether they are valid
+  /// UTF-8. Options like `caseInsensitive` and `word` still apply.
+  #[napi]
+  pub fn build_bytes(&self, env: &Env, literal: Buffer) -> Result<RegexMatcher> {
+    let pattern: String = literal.iter().map(|b| format!("\\x{:02X}", b)).collect();
+    self.build_reporting(env, &[format!("(?-u:{})", pattern)], false)
+  }
+
+  /// Build a matcher from a hex byte signature such as `"7F 45 4C 46 ?? 01"`.
+  ///
+  /// The signature is a sequence of two-digit hex bytes, optionally
+  /// separated by whitespace, where `??` matches any single byte.
+  #[napi]
+  pub fn build_hex(&self, env: &Env, signature: String) -> Result<RegexMatcher> {
+    let invalid = |message: String| {
+      Failure::classify(Error::new(Status::InvalidArg, message), None).into_error(env)
+    };
+    let digits: Vec<char> = signature.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+      return Err(invalid(format!(
+        "hex signature `{}` has an odd number of digits; bytes must be written as two hex digits",
+        signature
+      )));
+    }
+    let mut pattern = String::from("(?s-u:");
+    for pair in digits.chunks(2) {
+      match pair {
+        ['?', '?'] => pattern.push('.'),
+        [hi, lo] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+          pattern.push_str(&format!("\\x{}{}", hi, lo));
+        }
+        _ => {
+          return Err(invalid(format!(
+            "invalid byte `{}{}` in hex signature `{}`; expected two hex digits or `??`",
+            pair[0], pair[1], signature
+          )))
+        }
+      }
+    }
+    pattern.push(')');
+    self.build_reporting(env, &[pattern], false)
+  }
+
+  /// Treat patterns as matching raw bytes rather than Unicode text.
+  ///
+  /// This is the same as `unicode(false)`: `\xNN` escapes match the byte
+  /// `NN` and `.` matches any byte, so patterns can match data that is not
+  /// valid UTF-8. Unicode-aware parts of a pattern can still be written
+  /// inside a `(?u:...)` group.
+  #[napi]
+  pub fn bytes(&mut self, yes: bool) -> &Self {
+    self.unicode(!yes)
+  }
+
+  /// Set the value for the case insensitive (`i`) flag.
+  ///
+  /// When enabled, letters in the pattern will match both upper case and
+  /// lower case variants.
+  #[napi]
+  pub fn case_insensitive(&mut self, yes: bool) -> &Self {
+    self.inner.case_insensitive(yes);
+    self.options.case_insensitive = yes;
+    self
+  }
+
+  /// Match letters case-insensitively by ASCII case folding alone, for data
+  /// known to be ASCII.
+  ///
+  /// Matchers are built as with `caseInsensitive(true)` and
+  /// `unicode(false)`: the pattern is compiled without the Unicode case
+  /// tables and classes, which makes case-insensitive scans of large logs
+  /// cheaper, particularly for patterns with classes like `\w` or `\s`.
+  /// Only `A`-`Z` and `a`-`z` fold, so `k` no longer matches the Kelvin
+  /// sign, and non-ASCII letters must match exactly. Classes match ASCII
+  /// only and `.` matches any byte, as with `bytes(true)`. Turning it off
+  /// again leaves `caseInsensitive` and `unicode` as they were set.
+  #[napi]
+  pub fn ascii_case_insensitive(&mut self, yes: bool) -> &Self {
+    self.ascii_case_insensitive = yes;
+    self
+  }
+
+  /// Apply the matcher defaults of `preset`: smart case for `Ripgrep`,
+  /// case-sensitive matching otherwise. Options set afterwards override
+  /// them.
+  #[napi]
+  pub fn preset(&mut self, preset: Preset) -> &Self {
+    self.case_insensitive(false);
+    self.ascii_case_insensitive(false);
+    self.case_smart(matches!(preset, Preset::Ripgrep));
+    self
+  }
+
+  /// Whether to enable "smart case" or not.
+  ///
+  /// When smart case is enabled, the builder will automatically enable
+  /// case insensitive matching based on how the pattern is written.
+  /// Specifically, case insensitive mode is enabled when:
+  /// 1. The pattern contains at least one literal character.
+  /// 2. Of the literals in the pattern, none of them are uppercase.
+  #[napi]
+  pub fn case_smart(&mut self, yes: bool) -> &Self {
+    self.inner.case_smart(yes);
+    self.options.case_smart = yes;
+    self
+  }
+
+  /// Set the value for the multi-line matching (`m`) flag.
+  ///
   /// When enabled, `^` matches the beginning of lines and `$` matches
   /// the end of lines.
   #[napi]
   pub fn multi_line(&mut self, yes: bool) -> &Self {
     self.inner.multi_line(yes);
+    self.options.multi_line = yes;
     self
   }
 
@@ -214,6 +1327,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn dot_matches_new_line(&mut self, yes: bool) -> &Self {
     self.inner.dot_matches_new_line(yes);
+    self.options.dot_matches_new_line = yes;
     self
   }
 
@@ -223,6 +1337,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn swap_greed(&mut self, yes: bool) -> &Self {
     self.inner.swap_greed(yes);
+    self.options.swap_greed = yes;
     self
   }
 
@@ -232,6 +1347,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn ignore_whitespace(&mut self, yes: bool) -> &Self {
     self.inner.ignore_whitespace(yes);
+    self.options.ignore_whitespace = yes;
     self
   }
 
@@ -241,6 +1357,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn unicode(&mut self, yes: bool) -> &Self {
     self.inner.unicode(yes);
+    self.options.unicode = yes;
     self
   }
 
@@ -250,6 +1367,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn octal(&mut self, yes: bool) -> &Self {
     self.inner.octal(yes);
+    self.options.octal = yes;
     self
   }
 
@@ -271,6 +1389,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn nest_limit(&mut self, limit: u32) -> &Self {
     self.inner.nest_limit(limit);
+    self.options.nest_limit = limit;
     self
   }
 
@@ -280,6 +1399,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn line_terminator(&mut self, byte: Option<u32>) -> &Self {
     self.inner.line_terminator(byte.map(|b| b as u8));
+    self.options.line_terminator = byte.map(|b| b as u8);
     self
   }
 
@@ -294,10 +1414,17 @@ impl RegexMatcherBuilder {
 
   /// Set CRLF mode for line terminators.
   ///
-  /// When enabled, `$` will match both `\r\n` and `\n`.
+  /// When enabled, `^` and `$` treat both `\r\n` and `\n` as line endings
+  /// and never match between a `\r` and a `\n`, and reported matches are
+  /// widened so they never split a `\r\n` pair. Unlike grep's CRLF mode
+  /// this does not set a line terminator, so the matcher works with any
+  /// searcher and may match across lines in multi-line searches. Use
+  /// `lineTerminator` to keep matches within lines.
   #[napi]
   pub fn crlf(&mut self, yes: bool) -> &Self {
     self.inner.crlf(yes);
+    self.inner.line_terminator(self.options.line_terminator);
+    self.options.crlf = yes;
     self
   }
 
@@ -305,6 +1432,7 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn word(&mut self, yes: bool) -> &Self {
     self.inner.word(yes);
+    self.options.word = yes;
     self
   }
 
@@ -314,6 +1442,67 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn fixed_strings(&mut self, yes: bool) -> &Self {
     self.inner.fixed_strings(yes);
+    self.options.fixed_strings = yes;
+    self
+  }
+
+  /// Whether to report the longest match at each position (POSIX
+  /// leftmost-longest) instead of the first alternative that matches.
+  ///
+  /// For example, `a|ab` matches `ab` in `abc` instead of `a`. Which lines
+  /// match is unaffected; only reported match ranges change. Capture groups
+  /// used by `replace` keep leftmost-first semantics.
+  #[napi]
+  pub fn leftmost_longest(&mut self, yes: bool) -> &Self {
+    self.options.leftmost_longest = yes;
+    self
+  }
+
+  /// Require matches to start at the beginning of the haystack.
+  ///
+  /// Unlike `^`, this is unaffected by `multiLine`. Intended for validating
+  /// whole inputs with `isMatch`/`find`; in line-oriented searches use
+  /// `wholeLine` instead.
+  #[napi]
+  pub fn anchored_start(&mut self, yes: bool) -> &Self {
+    self.options.anchored_start = yes;
+    self
+  }
+
+  /// Require matches to span the entire haystack.
+  ///
+  /// Equivalent to wrapping each pattern in `\A(?:...)\z`, so `isMatch`
+  /// answers "does this whole buffer match?" regardless of `multiLine`.
+  /// In line-oriented searches use `wholeLine` instead.
+  #[napi]
+  pub fn full_match(&mut self, yes: bool) -> &Self {
+    self.options.full_match = yes;
+    self
+  }
+
+  /// Normalize text to the given Unicode form before matching.
+  ///
+  /// Patterns are normalized to the same form when the matcher is built.
+  /// Reported offsets and lines refer to the original input; a match that
+  /// covers part of a rewritten character sequence widens to the whole
+  /// sequence. `scanBytes` and `scanFile` always match raw bytes. Pass
+  /// `null` to disable (the default).
+  #[napi]
+  pub fn normalization(&mut self, form: Option<NormalizationForm>) -> &Self {
+    self.options.normalization = form;
+    self
+  }
+
+  /// Match letters regardless of diacritics, so `e` matches `é`, `ê` and
+  /// `ë`.
+  ///
+  /// Combining marks are removed from both patterns and text, and a few
+  /// letters without a decomposition are transliterated (`ø` to `o`, `ł` to
+  /// `l`, `ß` to `ss`, ...). As with `normalization`, offsets refer to the
+  /// original input.
+  #[napi]
+  pub fn diacritic_insensitive(&mut self, yes: bool) -> &Self {
+    self.options.diacritic_insensitive = yes;
     self
   }
 
@@ -323,16 +1512,200 @@ impl RegexMatcherBuilder {
   #[napi]
   pub fn whole_line(&mut self, yes: bool) -> &Self {
     self.inner.whole_line(yes);
+    self.options.whole_line = yes;
     self
   }
 }
 
+impl RegexMatcherBuilder {
+  /// The patterns of a `buildMany` call, with whether they are fixed
+  /// strings.
+  fn many_patterns(&self, patterns: Vec<Either<String, PatternEntry>>) -> (Vec<String>, bool) {
+    if patterns.iter().all(|p| matches!(p, Either::A(_))) {
+      let patterns = patterns
+        .into_iter()
+        .filter_map(|p| match p {
+          Either::A(p) => Some(p),
+          Either::B(_) => None,
+        })
+        .collect();
+      return (patterns, self.options.fixed_strings);
+    }
+    let patterns = patterns.iter().map(|p| self.entry_pattern(p)).collect();
+    (patterns, false)
+  }
+
+  /// Build a matcher for the alternation of `patterns`.
+  ///
+  /// `fixed_strings` overrides the builder setting, so that generated
+  /// patterns always have their escapes interpreted. Anchoring options are
+  /// applied here by wrapping each pattern.
+  fn build_patterns(&self, patterns: &[String], fixed_strings: bool) -> Result<RegexMatcher> {
+    if self.ascii_case_insensitive {
+      return self.ascii_folding().build_patterns(patterns, fixed_strings);
+    }
+    let mut options = self.options.clone();
+    options.fixed_strings = fixed_strings;
+    let folded;
+    let patterns = match options.fold() {
+      Some(fold) => {
+        folded = patterns
+          .iter()
+          .map(|p| options.fold_pattern(p, fold))
+          .collect::<Vec<_>>();
+        &folded
+      }
+      None => patterns,
+    };
+    let anchored;
+    let patterns = if options.anchored_start || options.full_match {
+      let end = if options.full_match { r"\z" } else { "" };
+      anchored = patterns
+        .iter()
+        .map(|p| {
+          let p = if fixed_strings {
+            regex_syntax::escape(p)
+          } else {
+            p.clone()
+          };
+          format!(r"\A(?:{}){}", p, end)
+        })
+        .collect::<Vec<_>>();
+      options.fixed_strings = false;
+      &anchored
+    } else {
+      patterns
+    };
+    let mut builder = self.inner.clone();
+    builder.fixed_strings(options.fixed_strings);
+    let matcher = builder
+      .build_many(patterns)
+      .map_err(|e| self.build_error(e))?;
+    let engine = Engine::with_options(matcher, patterns, &options)
+      .map_err(|e| Error::new(Status::InvalidArg, e))?;
+    Ok(RegexMatcher {
+      inner: Arc::new(engine),
+    })
+  }
+
+  /// Translate a `buildMany` entry into a regex with its flags applied
+  /// inline.
+  fn entry_pattern(&self, entry: &Either<String, PatternEntry>) -> String {
+    let (pattern, case_insensitive, word, fixed) = match entry {
+      Either::A(pattern) => (pattern, None, false, self.options.fixed_strings),
+      Either::B(entry) => (
+        &entry.pattern,
+        entry.case_insensitive,
+        entry.word.unwrap_or(false),
+        entry.fixed_string.unwrap_or(self.options.fixed_strings),
+      ),
+    };
+    let mut pattern = if fixed {
+      regex_syntax::escape(pattern)
+    } else {
+      pattern.clone()
+    };
+    if word {
+      // The same half boundaries grep uses for `word`.
+      pattern = format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern);
+    }
+    match case_insensitive {
+      Some(true) => format!("(?i:{})", pattern),
+      Some(false) => format!("(?-i:{})", pattern),
+      None => pattern,
+    }
+  }
+
+  /// Build `patterns`, throwing failures with the pattern that failed.
+  fn build_reporting(
+    &self,
+    env: &Env,
+    patterns: &[String],
+    fixed_strings: bool,
+  ) -> Result<RegexMatcher> {
+    self.build_patterns(patterns, fixed_strings).map_err(|e| {
+      self
+        .compile_failure(patterns, fixed_strings, e)
+        .into_error(env)
+    })
+  }
+
+  /// A copy of the builder that builds the matchers of one with
+  /// `asciiCaseInsensitive` set.
+  fn ascii_folding(&self) -> Self {
+    let mut builder = self.clone();
+    builder.ascii_case_insensitive = false;
+    builder.case_insensitive(true);
+    builder.unicode(false);
+    builder
+  }
+
+  /// Describe a failure to build `patterns`, locating the first of them
+  /// that does not parse.
+  fn compile_failure(&self, patterns: &[String], fixed_strings: bool, error: Error) -> Failure {
+    let unicode = self.options.unicode && !self.ascii_case_insensitive;
+    if !fixed_strings {
+      for (i, pattern) in patterns.iter().enumerate() {
+        if let Some(span) = error::syntax_error(pattern, unicode) {
+          return Failure::pattern(error, i as u32, pattern, Some(span));
+        }
+      }
+    }
+    match patterns {
+      [pattern] => Failure::pattern(error, 0, pattern, None),
+      _ => Failure::patterns(error),
+    }
+  }
+
+  /// Convert a build error, explaining failures caused by byte mode.
+  fn build_error(&self, err: grep::regex::Error) -> Error {
+    let mut msg = err.to_string();
+    if !self.options.unicode {
+      msg.push_str(
+        "\n\nnote: Unicode is disabled (byte mode), so the pattern matches raw \
+         bytes. Unicode-aware constructs such as `\\p{L}` are only available \
+         inside a `(?u:...)` group.",
+      );
+    }
+    Error::new(Status::InvalidArg, msg)
+  }
+}
+
 impl Default for RegexMatcherBuilder {
   fn default() -> Self {
     Self::new()
   }
 }
 
+/// A matcher compiled on the libuv thread pool.
+pub struct BuildTask {
+  builder: RegexMatcherBuilder,
+  patterns: Vec<String>,
+  fixed_strings: bool,
+}
+
+impl Task for BuildTask {
+  type Output = RegexMatcher;
+  type JsValue = RegexMatcher;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    self
+      .builder
+      .build_patterns(&self.patterns, self.fixed_strings)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    let failure = self
+      .builder
+      .compile_failure(&self.patterns, self.fixed_strings, err);
+    Err(failure.into_error(&env))
+  }
+}
+
 // ============================================================================
 // RegexMatcher
 // ============================================================================
@@ -342,32 +1715,77 @@ impl Default for RegexMatcherBuilder {
 /// Use `RegexMatcherBuilder` to construct this.
 #[napi]
 pub struct RegexMatcher {
-  inner: Arc<GrepRegexMatcher>,
+  inner: Arc<Engine>,
 }
 
 #[napi]
 impl RegexMatcher {
   /// Create a new matcher from a pattern with default options.
   #[napi(factory)]
-  pub fn from_pattern(pattern: String) -> Result<Self> {
-    let matcher =
-      GrepRegexMatcher::new(&pattern).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+  pub fn from_pattern(env: &Env, pattern: String) -> Result<Self> {
+    let failed = |e: String| {
+      let span = error::syntax_error(&pattern, true);
+      let error = Error::new(Status::InvalidArg, e);
+      Failure::pattern(error, 0, &pattern, span).into_error(env)
+    };
+    let matcher = GrepRegexMatcher::new(&pattern).map_err(|e| failed(e.to_string()))?;
+    let engine = Engine::with_options(
+      matcher,
+      std::slice::from_ref(&pattern),
+      &PatternOptions::default(),
+    )
+    .map_err(failed)?;
     Ok(Self {
-      inner: Arc::new(matcher),
+      inner: Arc::new(engine),
     })
   }
 
+  /// Create a matcher sharing this one's compiled pattern, without
+  /// compiling it again.
+  #[napi(js_name = "clone")]
+  pub fn clone_matcher(&self) -> Self {
+    Self {
+      inner: self.inner.clone(),
+    }
+  }
+
+  /// Share the compiled pattern with other worker threads.
+  ///
+  /// Returns a handle that can be posted to a worker and passed to
+  /// `RegexMatcher.fromShared` there. The pattern stays in memory until
+  /// the handle is passed to `RegexMatcher.releaseShared`.
+  #[napi]
+  pub fn share(&self) -> u32 {
+    shared::share(self.inner.clone())
+  }
+
+  /// Create a matcher from a handle returned by `share`, in any thread of
+  /// the process.
+  #[napi(factory)]
+  pub fn from_shared(handle: u32) -> Result<Self> {
+    let inner = shared::get(handle).ok_or_else(|| {
+      Error::new(
+        Status::InvalidArg,
+        format!("no shared matcher with handle {}", handle),
+      )
+    })?;
+    Ok(Self { inner })
+  }
+
+  /// Release a handle returned by `share`. Matchers already created from it
+  /// keep working. Returns whether the handle was still shared.
+  #[napi]
+  pub fn release_shared(handle: u32) -> bool {
+    shared::release(handle)
+  }
+
   /// Check if the given text matches the pattern.
   #[napi]
   pub fn is_match(&self, text: Either<String, Buffer>) -> Result<bool> {
-    let bytes = match &text {
+    self.is_match_bytes(match &text {
       Either::A(s) => s.as_bytes(),
       Either::B(b) => b.as_ref(),
-    };
-    self
-      .inner
-      .is_match(bytes)
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+    })
   }
 
   /// Find the first match in the given text.
@@ -379,40 +1797,308 @@ impl RegexMatcher {
       Either::A(s) => s.as_bytes(),
       Either::B(b) => b.as_ref(),
     };
-    match self.inner.find(bytes) {
-      Ok(Some(m)) => Ok(Some(MatchRange {
-        start: m.start() as u32,
-        end: m.end() as u32,
-      })),
+    let normalized = self.normalize(bytes);
+    match self
+      .inner
+      .find(normalized.as_ref().map_or(bytes, |n| n.text()))
+    {
+      Ok(Some(m)) => {
+        let (start, end) = normalized.as_ref().map_or((m.start(), m.end()), |n| {
+          (n.start(m.start()), n.end(m.end()))
+        });
+        Ok(Some(MatchRange {
+          start: start as u32,
+          end: end as u32,
+        }))
+      }
       Ok(None) => Ok(None),
       Err(e) => Err(Error::new(Status::GenericFailure, e.to_string())),
     }
   }
 
   /// Find all matches in the given text.
+  ///
+  /// With `overlapping` enabled, every occurrence is reported even when it
+  /// overlaps a previous one (e.g. `aa` in `aaaa` at 0, 1 and 2).
   #[napi]
-  pub fn find_all(&self, text: Either<String, Buffer>) -> Result<Vec<MatchRange>> {
+  pub fn find_all(
+    &self,
+    text: Either<String, Buffer>,
+    options: Option<FindOptions>,
+  ) -> Result<Vec<MatchRange>> {
     let bytes = match &text {
       Either::A(s) => s.as_bytes(),
       Either::B(b) => b.as_ref(),
     };
-    let mut matches = Vec::new();
-    let mut start = 0;
-    while start < bytes.len() {
-      match self.inner.find(&bytes[start..]) {
-        Ok(Some(m)) => {
-          matches.push(MatchRange {
-            start: (start + m.start()) as u32,
-            end: (start + m.end()) as u32,
-          });
-          start += m.end().max(1);
-        }
-        Ok(None) => break,
-        Err(e) => return Err(Error::new(Status::GenericFailure, e.to_string())),
+    let overlapping = options.and_then(|o| o.overlapping).unwrap_or(false);
+    self.find_all_bytes(bytes, overlapping)
+  }
+
+  /// Scan raw bytes for matches without any notion of lines.
+  ///
+  /// Reports absolute offsets of every match, optionally with a window of
+  /// surrounding bytes. No line splitting or UTF-8 conversion is performed,
+  /// which makes this suitable for firmware images and other blobs.
+  #[napi]
+  pub fn scan_bytes(
+    &self,
+    data: Buffer,
+    options: Option<BinaryScanOptions>,
+  ) -> Result<Vec<BinaryMatch>> {
+    self.scan_impl(data.as_ref(), &options.unwrap_or_default())
+  }
+
+  /// Scan a file's raw bytes for matches without any notion of lines.
+  ///
+  /// See `scanBytes`.
+  #[napi]
+  pub fn scan_file(
+    &self,
+    path: String,
+    options: Option<BinaryScanOptions>,
+  ) -> Result<Vec<BinaryMatch>> {
+    let data = std::fs::read(&path)
+      .map_err(|e| Error::new(Status::GenericFailure, format!("{}: {}", path, e)))?;
+    self.scan_impl(&data, &options.unwrap_or_default())
+  }
+
+  /// Replace the first match in the given text.
+  ///
+  /// See `replaceAll` for the replacement template syntax.
+  #[napi]
+  pub fn replace(
+    &self,
+    text: Either<String, Buffer>,
+    replacement: String,
+    options: Option<ReplaceOptions>,
+  ) -> Result<Either<String, Buffer>> {
+    self.replace_impl(text, &replacement, options.unwrap_or_default(), false)
+  }
+
+  /// Replace every match in the given text.
+  ///
+  /// The replacement may reference capture groups with `$1`, `$name` or
+  /// `${name}`, and supply a fallback with `${name:-default}` that is used
+  /// when the group did not participate in the match or matched nothing.
+  /// Use `$$` for a literal `$`.
+  ///
+  /// Returns a string for string input and a Buffer for Buffer input.
+  #[napi]
+  pub fn replace_all(
+    &self,
+    text: Either<String, Buffer>,
+    replacement: String,
+    options: Option<ReplaceOptions>,
+  ) -> Result<Either<String, Buffer>> {
+    self.replace_impl(text, &replacement, options.unwrap_or_default(), true)
+  }
+
+  /// The capture groups of the pattern, in index order, without group 0
+  /// (the whole match).
+  ///
+  /// Each group has the index and name replacement templates refer to it
+  /// by, and the group it is nested in, as parsed with the matcher's own
+  /// syntax options. Useful to offer capture pickers for replacements.
+  #[napi]
+  pub fn capture_groups(&self) -> Vec<CaptureGroup> {
+    self
+      .inner
+      .groups()
+      .iter()
+      .map(|g| CaptureGroup {
+        index: g.index as u32,
+        name: g.name.clone(),
+        parent: g.parent.map(|i| i as u32),
+        pattern: g.pattern as u32,
+      })
+      .collect()
+  }
+
+  /// The literals every match contains, for narrowing down candidate
+  /// documents in an external index before searching them, or null when
+  /// matches require no literal.
+  ///
+  /// Texts that contain all literals of none of the alternatives cannot
+  /// match. Texts that do may still not match.
+  #[napi]
+  pub fn prefilter_literals(&self) -> Option<RequiredLiterals> {
+    self.inner.required().map(|required| RequiredLiterals {
+      any_of: required
+        .any_of
+        .iter()
+        .map(|all| {
+          all
+            .iter()
+            .map(|lit| String::from_utf8_lossy(lit).into_owned())
+            .collect()
+        })
+        .collect(),
+      case_insensitive: required.case_insensitive,
+    })
+  }
+
+  /// Search several independent inputs in one call.
+  ///
+  /// Each input is searched on its own, as by `searcher.searchSlice`, with
+  /// `searcher` or a default searcher. Results are in the order of the
+  /// inputs.
+  #[napi(ts_return_type = "Array<SearchResult>")]
+  pub fn search_all(
+    &self,
+    haystacks: Vec<Either<String, Buffer>>,
+    options: Option<SearchAllOptions>,
+    searcher: Option<&Searcher>,
+  ) -> Result<Vec<SearchOutput>> {
+    let searcher = searcher.cloned().unwrap_or_default();
+    let inputs: Vec<&[u8]> = haystacks
+      .iter()
+      .map(|h| match h {
+        Either::A(s) => s.as_bytes(),
+        Either::B(b) => b.as_ref(),
+      })
+      .collect();
+    let search = |searcher: Searcher, inputs: &[&[u8]]| {
+      inputs
+        .iter()
+        .map(|input| searcher.search(self, Haystack::Slice(input), SearchOptions::default()))
+        .collect::<Vec<_>>()
+    };
+    if !options.unwrap_or_default().parallel.unwrap_or(false) {
+      return search(searcher, &inputs).into_iter().collect();
+    }
+    let chunk_size = inputs.len().div_ceil(searcher.config.threads()).max(1);
+    std::thread::scope(|scope| {
+      let handles: Vec<_> = inputs
+        .chunks(chunk_size)
+        .map(|chunk| {
+          let searcher = searcher.clone();
+          scope.spawn(move || search(searcher, chunk))
+        })
+        .collect();
+      handles
+        .into_iter()
+        .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+        .collect()
+    })
+  }
+}
+
+impl RegexMatcher {
+  /// Check if `bytes` match the pattern, as by `isMatch`.
+  fn is_match_bytes(&self, bytes: &[u8]) -> Result<bool> {
+    let normalized = self.normalize(bytes);
+    self
+      .inner
+      .is_match(normalized.as_ref().map_or(bytes, |n| n.text()))
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+  }
+
+  /// Find all matches in `bytes`, with offsets into `bytes` when the
+  /// matcher normalizes text.
+  fn find_all_bytes(&self, bytes: &[u8], overlapping: bool) -> Result<Vec<MatchRange>> {
+    let normalized = self.normalize(bytes);
+    let mut ranges = match_ranges(
+      &self.inner,
+      normalized.as_ref().map_or(bytes, |n| n.text()),
+      overlapping,
+      usize::MAX,
+    )
+    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    if let Some(n) = &normalized {
+      for r in &mut ranges {
+        r.start = n.start(r.start as usize) as u32;
+        r.end = n.end(r.end as usize) as u32;
       }
     }
+    Ok(ranges)
+  }
+
+  /// Fold a haystack if the matcher was built with normalization or
+  /// diacritic folding.
+  fn normalize(&self, bytes: &[u8]) -> Option<Normalized> {
+    self.inner.fold().map(|fold| Normalized::new(bytes, fold))
+  }
+
+  fn scan_impl(&self, data: &[u8], options: &BinaryScanOptions) -> Result<Vec<BinaryMatch>> {
+    let before = options.before_bytes.unwrap_or(0) as usize;
+    let after = options.after_bytes.unwrap_or(0) as usize;
+    let limit = options.max_matches.map(|n| n as usize);
+    let mut matches = Vec::new();
+    if limit == Some(0) {
+      return Ok(matches);
+    }
+    self
+      .inner
+      .find_iter(data, |m| {
+        let window = if before > 0 || after > 0 {
+          Some(byte_window(data, 0, m.start(), m.end(), before, after))
+        } else {
+          None
+        };
+        matches.push(BinaryMatch {
+          start: m.start() as i64,
+          end: m.end() as i64,
+          window,
+        });
+        limit.is_none_or(|n| matches.len() < n)
+      })
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
     Ok(matches)
   }
+
+  fn replace_impl(
+    &self,
+    text: Either<String, Buffer>,
+    replacement: &str,
+    options: ReplaceOptions,
+    all: bool,
+  ) -> Result<Either<String, Buffer>> {
+    let template = Template::parse(
+      &*self.inner,
+      replacement.as_bytes(),
+      options.strict.unwrap_or(false),
+    )
+    .map_err(|e| Error::new(Status::InvalidArg, e))?;
+    let bytes = match &text {
+      Either::A(s) => s.as_bytes(),
+      Either::B(b) => b.as_ref(),
+    };
+    let mut caps = self
+      .inner
+      .new_captures()
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    let normalized = self.normalize(bytes);
+    let haystack = normalized.as_ref().map_or(bytes, |n| n.text());
+    let mut dst = Vec::with_capacity(bytes.len());
+    let mut replaced = false;
+    // Like `Matcher::replace_with_captures`, but copies the unmatched parts
+    // from the original text when matching against a normalized copy.
+    let mut last = 0;
+    self
+      .inner
+      .captures_iter(haystack, &mut caps, |caps| {
+        let Some(m) = caps.get(0) else {
+          return true;
+        };
+        let (start, end) = normalized.as_ref().map_or((m.start(), m.end()), |n| {
+          (n.start(m.start()), n.end(m.end()))
+        });
+        dst.extend_from_slice(&bytes[last..start.max(last)]);
+        template.expand(haystack, caps, &mut dst);
+        last = last.max(end);
+        replaced = true;
+        all
+      })
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    dst.extend_from_slice(&bytes[last..]);
+    if !replaced {
+      return Ok(text);
+    }
+    Ok(match text {
+      Either::A(_) => Either::A(String::from_utf8_lossy(&dst).into_owned()),
+      Either::B(_) => Either::B(Buffer::from(dst)),
+    })
+  }
 }
 
 // ============================================================================
@@ -426,6 +2112,11 @@ impl RegexMatcher {
 #[napi]
 pub struct SearcherBuilder {
   inner: GrepSearcherBuilder,
+  config: SearchConfig,
+  /// Options the underlying builder does not report, for `validate`.
+  after_context: usize,
+  passthru: bool,
+  heap_limit: Option<usize>,
 }
 
 #[napi]
@@ -435,15 +2126,76 @@ impl SearcherBuilder {
   pub fn new() -> Self {
     Self {
       inner: GrepSearcherBuilder::new(),
+      config: SearchConfig::default(),
+      after_context: 0,
+      passthru: false,
+      heap_limit: None,
     }
   }
 
   /// Build a searcher with the current configuration.
+  ///
+  /// Throws if options conflict (see `validate`), with the first conflict
+  /// as the error's `code`.
   #[napi]
-  pub fn build(&self) -> Searcher {
-    Searcher {
+  pub fn build(&self) -> Result<Searcher, String> {
+    if let Some(problem) = self.validate().into_iter().next() {
+      return Err(Error::new(problem.code.name().to_string(), problem.message));
+    }
+    Ok(Searcher {
       inner: self.inner.build(),
+      builder: self.inner.clone(),
+      config: self.config.clone(),
+    })
+  }
+
+  /// The combinations of options set on this builder that cannot work
+  /// together, which `build` rejects. Empty when the configuration is
+  /// coherent.
+  #[napi]
+  pub fn validate(&self) -> Vec<ConfigProblem> {
+    let searcher = self.inner.build();
+    let mut problems = Vec::new();
+    let context = self.config.before_context > 0
+      || self.after_context > 0
+      || self.config.after_context_until.is_some();
+    if self.passthru && context {
+      problems.push(ConfigProblem {
+        code: ConfigConflict::PassthruWithContext,
+        message: "passthru reports every line, so beforeContext and afterContext have no effect"
+          .into(),
+      });
+    }
+    if self.heap_limit == Some(0) && !self.config.memory_map {
+      problems.push(ConfigProblem {
+        code: ConfigConflict::HeapLimitWithoutMemoryMap,
+        message: "a heap limit of 0 needs memoryMap(true), or no input can be searched".into(),
+      });
+    }
+    if searcher.stop_on_nonmatch() && searcher.invert_match() {
+      problems.push(ConfigProblem {
+        code: ConfigConflict::StopOnNonmatchWithInvert,
+        message: "stopOnNonmatch cannot be combined with invertMatch".into(),
+      });
     }
+    problems
+  }
+
+  /// Apply the searcher defaults of `preset`: line numbers for `Ripgrep`,
+  /// binary detection that stops at the first NUL byte for `Ripgrep` and
+  /// `Grep`, and BOM sniffing for `Ripgrep`. Options set afterwards
+  /// override them.
+  #[napi]
+  pub fn preset(&mut self, preset: Preset) -> &Self {
+    let (line_number, binary, bom) = match preset {
+      Preset::Ripgrep => (true, BinaryDetectionMode::Quit, true),
+      Preset::Grep => (false, BinaryDetectionMode::Quit, false),
+      Preset::Minimal => (false, BinaryDetectionMode::None, false),
+    };
+    self.line_number(line_number);
+    self.binary_detection(binary);
+    self.bom_sniffing(bom);
+    self
   }
 
   /// Set the line terminator used by the searcher.
@@ -489,6 +2241,29 @@ impl SearcherBuilder {
   #[napi]
   pub fn after_context(&mut self, line_count: u32) -> &Self {
     self.inner.after_context(line_count as usize);
+    self.after_context = line_count as usize;
+    self.config.after_context_until = None;
+    self
+  }
+
+  /// Include the lines after each match up to the next blank line, instead
+  /// of a fixed number of lines. With a `delimiter`, the after context ends
+  /// at the next line it matches instead.
+  ///
+  /// Suited to stack traces following an error line. The line ending the
+  /// context is not included. `maxLines` caps the after context of each
+  /// match, which is otherwise unlimited. `afterContext` switches back to a
+  /// fixed count.
+  #[napi]
+  pub fn after_context_until(
+    &mut self,
+    delimiter: Option<&RegexMatcher>,
+    max_lines: Option<u32>,
+  ) -> &Self {
+    self
+      .inner
+      .after_context(max_lines.map_or(usize::MAX, |n| n as usize));
+    self.config.after_context_until = Some(delimiter.map(|m| m.inner.clone()));
     self
   }
 
@@ -496,31 +2271,86 @@ impl SearcherBuilder {
   #[napi]
   pub fn before_context(&mut self, line_count: u32) -> &Self {
     self.inner.before_context(line_count as usize);
+    self.config.before_context = line_count as usize;
     self
   }
 
-  /// Whether to enable passthru mode.
+  /// Number of bytes to include before each match in its byte window.
   ///
-  /// When enabled, all non-matching lines are reported as context.
+  /// Unlike line context, byte windows ignore line boundaries, which is
+  /// useful for data without meaningful lines. Windows are reported in
+  /// `SearchMatch.windows` and are limited to the searcher's buffer.
   #[napi]
-  pub fn passthru(&mut self, yes: bool) -> &Self {
-    self.inner.passthru(yes);
+  pub fn before_context_bytes(&mut self, byte_count: u32) -> &Self {
+    self.config.before_context_bytes = byte_count as usize;
     self
   }
 
-  /// Set an approximate heap limit in bytes.
+  /// Number of bytes to include after each match in its byte window.
   ///
-  /// Set to 0 to disable heap usage (requires memory maps for large files).
+  /// See `beforeContextBytes`.
   #[napi]
-  pub fn heap_limit(&mut self, bytes: Option<u32>) -> &Self {
-    self.inner.heap_limit(bytes.map(|b| b as usize));
+  pub fn after_context_bytes(&mut self, byte_count: u32) -> &Self {
+    self.config.after_context_bytes = byte_count as usize;
     self
   }
 
-  /// Set binary detection mode.
+  /// Whether to report overlapping match ranges within matching lines.
   ///
-  /// - "None": No binary detection
-  /// - "Quit": Stop searching when binary data is detected
+  /// See `RegexMatcher.findAll`.
+  #[napi]
+  pub fn overlapping(&mut self, yes: bool) -> &Self {
+    self.config.overlapping = yes;
+    self
+  }
+
+  /// Whether to enable passthru mode.
+  ///
+  /// When enabled, all non-matching lines are reported as context.
+  #[napi]
+  pub fn passthru(&mut self, yes: bool) -> &Self {
+    self.inner.passthru(yes);
+    self.passthru = yes;
+    self
+  }
+
+  /// Set an approximate heap limit in bytes.
+  ///
+  /// Set to 0 to disable heap usage (requires memory maps for large files).
+  #[napi]
+  pub fn heap_limit(&mut self, bytes: Option<u32>) -> &Self {
+    self.inner.heap_limit(bytes.map(|b| b as usize));
+    self.heap_limit = bytes.map(|b| b as usize);
+    self
+  }
+
+  /// Whether to search files through memory maps when possible.
+  ///
+  /// Memory maps let the searcher work on file contents directly instead of
+  /// copying them through its internal line buffer, which avoids buffer
+  /// growth for files with extremely long lines. Only applies to
+  /// `searchPath`. Disabled by default.
+  ///
+  /// Warning: if a file is truncated while it is being searched through a
+  /// memory map, the process may crash.
+  #[napi]
+  pub fn memory_map(&mut self, yes: bool) -> &Self {
+    let choice = if yes {
+      // SAFETY: the caller opts in and accepts the documented risk of the
+      // file being mutated while it is mapped.
+      unsafe { MmapChoice::auto() }
+    } else {
+      MmapChoice::never()
+    };
+    self.inner.memory_map(choice);
+    self.config.memory_map = yes;
+    self
+  }
+
+  /// Set binary detection mode.
+  ///
+  /// - "None": No binary detection
+  /// - "Quit": Stop searching when binary data is detected
   /// - "Convert": Convert NUL bytes to line terminators
   #[napi]
   pub fn binary_detection(&mut self, mode: BinaryDetectionMode) -> &Self {
@@ -539,6 +2369,78 @@ impl SearcherBuilder {
   #[napi]
   pub fn bom_sniffing(&mut self, yes: bool) -> &Self {
     self.inner.bom_sniffing(yes);
+    self.config.bom_sniffing_disabled = !yes;
+    self
+  }
+
+  /// Count the LF and CRLF line endings of every searched input in
+  /// `finish.lineEndings`.
+  ///
+  /// They are counted in the bytes the search reads, so a search that
+  /// stops early counts only part of its input. Disabled by default.
+  #[napi]
+  pub fn line_ending_report(&mut self, yes: bool) -> &Self {
+    self.config.line_endings = yes;
+    self
+  }
+
+  /// Hash the content of every searched input with `algorithm` into
+  /// `finish.contentHash`, or stop hashing with `null`.
+  ///
+  /// The hash is computed from the bytes the search reads, so a file is
+  /// read only once. If the search stops early, the rest of the input is
+  /// read for the hash. Disabled by default.
+  #[napi]
+  pub fn content_hash(&mut self, algorithm: Option<HashAlgorithm>) -> &Self {
+    self.config.content_hash = algorithm;
+    self
+  }
+
+  /// Set how a byte order mark shows up in the first line and in offsets.
+  ///
+  /// By default it is stripped, as grep does.
+  #[napi]
+  pub fn bom_handling(&mut self, mode: BomHandling) -> &Self {
+    self.config.bom = mode;
+    self
+  }
+
+  /// Stop each search after `ms` milliseconds, or never with `null`.
+  ///
+  /// A search that runs out of time returns what it found so far, with
+  /// `finish.timedOut` set and `finish.resumeOffset` at the start of the
+  /// first line it did not search. The time is checked between lines and
+  /// between the blocks read, so in-memory inputs are read in blocks while a
+  /// timeout is set.
+  #[napi]
+  pub fn timeout_ms(&mut self, ms: Option<u32>) -> &Self {
+    self.config.timeout = ms.map(|ms| Duration::from_millis(ms as u64));
+    self
+  }
+
+  /// Stop every search of the built searchers with a "search cancelled"
+  /// error once `token` is cancelled, or stop watching a token with `null`.
+  ///
+  /// Searches check the token between the lines they collect and between
+  /// the blocks they read, so in-memory inputs are read in blocks while a
+  /// token is set.
+  #[napi]
+  pub fn cancellation_token(&mut self, token: Option<&CancellationToken>) -> &Self {
+    self.config.cancel = token
+      .map(|token| token.cancelled.clone())
+      .into_iter()
+      .collect();
+    self
+  }
+
+  /// Set what happens to undecodable byte sequences when an input with a
+  /// byte order mark is transcoded, and count them in
+  /// `finish.encodingErrors`.
+  ///
+  /// Without a policy they are replaced with U+FFFD silently.
+  #[napi]
+  pub fn encoding_error_policy(&mut self, action: Option<EncodingErrorAction>) -> &Self {
+    self.config.encoding_error = action;
     self
   }
 
@@ -551,10 +2453,117 @@ impl SearcherBuilder {
     self
   }
 
-  /// Set the maximum number of matches to return.
+  /// Set the maximum number of matching lines to return.
   #[napi]
   pub fn max_matches(&mut self, limit: Option<u32>) -> &Self {
     self.inner.max_matches(limit.map(|l| l as u64));
+    self.config.max_matches = limit;
+    self
+  }
+
+  /// Set the maximum number of individual matches to return.
+  ///
+  /// Unlike `maxMatches`, every match counts, including several on one
+  /// line. The search stops at the limit and the ranges of the last
+  /// matching line are cut to fit, so exactly this many are reported when
+  /// the input has at least as many. An inverted search counts lines.
+  #[napi]
+  pub fn max_match_count(&mut self, limit: Option<u32>) -> &Self {
+    self.config.max_match_count = limit;
+    self
+  }
+
+  /// Limit the number of threads that parallel searches with the built
+  /// searchers use, such as `searchFiles` and `searchAll`, to leave CPUs for
+  /// other work. Defaults to the number of CPUs.
+  #[napi]
+  pub fn threads(&mut self, threads: Option<u32>) -> &Self {
+    self.config.threads = threads.map(|n| n.max(1) as usize);
+    self
+  }
+
+  /// Limit the number of match ranges reported for each matching line.
+  ///
+  /// Lines with more matches are still reported, with the first `limit`
+  /// ranges and `moreMatches` set. This keeps lines with thousands of hits,
+  /// such as minified files, from dominating the result size.
+  #[napi]
+  pub fn max_matches_per_line(&mut self, limit: Option<u32>) -> &Self {
+    self.config.max_matches_per_line = limit;
+    self
+  }
+
+  /// Whether to merge overlapping and adjacent match ranges on a line into
+  /// maximal spans.
+  ///
+  /// This is what highlighters render, and it shrinks results for dense
+  /// matches. Limits on the number of matches count the ranges before they
+  /// are merged.
+  #[napi]
+  pub fn merge_ranges(&mut self, yes: bool) -> &Self {
+    self.config.merge_ranges = yes;
+    self
+  }
+
+  /// Set a budget in bytes for the results of a single search.
+  ///
+  /// Sizes are approximated as in `finish.resultBytes`. A match or context
+  /// line that would take the results over the budget is not collected, and
+  /// `action` decides whether the search then stops with what it has
+  /// (the default) or fails.
+  #[napi]
+  pub fn max_result_bytes(
+    &mut self,
+    limit: Option<u32>,
+    action: Option<ResultLimitAction>,
+  ) -> &Self {
+    self.config.max_result_bytes = limit.map(|l| (l as usize, action.unwrap_or_default()));
+    self
+  }
+
+  /// Set the policy for files that fail to read during multi-file searches.
+  ///
+  /// By default the first read error aborts the search.
+  #[napi]
+  pub fn read_error_policy(&mut self, policy: ReadErrorPolicy) -> &Self {
+    self.config.read_error_policy = policy;
+    self
+  }
+
+  /// Sort results before returning them.
+  ///
+  /// Matching lines are sorted by the given key, with ties kept in file
+  /// order. `searchFiles` and `searchDir` also sort their files, by path,
+  /// by total match count or by the offset of their first match. `searchMatrix` sorts its
+  /// files by path or by total match count; `FirstMatchOffset` leaves them
+  /// in input order. Pass `null` to return results in search order (the
+  /// default).
+  #[napi]
+  pub fn sort_by(&mut self, order: Option<SortBy>) -> &Self {
+    self.config.sort_by = order;
+    self
+  }
+
+  /// Whether to report phase timings in `SearchResult.profile`.
+  ///
+  /// Useful to tell whether a slow search is bound by IO, matching or the
+  /// cost of handing results to JavaScript. While profiling, `searchPath`
+  /// reads files through a timed reader instead of memory maps.
+  #[napi]
+  pub fn profile(&mut self, yes: bool) -> &Self {
+    self.config.profile = yes;
+    self
+  }
+
+  /// Whether to return results made only of plain data.
+  ///
+  /// Plain results leave out the `bytes` of matches, context lines and byte
+  /// windows, and are ordinary objects that survive `structuredClone` and
+  /// `postMessage` unchanged. Byte positions are still available through
+  /// the offsets and lengths in the result.
+  #[napi]
+  pub fn plain_results(&mut self, yes: bool) -> &Self {
+    self.config.plain = yes;
     self
   }
 }
@@ -573,8 +2582,13 @@ impl Default for SearcherBuilder {
 ///
 /// Use `SearcherBuilder` to construct this with custom configuration.
 #[napi]
+#[derive(Clone)]
 pub struct Searcher {
   inner: GrepSearcher,
+  /// The configuration `inner` was built from, for searches that need a
+  /// variation of it.
+  builder: GrepSearcherBuilder,
+  config: SearchConfig,
 }
 
 #[napi]
@@ -582,90 +2596,2722 @@ impl Searcher {
   /// Create a new searcher with default configuration.
   #[napi(constructor)]
   pub fn new() -> Self {
+    let builder = GrepSearcherBuilder::new();
     Self {
-      inner: GrepSearcher::new(),
+      inner: builder.build(),
+      builder,
+      config: SearchConfig::default(),
     }
   }
 
   /// Search a file for matches.
-  #[napi]
-  pub fn search_path(&mut self, matcher: &RegexMatcher, path: String) -> Result<SearchResult> {
-    let mut sink = CollectSink::new(matcher.inner.clone());
-    self
-      .inner
-      .search_path(&*matcher.inner, Path::new(&path), &mut sink)
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
-    Ok(sink.into_result())
+  ///
+  /// `onTick` is called with the number of bytes read so far every
+  /// `tickBytes` bytes, so a long search can report progress or decide to
+  /// stop. Returning false stops the search as if it had been cancelled.
+  #[napi(
+    ts_args_type = "matcher: RegexMatcher, path: string, options?: SearchOptions | undefined | null, onTick?: ((bytesRead: number) => boolean | void) | undefined | null",
+    ts_return_type = "SearchResult"
+  )]
+  pub fn search_path(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    path: String,
+    options: Option<SearchOptions>,
+    on_tick: Option<Function<i64, Option<bool>>>,
+  ) -> Result<SearchOutput> {
+    self.search_calling(
+      env,
+      matcher,
+      Haystack::Path(&path),
+      options.unwrap_or_default(),
+      on_tick,
+    )
   }
 
   /// Search a byte slice for matches.
-  #[napi]
+  ///
+  /// `onTick` is called as by `searchPath`.
+  #[napi(
+    ts_args_type = "matcher: RegexMatcher, slice: string | Buffer, options?: SearchOptions | undefined | null, onTick?: ((bytesRead: number) => boolean | void) | undefined | null",
+    ts_return_type = "SearchResult"
+  )]
   pub fn search_slice(
-    &mut self,
+    &self,
+    env: &Env,
     matcher: &RegexMatcher,
     slice: Either<String, Buffer>,
-  ) -> Result<SearchResult> {
+    options: Option<SearchOptions>,
+    on_tick: Option<Function<i64, Option<bool>>>,
+  ) -> Result<SearchOutput> {
     let bytes = match &slice {
       Either::A(s) => s.as_bytes(),
       Either::B(b) => b.as_ref(),
     };
-    let mut sink = CollectSink::new(matcher.inner.clone());
-    self
-      .inner
-      .search_slice(&*matcher.inner, bytes, &mut sink)
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
-    Ok(sink.into_result())
+    self.search_calling(
+      env,
+      matcher,
+      Haystack::Slice(bytes),
+      options.unwrap_or_default(),
+      on_tick,
+    )
+  }
+
+  /// Search a large byte slice on several threads, as many as `threads`.
+  ///
+  /// The slice is split at line boundaries, each part is searched on its
+  /// own thread with the context lines around it, and the results are
+  /// merged with offsets and line numbers into the whole slice, so the
+  /// result is the same as that of `searchSlice`. Inputs under a few
+  /// megabytes, and searches whose result depends on the input as a whole
+  /// (multi-line matching, match limits, binary detection, BOM transcoding,
+  /// byte context, line ending reports, content hashes and timeouts), are
+  /// searched on the calling thread instead.
+  #[napi(ts_return_type = "SearchResult")]
+  pub fn search_slice_parallel(
+    &self,
+    matcher: &RegexMatcher,
+    slice: Either<String, Buffer>,
+  ) -> Result<SearchOutput> {
+    let bytes = match &slice {
+      Either::A(s) => s.as_bytes(),
+      Either::B(b) => b.as_ref(),
+    };
+    chunks::search(self, matcher, bytes)
   }
 
   /// Search a reader for matches.
+  #[napi(ts_return_type = "SearchResult")]
+  pub fn search_reader(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    data: Buffer,
+    options: Option<SearchOptions>,
+  ) -> Result<SearchOutput> {
+    self.search_calling(
+      env,
+      matcher,
+      Haystack::Reader(&data),
+      options.unwrap_or_default(),
+      None,
+    )
+  }
+
+  /// Search a file on the libuv thread pool, without blocking the event
+  /// loop.
+  ///
+  /// Resolves with the same result as `searchPath`. The promise has the
+  /// `handle` of the search, which `cancelSearch` takes.
+  #[napi(ts_return_type = "Promise<SearchResult> & { handle: number }")]
+  pub fn search_path_async<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    path: String,
+    options: Option<SearchOptions>,
+  ) -> Result<PromiseRaw<'env, SearchOutput>> {
+    self.spawn_search(env, matcher, TaskInput::Path(path), options)
+  }
+
+  /// Search a byte slice on the libuv thread pool, without blocking the
+  /// event loop.
+  ///
+  /// The search reads the Buffer in place, so it must not be modified until
+  /// the promise settles. Resolves with the same result as `searchSlice`.
+  /// The promise has the `handle` of the search, which `cancelSearch` takes.
+  #[napi(ts_return_type = "Promise<SearchResult> & { handle: number }")]
+  pub fn search_slice_async<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    slice: Either<String, Buffer>,
+    options: Option<SearchOptions>,
+  ) -> Result<PromiseRaw<'env, SearchOutput>> {
+    self.spawn_search(env, matcher, TaskInput::Slice(slice), options)
+  }
+
+  /// Search the files below a directory on a background thread.
+  ///
+  /// Files are searched in a stable order and only those with at least one
+  /// match are produced. The read error policy decides whether a file or
+  /// directory that cannot be read ends the iteration with an error or is
+  /// skipped. At most `queueSize` results are buffered, so the search only
+  /// runs ahead of the consumer by that much.
+  #[napi(ts_return_type = "AsyncIterableIterator<DirSearchEntry>")]
+  pub fn search_dir_iter<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    dir: String,
+    options: Option<DirSearchOptions>,
+  ) -> Result<ClassInstance<'env, DirSearch>> {
+    DirSearch::start(env, self.clone(), matcher, dir, options)
+  }
+
+  /// Search the files below a directory, grouping the results by file.
+  ///
+  /// Files are searched in a stable order, filtered as `options` ask, and
+  /// only those with at least one match are returned, in that order unless
+  /// the searcher's `sortBy` orders them. A file that cannot be read fails
+  /// the call, or is returned with its `error` and no matches, according to
+  /// the read error policy.
   #[napi]
-  pub fn search_reader(&mut self, matcher: &RegexMatcher, data: Buffer) -> Result<SearchResult> {
-    let mut sink = CollectSink::new(matcher.inner.clone());
-    let cursor = Cursor::new(data.as_ref());
-    self
-      .inner
-      .search_reader(&*matcher.inner, cursor, &mut sink)
-      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
-    Ok(sink.into_result())
+  pub fn search_dir(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    dir: String,
+    options: Option<DirSearchOptions>,
+  ) -> Result<Vec<FileSearchResult>> {
+    let walker = dir::walker(&dir, &options.unwrap_or_default())
+      .map_err(|e| Failure::classify(e, None).into_error(env))?;
+    dir::collect(self, matcher, &dir, &walker).map_err(|failure| failure.into_error(env))
   }
-}
 
-impl Default for Searcher {
-  fn default() -> Self {
-    Self::new()
+  /// Search the files below a directory on `threads` threads at once, each
+  /// with its own copy of the searcher, passing each file with matches to
+  /// `onFile` as soon as it has been searched.
+  ///
+  /// Files are filtered as `options` ask, but are walked and searched in no
+  /// particular order. `onFinish` is called once the search ends, with the
+  /// error that ended it or the counts of files searched. The read error
+  /// policy decides whether a file that cannot be read ends the search or
+  /// is skipped. The search waits while `queueSize` files are queued for
+  /// `onFile`, so a slow consumer pauses it.
+  #[napi(
+    ts_args_type = "matcher: RegexMatcher, dir: string, onFile: (entry: DirSearchEntry) => void, onFinish?: ((error: Error | null, finish?: DirSearchFinish) => void) | null, options?: DirSearchOptions | null"
+  )]
+  pub fn search_dir_parallel(
+    &self,
+    matcher: &RegexMatcher,
+    dir: String,
+    on_file: dir::FileCallback,
+    on_finish: Option<dir::FinishCallback>,
+    options: Option<DirSearchOptions>,
+  ) -> Result<()> {
+    let options = options.unwrap_or_default();
+    let mut walker = dir::walker(&dir, &options)?;
+    walker.threads(
+      options
+        .threads
+        .map_or_else(|| self.config.threads(), |n| n as usize),
+    );
+    let queue_size = options.queue_size.unwrap_or(16) as usize;
+    let matcher = RegexMatcher {
+      inner: matcher.inner.clone(),
+    };
+    dir::spawn_parallel(
+      self.in_background(),
+      matcher,
+      dir,
+      walker,
+      queue_size,
+      on_file,
+      on_finish,
+    );
+    Ok(())
+  }
+
+  /// Search many files concurrently, returning a result for every path.
+  ///
+  /// The files are shared out among `threads` threads as they finish, so
+  /// one large file does not hold up the rest. Results are in the order of
+  /// the paths unless `ordered` is false, in which case they are in the
+  /// order the searches finished, or the searcher's `sortBy` orders them
+  /// after the search. A file that cannot be read fails the call, or is
+  /// returned with its `error` and no matches, according to the read error
+  /// policy.
+  #[napi]
+  pub fn search_files(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    paths: Vec<String>,
+    options: Option<SearchFilesOptions>,
+  ) -> Result<Vec<FileSearchResult>> {
+    let plan = options.unwrap_or_default().plan(&self.config);
+    files::collect(self, matcher, &paths, plan).map_err(|failure| failure.into_error(env))
+  }
+
+  /// Search many inputs, each a path or the contents of an input, returning
+  /// an entry for every input in order.
+  ///
+  /// A search that fails, such as that of a file that cannot be read, is
+  /// reported in its entry's `error` without affecting the others, whatever
+  /// the read error policy.
+  #[napi]
+  pub fn search_many(
+    &self,
+    matcher: &RegexMatcher,
+    inputs: Vec<Either<String, Buffer>>,
+  ) -> Vec<SearchManyEntry> {
+    inputs
+      .iter()
+      .enumerate()
+      .map(|(i, input)| {
+        let (path, haystack) = match input {
+          Either::A(path) => (Some(path.clone()), Haystack::Path(path)),
+          Either::B(b) => (None, Haystack::Slice(b.as_ref())),
+        };
+        let (result, error) = match self.search(matcher, haystack, SearchOptions::default()) {
+          Ok(output) => (Some(output), None),
+          Err(e) => (None, Some(e)),
+        };
+        SearchManyEntry {
+          index: i as u32,
+          path,
+          result,
+          error_code: error.as_ref().map(error::code),
+          error: error.map(|e| e.reason),
+        }
+      })
+      .collect()
+  }
+
+  /// Search many files concurrently on background threads, producing the
+  /// result of each file as an async iterator.
+  ///
+  /// With `ordered: false`, each result is produced as soon as a thread
+  /// finishes the file, for the lowest latency. Otherwise results are
+  /// produced in the order of the paths. At most `queueSize` results are
+  /// buffered ahead of the consumer, and leaving the loop stops the search.
+  #[napi(ts_return_type = "AsyncIterableIterator<FileSearchResult>")]
+  pub fn search_files_iter<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    paths: Vec<String>,
+    options: Option<SearchFilesOptions>,
+  ) -> Result<ClassInstance<'env, FileSearch>> {
+    let options = options.unwrap_or_default();
+    let queue_size = options.queue_size.unwrap_or(16) as usize;
+    let matcher = RegexMatcher {
+      inner: matcher.inner.clone(),
+    };
+    let closed = Arc::default();
+    let files = files::spawn(
+      self.closed_by(&closed),
+      matcher,
+      paths,
+      options.plan(&self.config),
+      queue_size,
+    );
+    let instance = FileSearch {
+      files: Some(files),
+      closed,
+    }
+    .into_instance(env)?;
+    unsafe { iter::make_async_iterable(env.raw(), instance.value)? };
+    Ok(instance)
+  }
+
+  /// Search a pipe, FIFO or other unbounded input on a background thread.
+  ///
+  /// Pass `-` to search stdin. Match and context lines are produced as soon
+  /// as they are found, and the input is never held in memory beyond the
+  /// searcher's buffer (see `heapLimit`) and `queueSize` lines. Results are
+  /// not sorted, and matchers that normalize their input are not supported.
+  #[napi(ts_return_type = "AsyncIterableIterator<SearchMatch | SearchContext>")]
+  pub fn search_stream<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    path: String,
+    options: Option<StreamSearchOptions>,
+  ) -> Result<ClassInstance<'env, StreamSearch>> {
+    if matcher.inner.fold().is_some() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "streaming search does not support normalization or diacritic folding",
+      ));
+    }
+    let queue_size = options.unwrap_or_default().queue_size.unwrap_or(64) as usize;
+    let closed = Arc::default();
+    let searcher = self.closed_by(&closed);
+    let sink = CollectSink::new(matcher.inner.clone(), &searcher.config);
+    let lines = stream::spawn(searcher, sink, stream::Source::Path(path), queue_size, true);
+    let instance = StreamSearch {
+      lines: Some(lines),
+      closed,
+    }
+    .into_instance(env)?;
+    unsafe { iter::make_async_iterable(env.raw(), instance.value)? };
+    Ok(instance)
+  }
+
+  /// Iterate over the matches of a file, or of a Buffer holding the input
+  /// itself, as a background thread finds them.
+  ///
+  /// Pass `-` to search stdin. At most `queueSize` matches are buffered, so
+  /// the search only runs ahead of the consumer by that much, and stopping
+  /// the iteration stops the search. Context lines are left out, results are
+  /// not sorted, and matchers that normalize their input are not supported.
+  #[napi(ts_return_type = "AsyncIterableIterator<SearchMatch>")]
+  pub fn search_iter<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    input: Either<String, Buffer>,
+    options: Option<StreamSearchOptions>,
+  ) -> Result<ClassInstance<'env, StreamSearch>> {
+    if matcher.inner.fold().is_some() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "streaming search does not support normalization or diacritic folding",
+      ));
+    }
+    let queue_size = options.unwrap_or_default().queue_size.unwrap_or(64) as usize;
+    let closed = Arc::default();
+    let searcher = self.closed_by(&closed);
+    let sink = CollectSink::new(matcher.inner.clone(), &searcher.config);
+    let source = match input {
+      Either::A(path) => stream::Source::Path(path),
+      Either::B(bytes) => stream::Source::Bytes(bytes),
+    };
+    let lines = stream::spawn(searcher, sink, source, queue_size, false);
+    let instance = StreamSearch {
+      lines: Some(lines),
+      closed,
+    }
+    .into_instance(env)?;
+    unsafe { iter::make_async_iterable(env.raw(), instance.value)? };
+    Ok(instance)
+  }
+
+  /// Search a file, or stdin for `-`, on a background thread, producing
+  /// every match and context line, binary data when it is first found, and
+  /// then the summary as records tagged with their `type`.
+  ///
+  /// This is the source of `createSearchStream` (see the `stream` entry
+  /// point). At most `queueSize` records are buffered. Results are not
+  /// sorted, and matchers that normalize their input are not supported.
+  #[napi(
+    ts_return_type = "AsyncIterableIterator<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish)>"
+  )]
+  pub fn search_records<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    path: String,
+    options: Option<StreamSearchOptions>,
+  ) -> Result<ClassInstance<'env, RecordSearch>> {
+    if matcher.inner.fold().is_some() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "streaming search does not support normalization or diacritic folding",
+      ));
+    }
+    let queue_size = options.unwrap_or_default().queue_size.unwrap_or(64) as usize;
+    let closed = Arc::default();
+    let searcher = self.closed_by(&closed);
+    let sink = CollectSink::new(matcher.inner.clone(), &searcher.config);
+    let records = stream::spawn_records(searcher, sink, path, queue_size);
+    let instance = RecordSearch {
+      records: Some(records),
+      closed,
+    }
+    .into_instance(env)?;
+    unsafe { iter::make_async_iterable(env.raw(), instance.value)? };
+    Ok(instance)
+  }
+
+  /// Search a file, or stdin for `-`, on a background thread, passing each
+  /// match to `onMatch` and each context line to `onContext` as soon as it is
+  /// found.
+  ///
+  /// `onFinish` is called once the search ends, with the error that ended it
+  /// or the summary. The search waits while `queueSize` lines (default 64)
+  /// are queued for the callbacks, so a slow consumer pauses it, and stops
+  /// if the environment shuts down. Results are not sorted, and matchers
+  /// that normalize their input are not supported.
+  ///
+  /// The `onBinary` option is called when binary data is first found,
+  /// after the lines before it, so the consumer knows when and why a search
+  /// that quits at binary data stopped producing lines.
+  #[napi(
+    ts_args_type = "matcher: RegexMatcher, path: string, onMatch: (match: SearchMatch) => void, onContext?: ((context: SearchContext) => void) | null, onFinish?: ((error: Error | null, finish?: SearchFinish) => void) | null, options?: StreamingSearchOptions | null"
+  )]
+  pub fn search_path_streaming(
+    &self,
+    matcher: &RegexMatcher,
+    path: String,
+    on_match: stream::LineCallback,
+    on_context: Option<stream::LineCallback>,
+    on_finish: Option<stream::FinishCallback>,
+    options: Option<StreamingSearchOptions>,
+  ) -> Result<()> {
+    if matcher.inner.fold().is_some() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "streaming search does not support normalization or diacritic folding",
+      ));
+    }
+    let options = options.unwrap_or_default();
+    let queue_size = options.queue_size.unwrap_or(64) as usize;
+    let sink = CollectSink::new(matcher.inner.clone(), &self.config);
+    let callbacks = stream::Callbacks {
+      on_match,
+      on_context,
+      on_binary: options.on_binary,
+      on_finish,
+    };
+    stream::spawn_callbacks(self.in_background(), sink, path, queue_size, callbacks);
+    Ok(())
+  }
+
+  /// Count the lines of a file that the matcher does not match.
+  ///
+  /// Lines are only counted, so this is much cheaper than an inverted
+  /// search. The count does not depend on `invertMatch`.
+  #[napi]
+  pub fn count_non_matching_lines(&self, matcher: &RegexMatcher, path: String) -> Result<i64> {
+    let failed = |e: std::io::Error| Error::new(Status::GenericFailure, format!("{}: {}", path, e));
+    let file = std::fs::File::open(&path).map_err(failed)?;
+    let mut sink = CountSink::default();
+    search_opened(&mut self.with_invert_match(true), matcher, file, &mut sink).map_err(failed)?;
+    Ok(sink.count as i64)
+  }
+
+  /// Read the lines around line `lineNumber` (1-based) of a file: up to
+  /// `before` lines before it and `after` lines after it, without the line
+  /// itself.
+  ///
+  /// This lets a search run without context and load it later, such as
+  /// when a result is expanded in a UI. Lines are split and decoded as the
+  /// searcher does, and the file is only read up to the last line needed.
+  #[napi]
+  pub fn get_context(
+    &self,
+    path: String,
+    line_number: u32,
+    before: u32,
+    after: u32,
+  ) -> Result<Vec<SearchContext>> {
+    let failed = |e: std::io::Error| Error::new(Status::GenericFailure, format!("{}: {}", path, e));
+    let file = std::fs::File::open(&path).map_err(failed)?;
+    let mut builder = self.builder.clone();
+    builder
+      .line_number(true)
+      .invert_match(false)
+      .multi_line(false)
+      .before_context(0)
+      .after_context(0);
+    let every_line = GrepRegexMatcher::new("").map_err(|e| Error::from_reason(e.to_string()))?;
+    let line_number = line_number as u64;
+    let mut sink = LineSink {
+      first: line_number.saturating_sub(before as u64).max(1),
+      target: line_number,
+      last: line_number + after as u64,
+      lines: Vec::new(),
+    };
+    // Read through the searcher's buffer, which stops reading once the sink
+    // has the last line.
+    builder
+      .build()
+      .search_reader(&every_line, file, &mut sink)
+      .map_err(failed)?;
+    Ok(sink.lines)
+  }
+
+  /// Make a cursor over `path` that reads lines of the file on demand,
+  /// starting from the nearest line of `result`, the result of searching
+  /// it, rather than from the start of the file.
+  ///
+  /// `result` must come from a search of the file as it is now, with line
+  /// numbers and without a transcoding encoding, as the cursor seeks to the
+  /// byte offsets of its lines.
+  #[napi]
+  pub fn result_cursor(
+    &self,
+    path: String,
+    #[napi(ts_arg_type = "SearchResult")] result: ResultLines,
+  ) -> ResultCursor {
+    let mut anchors: Vec<(u64, u64)> = result
+      .matches
+      .iter()
+      .chain(&result.context)
+      .filter_map(|line| {
+        Some((
+          line.line_number? as u64,
+          line.absolute_byte_offset.max(0) as u64,
+        ))
+      })
+      .collect();
+    anchors.sort_unstable();
+    anchors.dedup_by_key(|&mut (line, _)| line);
+    ResultCursor {
+      path,
+      terminator: self.inner.line_terminator().as_byte(),
+      anchors,
+    }
+  }
+
+  /// List the files in which the matcher matches no line, like
+  /// `rg --files-without-match`. Directories are searched recursively.
+  ///
+  /// Each file is searched only up to its first match. Files that cannot be
+  /// read are left out or fail the call, according to the read error
+  /// policy. The result does not depend on `invertMatch`.
+  #[napi]
+  pub fn files_without_match(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    paths: Vec<String>,
+  ) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for path in paths {
+      files
+        .extend(dir::files(&path).map_err(|e| Failure::classify(e, Some(&path)).into_error(env))?);
+    }
+    let mut searcher = self.with_invert_match(false);
+    let mut found = Vec::new();
+    for (file, error) in files {
+      let path = file.to_string_lossy().into_owned();
+      let mut matched = false;
+      let sink = grep::searcher::sinks::Bytes(|_, _| {
+        matched = true;
+        Ok(false)
+      });
+      let searched = match error {
+        Some(e) => Err(e),
+        None => self
+          .config
+          .retry(|| std::fs::File::open(&path))
+          .and_then(|file| search_opened(&mut searcher, matcher, file, sink)),
+      };
+      if let Err(e) = searched {
+        match self.config.read_error_policy.action.unwrap_or_default() {
+          ReadErrorAction::Abort => {
+            let error = Error::new(Status::GenericFailure, e.to_string());
+            return Err(Failure::file(&path, error).into_error(env));
+          }
+          ReadErrorAction::Skip => continue,
+        }
+      }
+      if !matched {
+        found.push(path);
+      }
+    }
+    Ok(found)
+  }
+
+  /// Count matches for every (matcher, file) pair.
+  ///
+  /// Each file is read once and then searched with every matcher, which
+  /// makes this suitable for rule-coverage reports over many patterns.
+  /// Every match counts, including several on one line; an inverted search
+  /// counts lines.
+  #[napi]
+  pub fn search_matrix(
+    &self,
+    matchers: Vec<ClassInstance<RegexMatcher>>,
+    paths: Vec<String>,
+  ) -> Result<MatchMatrix> {
+    let mut searcher = self.inner.clone();
+    let mut counts = vec![vec![0; paths.len()]; matchers.len()];
+    let mut errors = Vec::new();
+    for (f, path) in paths.iter().enumerate() {
+      let data = match self.config.read_file(path) {
+        Ok(data) => data,
+        Err(e) => match self.config.read_error_policy.action.unwrap_or_default() {
+          ReadErrorAction::Abort => {
+            return Err(Error::new(
+              Status::GenericFailure,
+              format!("{}: {}", path, e),
+            ))
+          }
+          ReadErrorAction::Skip => {
+            errors.push(FileError {
+              path: path.clone(),
+              message: e.to_string(),
+            });
+            continue;
+          }
+        },
+      };
+      let mut folded: Vec<(Fold, Normalized)> = Vec::new();
+      for (m, matcher) in matchers.iter().enumerate() {
+        let haystack = match matcher.inner.fold() {
+          Some(fold) => {
+            let i = match folded.iter().position(|(f, _)| *f == fold) {
+              Some(i) => i,
+              None => {
+                folded.push((fold, Normalized::new(&data, fold)));
+                folded.len() - 1
+              }
+            };
+            folded[i].1.text()
+          }
+          None => &data,
+        };
+        let mut sink = MatchCountSink {
+          matcher: &matcher.inner,
+          overlapping: self.config.overlapping,
+          count: 0,
+        };
+        searcher
+          .search_slice(&*matcher.inner, haystack, &mut sink)
+          .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+        counts[m][f] = sink.count as u32;
+      }
+    }
+    let mut matrix = MatchMatrix {
+      paths,
+      counts,
+      errors,
+    };
+    if let Some(order) = self.config.sort_by {
+      sort_matrix(&mut matrix, order);
+    }
+    Ok(matrix)
   }
 }
 
-// ============================================================================
-// Internal Sink implementation
-// ============================================================================
+impl Searcher {
+  /// Search `input` on the libuv thread pool, registered under the
+  /// `handle` of the returned promise.
+  fn spawn_search<'env>(
+    &self,
+    env: &'env Env,
+    matcher: &RegexMatcher,
+    input: TaskInput,
+    options: Option<SearchOptions>,
+  ) -> Result<PromiseRaw<'env, SearchOutput>> {
+    let task = self.search_task(matcher, input, options);
+    let handle = task.handle;
+    let mut promise = env.spawn(task)?.promise_object();
+    promise.set_named_property("handle", handle)?;
+    Ok(promise)
+  }
+
+  /// A search of `input`, registered so `cancelSearch` can stop it.
+  fn search_task(
+    &self,
+    matcher: &RegexMatcher,
+    input: TaskInput,
+    options: Option<SearchOptions>,
+  ) -> SearchTask {
+    let cancel = Arc::default();
+    let handle = registry::register(&cancel);
+    // Slices are read in blocks when the search may be stopped, so one
+    // searched in place is only checked for cancellation as it starts.
+    let searcher = match input {
+      TaskInput::Slice(_) if !self.config.interruptible() => self.in_background(),
+      _ => self.closed_by(&cancel),
+    };
+    SearchTask {
+      searcher,
+      matcher: RegexMatcher {
+        inner: matcher.inner.clone(),
+      },
+      input,
+      options: options.unwrap_or_default(),
+      cancel,
+      handle,
+    }
+  }
+
+  /// A copy of the searcher for a search off the JavaScript thread, which
+  /// also stops once `closed` is set.
+  fn closed_by(&self, closed: &Arc<AtomicBool>) -> Searcher {
+    let mut searcher = self.in_background();
+    searcher.config.cancel.push(closed.clone());
+    searcher
+  }
+
+  /// A copy of the searcher for a search off the JavaScript thread, which
+  /// may retry failed reads.
+  fn in_background(&self) -> Searcher {
+    let mut searcher = self.clone();
+    searcher.config.background = true;
+    searcher
+  }
+
+  /// A copy of the underlying searcher with `invertMatch` set to `yes` and
+  /// no `maxMatches`, for searches that count or look for matching lines.
+  fn with_invert_match(&self, yes: bool) -> GrepSearcher {
+    let mut builder = self.builder.clone();
+    builder.invert_match(yes).max_matches(None);
+    builder.build()
+  }
+
+  fn search(
+    &self,
+    matcher: &RegexMatcher,
+    haystack: Haystack<'_>,
+    options: SearchOptions,
+  ) -> Result<SearchOutput> {
+    self.search_ticking(matcher, haystack, options, None)
+  }
+
+  /// Search `haystack`, calling `on_tick` every `tickBytes` bytes read. An
+  /// exception thrown by `on_tick` stops the search and is rethrown.
+  fn search_calling(
+    &self,
+    env: &Env,
+    matcher: &RegexMatcher,
+    haystack: Haystack<'_>,
+    options: SearchOptions,
+    on_tick: Option<Function<i64, Option<bool>>>,
+  ) -> Result<SearchOutput> {
+    let path = match haystack {
+      Haystack::Path(path) => Some(path),
+      _ => None,
+    };
+    let failed_search = |e| Failure::classify(e, path).into_error(env);
+    let Some(on_tick) = on_tick else {
+      return self
+        .search(matcher, haystack, options)
+        .map_err(failed_search);
+    };
+    let failed = RefCell::new(None);
+    let call = |read: u64| match on_tick.call(read as i64) {
+      Ok(more) => more != Some(false),
+      Err(e) => {
+        failed.replace(Some(e));
+        false
+      }
+    };
+    let tick = Tick {
+      every: options.tick_bytes.unwrap_or(16 << 20).max(1) as u64,
+      call: &call,
+    };
+    let output = self.search_ticking(matcher, haystack, options, Some(&tick));
+    match failed.into_inner() {
+      Some(e) => Err(e),
+      None => output.map_err(failed_search),
+    }
+  }
+
+  /// Search `haystack`, calling `tick` as the input is read, and record the
+  /// search in the metrics.
+  fn search_ticking(
+    &self,
+    matcher: &RegexMatcher,
+    haystack: Haystack<'_>,
+    options: SearchOptions,
+    tick: Option<&Tick>,
+  ) -> Result<SearchOutput> {
+    let _running = metrics::start();
+    let output = self.search_haystack(matcher, haystack, options, tick);
+    match &output {
+      Ok(output) => {
+        let result = &output.result;
+        metrics::completed(result.finish.byte_count, result.matches.len());
+      }
+      Err(e) => metrics::failed(e),
+    }
+    output
+  }
+
+  /// Search `haystack`, calling `tick` as the input is read.
+  fn search_haystack(
+    &self,
+    matcher: &RegexMatcher,
+    haystack: Haystack<'_>,
+    options: SearchOptions,
+    tick: Option<&Tick>,
+  ) -> Result<SearchOutput> {
+    let from_start = options.resume_from.unwrap_or(0) <= options.base_offset.unwrap_or(0);
+    // Offsets into a transcoded input are offsets into the decoded text,
+    // and a search started past the byte order mark would not decode it.
+    if !from_start && self.transcodes(&haystack)? {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "resumeFrom does not apply to inputs with a byte order mark",
+      ));
+    }
+    let transcoded = match self.config.encoding_error {
+      Some(action) if !self.config.bom_sniffing_disabled => {
+        self.search_transcoded(matcher, &haystack, &options, action, tick)?
+      }
+      _ => None,
+    };
+    let mut output = match transcoded {
+      Some(output) => output,
+      None => self.search_input(matcher, &haystack, &options, tick)?,
+    };
+    if output.result.finish.resume_offset.is_some() && self.transcodes(&haystack)? {
+      output.result.finish.resume_offset = None;
+    }
+    if self.config.bom == BomHandling::Preserve && from_start && self.transcodes(&haystack)? {
+      keep_bom(&mut output.result, options.base_offset.unwrap_or(0));
+    }
+    Ok(output)
+  }
+
+  /// Whether `haystack` is transcoded before it is searched, because it
+  /// starts with a byte order mark. Files that are not regular files are
+  /// not read again, as their contents may not be there a second time.
+  fn transcodes(&self, haystack: &Haystack<'_>) -> Result<bool> {
+    if self.config.bom_sniffing_disabled {
+      return Ok(false);
+    }
+    let io_error = |e: std::io::Error| Error::new(Status::GenericFailure, e.to_string());
+    match *haystack {
+      Haystack::Path(path) => {
+        if !std::fs::metadata(path).map_err(io_error)?.is_file() {
+          return Ok(false);
+        }
+        let mut head = Vec::new();
+        std::fs::File::open(path)
+          .and_then(|file| file.take(3).read_to_end(&mut head))
+          .map_err(io_error)?;
+        Ok(transcode::has_bom(&head))
+      }
+      Haystack::Slice(bytes) | Haystack::Reader(bytes) => Ok(transcode::has_bom(bytes)),
+    }
+  }
+
+  /// Search `haystack` as grep would, apart from the options this crate
+  /// implements itself.
+  fn search_input(
+    &self,
+    matcher: &RegexMatcher,
+    haystack: &Haystack<'_>,
+    options: &SearchOptions,
+    tick: Option<&Tick>,
+  ) -> Result<SearchOutput> {
+    let started = Instant::now();
+    // grep's searcher keeps per-search state, so every search uses its own
+    // copy and one `Searcher` can serve concurrent calls.
+    let mut searcher = self.inner.clone();
+    let mut sink = CollectSink::new(matcher.inner.clone(), &self.config);
+    let read = ReadStats {
+      cancel: self.config.cancel.clone(),
+      deadline: sink.deadline,
+      terminator: self.inner.line_terminator().as_byte(),
+      tick,
+      ..Default::default()
+    };
+    // Inputs are read in blocks when the search may be stopped.
+    let interruptible = self.config.interruptible() || tick.is_some();
+    if self.config.line_endings {
+      read.line_endings.set(Some(LineEndingCount::default()));
+    }
+    *read.hasher.borrow_mut() = self.config.content_hash.map(hash::Hasher::new);
+    let mut transcode = Duration::ZERO;
+    let mut copies = 0;
+    // Inputs searched in place are held in memory whole.
+    let mut buffer = 0;
+    let io_error = |e: std::io::Error| Error::new(Status::GenericFailure, e.to_string());
+    let input_len = match haystack {
+      Haystack::Path(path) => {
+        let metadata = self.config.retry(|| std::fs::metadata(path));
+        metadata.map_err(io_error)?.len()
+      }
+      Haystack::Slice(bytes) | Haystack::Reader(bytes) => bytes.len() as u64,
+    };
+    let base_offset = options.base_offset.unwrap_or(0);
+    let base_lines = options.first_line_number.unwrap_or(1).saturating_sub(1);
+    let start = (options.resume_from.unwrap_or(0) - base_offset).clamp(0, input_len as i64) as u64;
+    let terminator = self.inner.line_terminator().as_byte();
+    let skipped_lines = match start {
+      0 => 0,
+      _ if !self.inner.line_number() => 0,
+      _ => {
+        line_position(haystack, start, terminator)
+          .map_err(io_error)?
+          .0
+      }
+    };
+    if let (Haystack::Path(path), Some(hasher)) = (haystack, read.hasher.borrow_mut().as_mut()) {
+      if start > 0 {
+        // The reader starts at `start`, so hash what it skips first.
+        let file = std::fs::File::open(path).map_err(io_error)?;
+        std::io::copy(&mut file.take(start), hasher).map_err(io_error)?;
+      }
+    }
+    let normalized = match matcher.inner.fold() {
+      Some(fold) => {
+        let data = match haystack {
+          Haystack::Path(path) => {
+            let read_started = Instant::now();
+            let mut data = self.config.read_file(path).map_err(io_error)?;
+            read.elapsed.set(read_started.elapsed());
+            read.hash_input(&data);
+            copies += data.len();
+            data.drain(..(start as usize).min(data.len()));
+            std::borrow::Cow::Owned(data)
+          }
+          Haystack::Slice(bytes) | Haystack::Reader(bytes) => {
+            std::borrow::Cow::Borrowed(&bytes[start as usize..])
+          }
+        };
+        let transcode_started = Instant::now();
+        let normalized = Normalized::new(&data, fold);
+        transcode = transcode_started.elapsed();
+        buffer = normalized.text().len();
+        searcher
+          .search_slice(&*matcher.inner, normalized.text(), &mut sink)
+          .map_err(io_error)?;
+        Some((data, normalized))
+      }
+      None => {
+        match haystack {
+          Haystack::Path(path)
+            if self.config.profile
+              || self.config.line_endings
+              || self.config.content_hash.is_some()
+              || interruptible
+              || !self.config.memory_map
+              || start > 0 =>
+          {
+            let file = MeteredReader::new(&read, || {
+              let mut file = self.config.retry(|| std::fs::File::open(path))?;
+              // Pipes cannot seek, even to where they already are.
+              if start > 0 {
+                file.seek(std::io::SeekFrom::Start(start))?;
+              }
+              Ok(file)
+            })
+            .map_err(io_error)?;
+            searcher.search_reader(&*matcher.inner, file, &mut sink)
+          }
+          Haystack::Path(path) => {
+            // grep falls back to reading when it cannot map the file, in
+            // which case this overestimates.
+            buffer = std::fs::metadata(path).map_or(0, |m| m.len() as usize);
+            searcher.search_path(&*matcher.inner, Path::new(path), &mut sink)
+          }
+          Haystack::Slice(bytes) if !interruptible => {
+            let bytes = &bytes[start as usize..];
+            buffer = bytes.len();
+            searcher.search_slice(&*matcher.inner, bytes, &mut sink)
+          }
+          // A slice is read in blocks when the search may be stopped.
+          Haystack::Slice(bytes) | Haystack::Reader(bytes) => {
+            let bytes = &bytes[start as usize..];
+            let cursor = MeteredReader::new(&read, || Ok(Cursor::new(bytes))).map_err(io_error)?;
+            searcher.search_reader(&*matcher.inner, cursor, &mut sink)
+          }
+        }
+        .map_err(io_error)?;
+        None
+      }
+    };
+    let collect = sink.collect_time;
+    let result_bytes = sink.result_bytes;
+    let timed_out = sink.timed_out_at.is_some() || read.timed_out.get();
+    let buffer_bytes = buffer.max(sink.peak_buffer).max(read.max_read.get());
+    let mut result = sink.into_result();
+    result.finish.peak_buffer_bytes = buffer_bytes as i64;
+    result.finish.result_bytes = result_bytes as i64;
+    result.finish.peak_memory_bytes = (buffer_bytes + result_bytes + copies) as i64;
+    if self.config.timeout.is_some() {
+      result.finish.timed_out = Some(timed_out);
+    }
+    if let Some((data, normalized)) = &normalized {
+      denormalize_result(&mut result, normalized, data);
+    }
+    if let Some(mut count) = read.line_endings.get() {
+      let read_in_place = match (&normalized, haystack) {
+        (Some((data, _)), _) => Some(&data[..]),
+        (None, Haystack::Slice(bytes)) => Some(&bytes[start as usize..]),
+        _ => None,
+      };
+      if let Some(bytes) = read_in_place {
+        count.add(&bytes[..(result.finish.byte_count as usize).min(bytes.len())]);
+      }
+      result.finish.line_endings = Some(count.report());
+    }
+    if let Some(mut hasher) = read.hasher.take() {
+      match haystack {
+        Haystack::Path(path) if hasher.len() < input_len => {
+          // The search stopped early, so read the rest for the hash.
+          let mut file = std::fs::File::open(path).map_err(io_error)?;
+          file
+            .seek(std::io::SeekFrom::Start(hasher.len()))
+            .map_err(io_error)?;
+          std::io::copy(&mut file, &mut hasher).map_err(io_error)?;
+        }
+        Haystack::Path(_) => {}
+        Haystack::Slice(bytes) | Haystack::Reader(bytes) => {
+          hasher.update(&bytes[(hasher.len() as usize).min(bytes.len())..]);
+        }
+      }
+      result.finish.content_hash = Some(hasher.finish());
+    }
+    let searched = (input_len - start) as i64;
+    if result.finish.byte_count < searched {
+      result.finish.resume_offset = Some(result.finish.byte_count);
+    }
+    offset_result(
+      &mut result,
+      base_offset + start as i64,
+      base_lines + skipped_lines as u32,
+    );
+    if self.inner.stop_on_nonmatch()
+      && !result.finish.truncated
+      && result.finish.binary_byte_offset.is_none()
+    {
+      // The search ends after the line that stopped it.
+      if let Some(end) = result
+        .finish
+        .resume_offset
+        .map(|end| end - base_offset)
+        .filter(|&end| end > 0)
+      {
+        let (lines, line_start) =
+          line_position(haystack, end as u64 - 1, terminator).map_err(io_error)?;
+        result.finish.stopped_at = Some(StopPosition {
+          line_number: self
+            .inner
+            .line_number()
+            .then_some(base_lines + lines as u32 + 1),
+          absolute_byte_offset: base_offset + line_start as i64,
+        });
+      }
+    }
+    if self.config.profile {
+      let total = started.elapsed();
+      let read = read.elapsed.get();
+      result.profile = Some(SearchProfile {
+        read_ms: millis(read),
+        transcode_ms: millis(transcode),
+        match_ms: millis(total.saturating_sub(read + transcode + collect)),
+        collect_ms: millis(collect),
+        marshal_ms: 0.0,
+        total_ms: millis(total),
+      });
+    }
+    Ok(SearchOutput {
+      result,
+      plain: self.config.plain,
+    })
+  }
+
+  /// Decode an input with a byte order mark under an encoding error policy
+  /// and search the decoded text. Returns `None` for other inputs.
+  fn search_transcoded(
+    &self,
+    matcher: &RegexMatcher,
+    haystack: &Haystack<'_>,
+    options: &SearchOptions,
+    action: EncodingErrorAction,
+    tick: Option<&Tick>,
+  ) -> Result<Option<SearchOutput>> {
+    let io_error = |e: std::io::Error| Error::new(Status::GenericFailure, e.to_string());
+    let bytes = match *haystack {
+      Haystack::Path(path) => {
+        let mut head = Vec::new();
+        std::fs::File::open(path)
+          .and_then(|file| file.take(3).read_to_end(&mut head))
+          .map_err(io_error)?;
+        if !transcode::has_bom(&head) {
+          return Ok(None);
+        }
+        std::borrow::Cow::Owned(self.config.read_file(path).map_err(io_error)?)
+      }
+      Haystack::Slice(bytes) | Haystack::Reader(bytes) => std::borrow::Cow::Borrowed(bytes),
+    };
+    let Some(decoded) = transcode::decode(&bytes) else {
+      return Ok(None);
+    };
+    if let (EncodingErrorAction::Abort, Some(at)) = (action, decoded.first_error) {
+      return Err(Error::new(
+        Status::GenericFailure,
+        format!(
+          "{}{} data at byte {}",
+          error::UNDECODABLE,
+          decoded.encoding.name(),
+          at
+        ),
+      ));
+    }
+    // The caller records the search in the metrics, once.
+    let mut output = self.search_haystack(
+      matcher,
+      Haystack::Slice(&decoded.text),
+      options.clone(),
+      tick,
+    )?;
+    let result = &mut output.result;
+    result.finish.encoding_errors = Some(decoded.errors.len() as u32);
+    // The decoded text was hashed, but the hash is of the input.
+    result.finish.content_hash = self
+      .config
+      .content_hash
+      .map(|algorithm| hash::digest(algorithm, &bytes));
+    if let EncodingErrorAction::SkipLine = action {
+      let lines = decoded.error_lines();
+      let base_offset = options.base_offset.unwrap_or(0);
+      let undecodable = |offset: i64, len: usize| {
+        let start = (offset - base_offset) as usize;
+        let end = start + len.max(1);
+        lines
+          .iter()
+          .any(|(line, _)| line.start < end && start < line.end)
+      };
+      result
+        .matches
+        .retain(|m| !undecodable(m.absolute_byte_offset, m.bytes.len()));
+      result
+        .context
+        .retain(|c| !undecodable(c.absolute_byte_offset, c.bytes.len()));
+      let base_lines = options.first_line_number.unwrap_or(1).saturating_sub(1);
+      result.finish.undecodable_lines = Some(lines.iter().map(|(_, n)| base_lines + n).collect());
+    }
+    Ok(Some(output))
+  }
+}
+
+impl Default for Searcher {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// A search run on the libuv thread pool.
+pub struct SearchTask {
+  searcher: Searcher,
+  matcher: RegexMatcher,
+  input: TaskInput,
+  options: SearchOptions,
+  /// Set by `cancelSearch`.
+  cancel: Arc<AtomicBool>,
+  /// The handle `cancelSearch` takes.
+  handle: u32,
+}
+
+/// The input of a `SearchTask`, owned by the task while it runs.
+enum TaskInput {
+  Path(String),
+  Slice(Either<String, Buffer>),
+}
+
+impl Task for SearchTask {
+  type Output = SearchOutput;
+  type JsValue = SearchOutput;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    if let Err(e) = check_cancelled(std::slice::from_ref(&self.cancel)) {
+      metrics::cancelled_early();
+      return Err(Error::new(Status::GenericFailure, e.to_string()));
+    }
+    let haystack = match &self.input {
+      TaskInput::Path(path) => Haystack::Path(path),
+      TaskInput::Slice(Either::A(s)) => Haystack::Slice(s.as_bytes()),
+      TaskInput::Slice(Either::B(b)) => Haystack::Slice(b.as_ref()),
+    };
+    self
+      .searcher
+      .search(&self.matcher, haystack, self.options.clone())
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    let path = match &self.input {
+      TaskInput::Path(path) => Some(path.as_str()),
+      TaskInput::Slice(_) => None,
+    };
+    Err(Failure::classify(err, path).into_error(&env))
+  }
+}
+
+// ============================================================================
+// ResultCursor
+// ============================================================================
+
+/// Reads lines of a searched file on demand, such as to show more lines
+/// around a result in a UI without searching the file again.
+///
+/// Created by `Searcher.resultCursor`. Reading starts at the line of the
+/// result nearest to the first line asked for, going back from a later line
+/// when that is nearer, so lines near a result are read without reading the
+/// file from the start.
+#[napi]
+pub struct ResultCursor {
+  path: String,
+  terminator: u8,
+  /// The line numbers and byte offsets of the result's lines, by line.
+  anchors: Vec<(u64, u64)>,
+}
+
+#[napi]
+impl ResultCursor {
+  /// Read lines `first` to `last` (1-based, inclusive) of the file, as
+  /// context lines of kind `Other`. Lines past the end of the file are left
+  /// out.
+  #[napi]
+  pub fn read_lines(&self, first: u32, last: u32) -> Result<Vec<SearchContext>> {
+    let (first, last) = (first.max(1) as u64, last as u64);
+    let failed =
+      |e: std::io::Error| Error::new(Status::GenericFailure, format!("{}: {}", self.path, e));
+    let nearest = self.anchors.partition_point(|&(line, _)| line <= first);
+    let mut file = std::fs::File::open(&self.path).map_err(failed)?;
+    let (mut number, mut offset) = match (nearest, self.anchors.get(nearest)) {
+      (i, Some(&next)) if i == 0 || next.0 - first < first - self.anchors[i - 1].0 => (
+        first,
+        self.line_start(&mut file, first, next).map_err(failed)?,
+      ),
+      (0, None) => (1, 0),
+      (i, _) => self.anchors[i - 1],
+    };
+    file.seek(SeekFrom::Start(offset)).map_err(failed)?;
+    let mut reader = BufReader::new(file);
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    while number <= last {
+      line.clear();
+      let read = reader
+        .read_until(self.terminator, &mut line)
+        .map_err(failed)?;
+      if read == 0 {
+        break;
+      }
+      if number >= first {
+        lines.push(SearchContext {
+          line_number: Some(number as u32),
+          absolute_byte_offset: offset as i64,
+          line: String::from_utf8_lossy(&line).into_owned(),
+          bytes: Buffer::from(line.clone()),
+          kind: ContextKind::Other,
+        });
+      }
+      number += 1;
+      offset += read as u64;
+    }
+    Ok(lines)
+  }
+}
+
+impl ResultCursor {
+  /// The offset of line `line`, found by reading back from `anchor`, the
+  /// number and offset of a later line, in blocks and counting line
+  /// terminators.
+  fn line_start(
+    &self,
+    file: &mut std::fs::File,
+    line: u64,
+    (mut number, offset): (u64, u64),
+  ) -> std::io::Result<u64> {
+    let mut block = vec![0; 64 * 1024];
+    // The byte before the anchor ends the line before it.
+    let mut end = offset.saturating_sub(1);
+    while end > 0 {
+      let start = end.saturating_sub(block.len() as u64);
+      let block = &mut block[..(end - start) as usize];
+      file.seek(SeekFrom::Start(start))?;
+      file.read_exact(block)?;
+      for (i, &byte) in block.iter().enumerate().rev() {
+        if byte == self.terminator {
+          number -= 1;
+          if number == line {
+            return Ok(start + i as u64 + 1);
+          }
+        }
+      }
+      end = start;
+    }
+    Ok(0)
+  }
+}
+
+// ============================================================================
+// DirSearch
+// ============================================================================
+
+/// The files with matches below a directory, as an async iterator.
+///
+/// Use with `for await`. Leaving the loop early stops the search.
+#[napi]
+pub struct DirSearch {
+  entries: Option<iter::Queue<DirSearchEntry>>,
+  /// Set by `close`, to stop the search.
+  closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl DirSearch {
+  /// Wait for the next file with matches.
+  #[napi(ts_return_type = "Promise<IteratorResult<DirSearchEntry, undefined>>")]
+  pub fn next<'env>(&self, env: &'env Env) -> Result<Object<'env>> {
+    iter::Queue::next(self.entries.as_ref(), env)
+  }
+
+  /// Stop the search.
+  #[napi(
+    js_name = "return",
+    ts_return_type = "IteratorResult<DirSearchEntry, undefined>"
+  )]
+  pub fn stop(&mut self) -> iter::Step<DirSearchEntry> {
+    self.close();
+    iter::Step(None)
+  }
+
+  /// Stop the search, releasing its input right away rather than when the
+  /// search next produces a value. Also available as `[Symbol.dispose]` and
+  /// `[Symbol.asyncDispose]` where the runtime defines them.
+  #[napi]
+  pub fn close(&mut self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.entries = None;
+  }
+}
+
+impl DirSearch {
+  fn start<'env>(
+    env: &'env Env,
+    searcher: Searcher,
+    matcher: &RegexMatcher,
+    dir: String,
+    options: Option<DirSearchOptions>,
+  ) -> Result<ClassInstance<'env, DirSearch>> {
+    let options = options.unwrap_or_default();
+    let walker = dir::walker(&dir, &options)?;
+    let queue_size = options.queue_size.unwrap_or(16) as usize;
+    let matcher = RegexMatcher {
+      inner: matcher.inner.clone(),
+    };
+    let closed = Arc::default();
+    let searcher = searcher.closed_by(&closed);
+    let entries = dir::spawn(searcher, matcher, dir, walker, queue_size);
+    let instance = DirSearch {
+      entries: Some(entries),
+      closed,
+    }
+    .into_instance(env)?;
+    unsafe { iter::make_async_iterable(env.raw(), instance.value)? };
+    Ok(instance)
+  }
+}
+
+/// The results of `Searcher.searchFilesIter`, as an async iterator.
+///
+/// Use with `for await`. Leaving the loop early stops the search.
+#[napi]
+pub struct FileSearch {
+  files: Option<iter::Queue<FileSearchResult>>,
+  /// Set by `close`, to stop the search.
+  closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl FileSearch {
+  /// Wait for the result of the next file.
+  #[napi(ts_return_type = "Promise<IteratorResult<FileSearchResult, undefined>>")]
+  pub fn next<'env>(&self, env: &'env Env) -> Result<Object<'env>> {
+    iter::Queue::next(self.files.as_ref(), env)
+  }
+
+  /// Stop the search.
+  #[napi(
+    js_name = "return",
+    ts_return_type = "IteratorResult<FileSearchResult, undefined>"
+  )]
+  pub fn stop(&mut self) -> iter::Step<FileSearchResult> {
+    self.close();
+    iter::Step(None)
+  }
+
+  /// Stop the search, releasing its input right away rather than when the
+  /// search next produces a value. Also available as `[Symbol.dispose]` and
+  /// `[Symbol.asyncDispose]` where the runtime defines them.
+  #[napi]
+  pub fn close(&mut self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.files = None;
+  }
+}
+
+/// The match and context lines of a streaming search, as an async
+/// iterator.
+///
+/// Use with `for await`. Leaving the loop early stops the search.
+#[napi]
+pub struct StreamSearch {
+  lines: Option<iter::Queue<stream::StreamLine>>,
+  /// Set by `close`, to stop the search.
+  closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl StreamSearch {
+  /// Wait for the next match or context line.
+  #[napi(ts_return_type = "Promise<IteratorResult<SearchMatch | SearchContext, undefined>>")]
+  pub fn next<'env>(&self, env: &'env Env) -> Result<Object<'env>> {
+    iter::Queue::next(self.lines.as_ref(), env)
+  }
+
+  /// Stop the search.
+  #[napi(
+    js_name = "return",
+    ts_return_type = "IteratorResult<SearchMatch | SearchContext, undefined>"
+  )]
+  pub fn stop(&mut self) -> iter::Step<stream::StreamLine> {
+    self.close();
+    iter::Step(None)
+  }
+
+  /// Stop the search, releasing its input right away rather than when the
+  /// search next produces a value. Also available as `[Symbol.dispose]` and
+  /// `[Symbol.asyncDispose]` where the runtime defines them.
+  #[napi]
+  pub fn close(&mut self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.lines = None;
+  }
+}
+
+/// The records of a search, produced by `Searcher.searchRecords`.
+#[napi]
+pub struct RecordSearch {
+  records: Option<iter::Queue<stream::StreamRecord>>,
+  /// Set by `close`, to stop the search.
+  closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl RecordSearch {
+  /// Wait for the next record.
+  #[napi(
+    ts_return_type = "Promise<IteratorResult<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish), undefined>>"
+  )]
+  pub fn next<'env>(&self, env: &'env Env) -> Result<Object<'env>> {
+    iter::Queue::next(self.records.as_ref(), env)
+  }
+
+  /// Stop the search.
+  #[napi(
+    js_name = "return",
+    ts_return_type = "IteratorResult<({ type: 'match' } & SearchMatch) | ({ type: 'context' } & SearchContext) | ({ type: 'binary' } & BinaryEvent) | ({ type: 'finish' } & SearchFinish), undefined>"
+  )]
+  pub fn stop(&mut self) -> iter::Step<stream::StreamRecord> {
+    self.close();
+    iter::Step(None)
+  }
+
+  /// Stop the search, releasing its input right away rather than when the
+  /// search next produces a value. Also available as `[Symbol.dispose]` and
+  /// `[Symbol.asyncDispose]` where the runtime defines them.
+  #[napi]
+  pub fn close(&mut self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.records = None;
+  }
+}
+
+// ============================================================================
+// KeywordScanner
+// ============================================================================
+
+/// Scans text for a large list of keywords at once.
+///
+/// Keywords are matched literally with an Aho-Corasick automaton, which
+/// scales to word lists far larger than a regex alternation comfortably
+/// handles. Every occurrence of every keyword is reported, including
+/// keywords that overlap.
+#[napi]
+pub struct KeywordScanner {
+  automaton: KeywordAutomaton,
+  /// The word list, including empty keywords so that matches report their
+  /// index in it.
+  keywords: Vec<String>,
+  word: bool,
+  case_insensitive: bool,
+}
+
+/// How a `KeywordScanner` finds its keywords.
+enum KeywordAutomaton {
+  /// Built from the non-empty keywords, at the given indexes of the word
+  /// list.
+  Built(AhoCorasick, Vec<u32>),
+  /// Loaded from a file saved by `KeywordScanner.save`.
+  Stored(keywords::Stored),
+}
+
+#[napi]
+impl KeywordScanner {
+  /// Create a scanner for the given keywords. Empty keywords are ignored,
+  /// but still count towards the `index` of the keywords after them.
+  #[napi(factory)]
+  pub fn from_keywords(
+    keywords: Vec<String>,
+    options: Option<KeywordScannerOptions>,
+  ) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let indexes: Vec<u32> = (0..keywords.len() as u32)
+      .filter(|&i| !keywords[i as usize].is_empty())
+      .collect();
+    let case_insensitive = options.case_insensitive.unwrap_or(false);
+    let automaton = AhoCorasick::builder()
+      .ascii_case_insensitive(case_insensitive)
+      .build(indexes.iter().map(|&i| &keywords[i as usize]))
+      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+    Ok(Self {
+      automaton: KeywordAutomaton::Built(automaton, indexes),
+      keywords,
+      word: options.word.unwrap_or(true),
+      case_insensitive,
+    })
+  }
+
+  /// Create a scanner from a word list file with one keyword per line.
+  ///
+  /// Blank lines are skipped, and so are `#` comments with `skipComments`.
+  #[napi(factory)]
+  pub fn from_file(
+    env: &Env,
+    path: String,
+    options: Option<KeywordScannerOptions>,
+  ) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let data = std::fs::read(&path).map_err(|e| {
+      let error = Error::new(Status::GenericFailure, format!("{}: {}", path, e));
+      Failure::classify(error, Some(&path)).into_error(env)
+    })?;
+    let lines = PatternFileOptions {
+      skip_blank_lines: Some(true),
+      skip_comments: options.skip_comments,
+    };
+    let keywords = pattern_lines(&data, &lines)
+      .map_err(|e| Error::new(Status::InvalidArg, format!("{}:{}", path, e)))?;
+    Self::from_keywords(keywords, Some(options))
+  }
+
+  /// Load a scanner saved by `save`, reading its file rather than building
+  /// it again.
+  ///
+  /// A loaded scanner is ready at once but scans more slowly than a built
+  /// one, about one and a half times as long.
+  #[napi(factory)]
+  pub fn load(env: &Env, path: String) -> Result<Self> {
+    let loaded =
+      keywords::load(&path).map_err(|e| Failure::classify(e, Some(&path)).into_error(env))?;
+    Ok(Self {
+      automaton: KeywordAutomaton::Stored(loaded.automaton),
+      keywords: loaded.keywords,
+      word: loaded.word,
+      case_insensitive: false,
+    })
+  }
+
+  /// Save the scanner to a file, to be loaded again with `load`.
+  ///
+  /// The file holds the keywords, the options and the compiled automaton,
+  /// which `load` searches straight from the file. The file is replaced
+  /// only once it is written in full.
+  #[napi]
+  pub fn save(&self, env: &Env, path: String) -> Result<()> {
+    let saved = match &self.automaton {
+      KeywordAutomaton::Built(..) => {
+        keywords::save(&path, &self.keywords, self.case_insensitive, self.word)
+      }
+      KeywordAutomaton::Stored(stored) => keywords::write(&path, stored.bytes()),
+    };
+    saved.map_err(|e| Failure::classify(e, Some(&path)).into_error(env))
+  }
+
+  /// The number of keywords in the scanner.
+  #[napi(getter)]
+  pub fn keyword_count(&self) -> u32 {
+    self.keywords.iter().filter(|k| !k.is_empty()).count() as u32
+  }
+
+  /// Find all keywords in the given text, ordered by position.
+  #[napi]
+  pub fn scan(&self, text: Either<String, Buffer>) -> Vec<KeywordMatch> {
+    let bytes = match &text {
+      Either::A(s) => s.as_bytes(),
+      Either::B(b) => b.as_ref(),
+    };
+    self.scan_bytes(bytes)
+  }
+
+  /// Scan files for keywords. Directories are searched recursively.
+  ///
+  /// Only files with at least one match are included in the result.
+  #[napi]
+  pub fn scan_paths(&self, env: &Env, paths: Vec<String>) -> Result<Vec<KeywordFileMatches>> {
+    let mut files = Vec::new();
+    for path in paths {
+      files
+        .extend(dir::files(&path).map_err(|e| Failure::classify(e, Some(&path)).into_error(env))?);
+    }
+    let mut results = Vec::new();
+    for (file, error) in files {
+      let data = match error {
+        Some(e) => Err(e),
+        None => std::fs::read(&file),
+      }
+      .map_err(|e| {
+        let error = Error::new(Status::GenericFailure, e.to_string());
+        Failure::file(&file.to_string_lossy(), error).into_error(env)
+      })?;
+      let matches = self.scan_bytes(&data);
+      if !matches.is_empty() {
+        results.push(KeywordFileMatches {
+          path: file.to_string_lossy().into_owned(),
+          matches,
+        });
+      }
+    }
+    Ok(results)
+  }
+}
+
+impl KeywordScanner {
+  fn scan_bytes(&self, bytes: &[u8]) -> Vec<KeywordMatch> {
+    let mut found: Vec<aho_corasick::Match> = match &self.automaton {
+      KeywordAutomaton::Built(automaton, indexes) => automaton
+        .find_overlapping_iter(bytes)
+        .map(|m| aho_corasick::Match::must(indexes[m.pattern()] as usize, m.span()))
+        .collect(),
+      KeywordAutomaton::Stored(stored) => stored.find_overlapping(bytes, &self.keywords),
+    };
+    found.retain(|m| !self.word || is_word_bounded(bytes, m.start(), m.end()));
+    found.sort_by_key(|m| (m.start(), m.pattern()));
+    let mut line_number = 1;
+    let mut line_counted = 0;
+    found
+      .into_iter()
+      .map(|m| {
+        line_number += bytes[line_counted..m.start()]
+          .iter()
+          .filter(|&&b| b == b'\n')
+          .count();
+        line_counted = m.start();
+        KeywordMatch {
+          keyword: self.keywords[m.pattern()].clone(),
+          index: m.pattern().as_u32(),
+          line_number: line_number as u32,
+          start: m.start() as i64,
+          end: m.end() as i64,
+        }
+      })
+      .collect()
+  }
+}
+
+/// Whether `bytes[start..end]` is neither preceded nor followed by a word
+/// character.
+fn is_word_bounded(bytes: &[u8], start: usize, end: usize) -> bool {
+  // A UTF-8 encoded character is at most four bytes long.
+  let before = bytes[start.saturating_sub(4)..start]
+    .utf8_chunks()
+    .last()
+    .and_then(|c| {
+      if c.invalid().is_empty() {
+        c.valid().chars().next_back()
+      } else {
+        None
+      }
+    });
+  let after = bytes[end..(end + 4).min(bytes.len())]
+    .utf8_chunks()
+    .next()
+    .and_then(|c| c.valid().chars().next());
+  let is_word = |c: Option<char>| c.is_some_and(regex_syntax::is_word_character);
+  !is_word(before) && !is_word(after)
+}
+
+// ============================================================================
+// DocumentSet
+// ============================================================================
+
+/// Named in-memory documents that can be searched repeatedly.
+///
+/// Documents are copied in once and searched with any matcher, for
+/// in-process search over notes, CMS entries and similar datasets.
+#[napi]
+pub struct DocumentSet {
+  documents: Vec<Document>,
+  prefilter: bool,
+}
+
+struct Document {
+  name: String,
+  content: Vec<u8>,
+  /// The bytes that occur in `content`, for the prefilter.
+  present: ByteSet,
+}
+
+#[napi]
+impl DocumentSet {
+  /// Create an empty document set.
+  #[napi(constructor)]
+  pub fn new(options: Option<DocumentSetOptions>) -> Self {
+    Self {
+      documents: Vec::new(),
+      prefilter: options.unwrap_or_default().prefilter.unwrap_or(true),
+    }
+  }
+
+  /// Add a document, replacing any document with the same name.
+  #[napi]
+  pub fn add(&mut self, name: String, content: Either<String, Buffer>) {
+    let content = match content {
+      Either::A(s) => s.into_bytes(),
+      Either::B(b) => b.to_vec(),
+    };
+    let mut present = ByteSet::empty();
+    if self.prefilter {
+      content.iter().for_each(|&b| present.add(b));
+    }
+    let document = Document {
+      name,
+      content,
+      present,
+    };
+    match self.documents.iter_mut().find(|d| d.name == document.name) {
+      Some(existing) => *existing = document,
+      None => self.documents.push(document),
+    }
+  }
+
+  /// Remove a document. Returns whether it was in the set.
+  #[napi]
+  pub fn remove(&mut self, name: String) -> bool {
+    let len = self.documents.len();
+    self.documents.retain(|d| d.name != name);
+    self.documents.len() < len
+  }
+
+  /// The number of documents in the set.
+  #[napi(getter)]
+  pub fn size(&self) -> u32 {
+    self.documents.len() as u32
+  }
+
+  /// Search every document, in the order they were added, with `searcher`
+  /// or a default searcher.
+  ///
+  /// Only documents with at least one match are included in the result.
+  #[napi]
+  pub fn search(
+    &self,
+    matcher: &RegexMatcher,
+    searcher: Option<&Searcher>,
+  ) -> Result<Vec<DocumentMatch>> {
+    let searcher = searcher.cloned().unwrap_or_default();
+    // An inverted search matches documents without the literals too.
+    let prefilter = self.prefilter && !searcher.inner.invert_match();
+    let mut found = Vec::new();
+    for document in &self.documents {
+      if prefilter && !matcher.inner.could_match(&document.present) {
+        continue;
+      }
+      let output = searcher.search(
+        matcher,
+        Haystack::Slice(&document.content),
+        SearchOptions::default(),
+      )?;
+      if !output.result.matches.is_empty() {
+        found.push(DocumentMatch {
+          name: document.name.clone(),
+          result: output,
+        });
+      }
+    }
+    Ok(found)
+  }
+}
+
+// ============================================================================
+// StringPool
+// ============================================================================
+
+/// Many small strings, copied in once and tested against matchers in one
+/// call each.
+///
+/// Meant for running patterns over large numbers of short strings (names,
+/// log lines, table cells), where calling `isMatch` per string costs more
+/// than the match itself.
+#[napi]
+pub struct StringPool {
+  /// The strings, back to back.
+  data: Vec<u8>,
+  /// The end offset of every string in `data`.
+  ends: Vec<usize>,
+}
+
+#[napi]
+impl StringPool {
+  /// Create a pool of `strings`, which keep their position as index.
+  #[napi(constructor)]
+  pub fn new(strings: Vec<Either<String, Buffer>>) -> Self {
+    let mut data = Vec::new();
+    let mut ends = Vec::with_capacity(strings.len());
+    for s in &strings {
+      data.extend_from_slice(match s {
+        Either::A(s) => s.as_bytes(),
+        Either::B(b) => b.as_ref(),
+      });
+      ends.push(data.len());
+    }
+    Self { data, ends }
+  }
+
+  /// The number of strings in the pool.
+  #[napi(getter)]
+  pub fn size(&self) -> u32 {
+    self.ends.len() as u32
+  }
+
+  /// Test every string, as by `matcher.isMatch`.
+  ///
+  /// Returns a bitmap with one bit per string: string `i` matches if bit
+  /// `i % 8` (the least significant first) of byte `i / 8` is set.
+  #[napi]
+  pub fn test(&self, matcher: &RegexMatcher) -> Result<Uint8Array> {
+    let mut bitmap = vec![0u8; self.ends.len().div_ceil(8)];
+    for (i, s) in self.strings().enumerate() {
+      if matcher.is_match_bytes(s)? {
+        bitmap[i / 8] |= 1 << (i % 8);
+      }
+    }
+    Ok(Uint8Array::new(bitmap))
+  }
+
+  /// The number of strings that match.
+  #[napi]
+  pub fn count(&self, matcher: &RegexMatcher) -> Result<u32> {
+    let mut count = 0;
+    for s in self.strings() {
+      count += matcher.is_match_bytes(s)? as u32;
+    }
+    Ok(count)
+  }
+
+  /// The number of non-overlapping matches in every string, as by
+  /// `matcher.findAll`, in the order of the strings.
+  #[napi]
+  pub fn match_counts(&self, matcher: &RegexMatcher) -> Result<Uint32Array> {
+    let counts = self
+      .strings()
+      .map(|s| {
+        let normalized = matcher.normalize(s);
+        match_ranges(
+          &matcher.inner,
+          normalized.as_ref().map_or(s, |n| n.text()),
+          false,
+          usize::MAX,
+        )
+        .map(|ranges| ranges.len() as u32)
+        .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+      })
+      .collect::<Result<Vec<_>>>()?;
+    Ok(Uint32Array::new(counts))
+  }
+}
+
+impl StringPool {
+  /// The strings, in order.
+  fn strings(&self) -> impl Iterator<Item = &[u8]> {
+    let starts = std::iter::once(0).chain(self.ends.iter().copied());
+    starts
+      .zip(&self.ends)
+      .map(|(start, &end)| &self.data[start..end])
+  }
+}
+
+// ============================================================================
+// StreamMatcher
+// ============================================================================
+
+/// How many bytes before the next search position are kept for
+/// look-behind assertions such as `\b`: one UTF-8 encoded character.
+const STREAM_LOOKBEHIND: usize = 4;
+
+/// Matches in a stream fed chunk by chunk, such as the chunks passing
+/// through a Node.js `Transform`.
+///
+/// Matches are found without regard to lines, as by
+/// `RegexMatcher.scanBytes`, and may span chunks. A match is reported once
+/// enough input has been fed that no longer match could start where it
+/// does, so only the last `maxMatchLength` bytes are buffered.
+#[napi]
+pub struct StreamMatcher {
+  matcher: Arc<Engine>,
+  max_match_length: usize,
+  /// The buffered tail of the stream.
+  pending: Vec<u8>,
+  /// The absolute offset of the first byte of `pending`.
+  base: usize,
+  /// Where the next search starts, in `pending`.
+  at: usize,
+  ended: bool,
+}
+
+#[napi]
+impl StreamMatcher {
+  /// Create a stream matcher. Matchers that normalize their input are not
+  /// supported.
+  #[napi(constructor)]
+  pub fn new(matcher: &RegexMatcher, options: Option<StreamMatcherOptions>) -> Result<Self> {
+    if matcher.inner.fold().is_some() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "stream matching does not support normalization",
+      ));
+    }
+    let max_match_length = options
+      .unwrap_or_default()
+      .max_match_length
+      .unwrap_or(64 * 1024)
+      .max(1) as usize;
+    Ok(Self {
+      matcher: matcher.inner.clone(),
+      max_match_length,
+      pending: Vec::new(),
+      base: 0,
+      at: 0,
+      ended: false,
+    })
+  }
+
+  /// The number of bytes fed so far.
+  #[napi(getter)]
+  pub fn position(&self) -> i64 {
+    (self.base + self.pending.len()) as i64
+  }
+
+  /// Feed the next chunk of the stream. Returns the matches that are
+  /// complete, with absolute offsets.
+  #[napi]
+  pub fn push(&mut self, chunk: Either<String, Buffer>) -> Result<Vec<BinaryMatch>> {
+    if self.ended {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "the stream has already ended",
+      ));
+    }
+    self.pending.extend_from_slice(match &chunk {
+      Either::A(s) => s.as_bytes(),
+      Either::B(b) => b.as_ref(),
+    });
+    self.drain()
+  }
+
+  /// End the stream. Returns the remaining matches.
+  #[napi]
+  pub fn end(&mut self) -> Result<Vec<BinaryMatch>> {
+    self.ended = true;
+    let matches = self.drain();
+    self.base += self.pending.len();
+    self.pending = Vec::new();
+    self.at = 0;
+    matches
+  }
+}
+
+impl StreamMatcher {
+  /// Report the matches in `pending` that more input cannot change, and
+  /// drop the bytes no later match can start in.
+  fn drain(&mut self) -> Result<Vec<BinaryMatch>> {
+    let mut matches = Vec::new();
+    let len = self.pending.len();
+    while self.at <= len {
+      let found = self
+        .matcher
+        .find_at(&self.pending, self.at)
+        .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+      match found {
+        Some(m) if self.ended || m.start() + self.max_match_length <= len => {
+          matches.push(BinaryMatch {
+            start: (self.base + m.start()) as i64,
+            end: (self.base + m.end()) as i64,
+            window: None,
+          });
+          self.at = if m.is_empty() { m.end() + 1 } else { m.end() };
+        }
+        // A match that could still grow, or be preceded by a match that
+        // ends in input yet to come, is searched for again later. No match
+        // starts where it would fit in the buffered input, though.
+        _ => {
+          self.at = self.at.max((len + 1).saturating_sub(self.max_match_length));
+          break;
+        }
+      }
+    }
+    let drop = self.at.min(len).saturating_sub(STREAM_LOOKBEHIND);
+    self.pending.drain(..drop);
+    self.base += drop;
+    self.at -= drop;
+    Ok(matches)
+  }
+}
+
+// ============================================================================
+// Gitignore
+// ============================================================================
+
+/// Gitignore rules, evaluated against paths without walking a directory.
+///
+/// Rules are interpreted exactly as ripgrep's directory walker interprets
+/// `.gitignore` files, including `!` rules, directory-only rules and
+/// anchoring to the root.
+#[napi]
+pub struct Gitignore {
+  inner: ignore::gitignore::Gitignore,
+}
+
+#[napi]
+impl Gitignore {
+  /// Build from the content of an ignore file, one rule per line.
+  #[napi(factory)]
+  pub fn from_content(content: String, options: Option<GitignoreOptions>) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(options.root.unwrap_or_default());
+    builder
+      .case_insensitive(options.case_insensitive.unwrap_or(false))
+      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+    for (i, line) in content.lines().enumerate() {
+      builder
+        .add_line(None, line)
+        .map_err(|e| Error::new(Status::InvalidArg, format!("line {}: {}", i + 1, e)))?;
+    }
+    let inner = builder
+      .build()
+      .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+    Ok(Self { inner })
+  }
+
+  /// The number of ignore rules.
+  #[napi(getter)]
+  pub fn ignore_count(&self) -> u32 {
+    self.inner.num_ignores() as u32
+  }
+
+  /// The number of `!` rules.
+  #[napi(getter)]
+  pub fn whitelist_count(&self) -> u32 {
+    self.inner.num_whitelists() as u32
+  }
+
+  /// Match a path against the rules, without considering its parent
+  /// directories.
+  #[napi]
+  pub fn matched(&self, path: String, is_dir: bool) -> IgnoreMatch {
+    ignore_match(self.inner.matched(&path, is_dir))
+  }
+
+  /// Whether a path is ignored, either by a rule matching it or by a rule
+  /// matching one of its parent directories below the root.
+  #[napi]
+  pub fn is_ignored(&self, path: String, is_dir: bool) -> bool {
+    let path = Path::new(&path);
+    let root = self.inner.path();
+    let result = if path.has_root() && !(root.has_root() && path.starts_with(root)) {
+      self.inner.matched(path, is_dir)
+    } else {
+      self.inner.matched_path_or_any_parents(path, is_dir)
+    };
+    result.is_ignore()
+  }
+}
+
+fn ignore_match<T>(result: ignore::Match<T>) -> IgnoreMatch {
+  match result {
+    ignore::Match::None => IgnoreMatch::None,
+    ignore::Match::Ignore(_) => IgnoreMatch::Ignore,
+    ignore::Match::Whitelist(_) => IgnoreMatch::Whitelist,
+  }
+}
+
+// ============================================================================
+// CancellationToken
+// ============================================================================
+
+/// A flag that stops every search watching it, such as all the searches in
+/// flight for a query that has been replaced.
+///
+/// Pass it to `SearcherBuilder.cancellationToken`. A token cannot be reset,
+/// so create a new one for the next set of searches.
+#[napi]
+#[derive(Default)]
+pub struct CancellationToken {
+  cancelled: Arc<AtomicBool>,
+}
+
+#[napi]
+impl CancellationToken {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Cancel the searches watching this token, including those running on
+  /// other threads.
+  #[napi]
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  /// Whether `cancel` has been called.
+  #[napi(getter)]
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+}
+
+// ============================================================================
+// RulePack
+// ============================================================================
+
+/// A set of named rules run together over a directory, the core of secret
+/// scanners and linters.
+///
+/// Each file is read once and searched with every rule whose globs select
+/// it, and the findings are grouped by rule.
+#[napi]
+pub struct RulePack {
+  rules: Vec<PackRule>,
+}
+
+struct PackRule {
+  name: String,
+  severity: Option<String>,
+  matcher: RegexMatcher,
+  globs: Option<ignore::overrides::Override>,
+}
+
+impl PackRule {
+  fn applies_to(&self, path: &Path) -> bool {
+    self
+      .globs
+      .as_ref()
+      .is_none_or(|globs| !globs.matched(path, false).is_ignore())
+  }
+}
+
+#[napi]
+impl RulePack {
+  /// Compile `rules`, such as the parsed content of a JSON rule file.
+  #[napi(constructor)]
+  pub fn new(rules: Vec<Rule>) -> Result<Self> {
+    let mut compiled: Vec<PackRule> = Vec::with_capacity(rules.len());
+    for rule in rules {
+      let invalid =
+        |e: String| Error::new(Status::InvalidArg, format!("rule {}: {}", rule.name, e));
+      if compiled.iter().any(|r| r.name == rule.name) {
+        return Err(invalid("duplicate name".to_string()));
+      }
+      let mut builder = RegexMatcherBuilder::new();
+      builder.case_insensitive(rule.case_insensitive.unwrap_or(false));
+      builder.word(rule.word.unwrap_or(false));
+      builder.fixed_strings(rule.fixed_string.unwrap_or(false));
+      let matcher = builder
+        .build_patterns(
+          std::slice::from_ref(&rule.pattern),
+          builder.options.fixed_strings,
+        )
+        .map_err(|e| invalid(e.reason))?;
+      let globs = match &rule.globs {
+        Some(globs) => {
+          let mut builder = ignore::overrides::OverrideBuilder::new("");
+          for glob in globs {
+            builder.add(glob).map_err(|e| invalid(e.to_string()))?;
+          }
+          Some(builder.build().map_err(|e| invalid(e.to_string()))?)
+        }
+        None => None,
+      };
+      compiled.push(PackRule {
+        name: rule.name,
+        severity: rule.severity,
+        matcher,
+        globs,
+      });
+    }
+    Ok(Self { rules: compiled })
+  }
+
+  /// The names of the rules, in order.
+  #[napi(getter)]
+  pub fn names(&self) -> Vec<String> {
+    self.rules.iter().map(|r| r.name.clone()).collect()
+  }
+
+  /// Run every rule over the files below a directory, or over a single
+  /// file, with `searcher` or a default one.
+  ///
+  /// Files that no rule applies to are not read. Files that cannot be read
+  /// fail the call or are listed in `errors`, according to the read error
+  /// policy.
+  #[napi]
+  pub fn run(&self, env: &Env, dir: String, searcher: Option<&Searcher>) -> Result<RuleReport> {
+    let searcher = searcher.cloned().unwrap_or_default();
+    let files = dir::files(&dir).map_err(|e| Failure::classify(e, Some(&dir)).into_error(env))?;
+    let mut found: Vec<Vec<FileSearchResult>> = self.rules.iter().map(|_| Vec::new()).collect();
+    let mut errors = Vec::new();
+    for (file, error) in files {
+      let path = file.to_string_lossy().into_owned();
+      let relative = match file.strip_prefix(&dir) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => Path::new(file.file_name().unwrap_or_default()),
+      };
+      let rules: Vec<usize> = (0..self.rules.len())
+        .filter(|&i| self.rules[i].applies_to(relative))
+        .collect();
+      if rules.is_empty() {
+        continue;
+      }
+      let read = match error {
+        Some(e) => Err(e),
+        None => searcher.config.read_file(&path),
+      };
+      let data = match read {
+        Ok(data) => data,
+        Err(e) => match searcher.config.read_error_policy.action.unwrap_or_default() {
+          ReadErrorAction::Abort => {
+            let error = Error::new(Status::GenericFailure, e.to_string());
+            return Err(Failure::file(&path, error).into_error(env));
+          }
+          ReadErrorAction::Skip => {
+            errors.push(FileError {
+              path,
+              message: e.to_string(),
+            });
+            continue;
+          }
+        },
+      };
+      for i in rules {
+        let output = searcher
+          .search(
+            &self.rules[i].matcher,
+            Haystack::Slice(&data),
+            SearchOptions::default(),
+          )
+          .map_err(|e| Failure::file(&path, e).into_error(env))?;
+        if !output.result.matches.is_empty() {
+          found[i].push(FileSearchResult::new(path.clone(), output, None));
+        }
+      }
+    }
+    let rules = self
+      .rules
+      .iter()
+      .zip(found)
+      .map(|(rule, files)| RuleFindings {
+        name: rule.name.clone(),
+        severity: rule.severity.clone(),
+        files,
+      })
+      .collect();
+    Ok(RuleReport { rules, errors })
+  }
+
+  /// Run the rules like `run` and render the findings as a SARIF 2.1.0
+  /// log, for CI systems and code-scanning services.
+  ///
+  /// Each match becomes a result with the rule name as its `ruleId` and a
+  /// region giving its line, UTF-16 columns and byte range. Severities
+  /// `"error"`, `"note"` and `"none"` are used as SARIF levels; any other
+  /// severity becomes `"warning"`. Locations are relative to `dir`.
+  #[napi]
+  pub fn run_sarif(&self, env: &Env, dir: String, searcher: Option<&Searcher>) -> Result<String> {
+    let report = self.run(env, dir.clone(), searcher)?;
+    Ok(sarif::render(&dir, &report))
+  }
+}
+
+// ============================================================================
+// SearchService
+// ============================================================================
+
+/// A pool of search threads of its own, with a queue of search jobs.
+///
+/// Jobs are queued with a priority and run as threads become free, so a
+/// server can bound how many searches run at once and let urgent ones jump
+/// the queue, while aging keeps the others from waiting forever. Each job's
+/// promise has the `handle` of the job, which `cancel` and `cancelSearch`
+/// take.
+#[napi]
+pub struct SearchService {
+  pool: service::Pool,
+}
+
+#[napi]
+impl SearchService {
+  /// Start the service's threads.
+  #[napi(constructor)]
+  pub fn new(options: Option<SearchServiceOptions>) -> Self {
+    let options = options.unwrap_or_default();
+    let threads = options.threads.map_or_else(
+      || std::thread::available_parallelism().map_or(1, |n| n.get()),
+      |n| (n as usize).max(1),
+    );
+    let aging = options
+      .aging_ms
+      .map(|ms| Duration::from_millis(ms.max(1) as u64));
+    Self {
+      pool: service::Pool::new(threads, aging),
+    }
+  }
+
+  /// Queue a search of a file with `searcher`.
+  ///
+  /// Resolves with the same result as `searcher.searchPath`.
+  #[napi(ts_return_type = "Promise<SearchResult> & { handle: number }")]
+  pub fn search_path<'env>(
+    &self,
+    env: &'env Env,
+    searcher: &Searcher,
+    matcher: &RegexMatcher,
+    path: String,
+    options: Option<SearchOptions>,
+    job: Option<SearchJobOptions>,
+  ) -> Result<Object<'env>> {
+    let task = searcher.search_task(matcher, TaskInput::Path(path), options);
+    self.submit(env, task, job)
+  }
+
+  /// Queue a search of a byte slice with `searcher`.
+  ///
+  /// The search reads the Buffer in place, so it must not be modified until
+  /// the promise settles. Resolves with the same result as
+  /// `searcher.searchSlice`.
+  #[napi(ts_return_type = "Promise<SearchResult> & { handle: number }")]
+  pub fn search_slice<'env>(
+    &self,
+    env: &'env Env,
+    searcher: &Searcher,
+    matcher: &RegexMatcher,
+    slice: Either<String, Buffer>,
+    options: Option<SearchOptions>,
+    job: Option<SearchJobOptions>,
+  ) -> Result<Object<'env>> {
+    let task = searcher.search_task(matcher, TaskInput::Slice(slice), options);
+    self.submit(env, task, job)
+  }
+
+  /// Cancel a job by the `handle` of its promise. A queued job is taken off
+  /// the queue, and a running one stops as with `cancelSearch`; either way
+  /// its promise rejects with a "search cancelled" error. Returns whether
+  /// the job was still queued or running.
+  #[napi]
+  pub fn cancel(&self, handle: u32) -> bool {
+    self.pool.cancel(handle) || registry::cancel(handle)
+  }
+
+  /// The number of jobs waiting for a thread.
+  #[napi(getter)]
+  pub fn queued(&self) -> u32 {
+    self.pool.queued() as u32
+  }
+
+  /// The number of jobs being searched.
+  #[napi(getter)]
+  pub fn running(&self) -> u32 {
+    self.pool.running() as u32
+  }
+
+  /// How long the jobs started so far waited for a thread, for each
+  /// priority they were queued with, from highest to lowest. Jobs cancelled
+  /// while queued are left out.
+  #[napi]
+  pub fn wait_stats(&self) -> Vec<QueueWaits> {
+    let waits = self.pool.waits().into_iter().rev();
+    waits
+      .map(|(priority, waits)| QueueWaits {
+        priority,
+        started: waits.started,
+        mean_wait_ms: millis(waits.total) / waits.started.max(1) as f64,
+        max_wait_ms: millis(waits.max),
+      })
+      .collect()
+  }
+
+  /// Stop the service. Queued jobs are rejected as cancelled, running jobs
+  /// finish, and queueing more jobs throws.
+  #[napi]
+  pub fn close(&self) {
+    self.pool.close();
+  }
+
+  fn submit<'env>(
+    &self,
+    env: &'env Env,
+    task: SearchTask,
+    job: Option<SearchJobOptions>,
+  ) -> Result<Object<'env>> {
+    if self.pool.is_shut_down() {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "the search service is closed",
+      ));
+    }
+    let handle = task.handle;
+    let (deferred, mut promise) = env.create_deferred()?;
+    let priority = job.unwrap_or_default().priority.unwrap_or(0);
+    self.pool.submit(task, deferred, priority);
+    promise.set_named_property("handle", handle)?;
+    Ok(promise)
+  }
+}
+
+impl Drop for SearchService {
+  fn drop(&mut self) {
+    self.pool.drain();
+  }
+}
+
+// ============================================================================
+// Internal Sink implementation
+// ============================================================================
+
+/// Options implemented by this crate on top of the grep searcher configuration.
+#[derive(Clone, Default)]
+struct SearchConfig {
+  before_context: usize,
+  line_endings: bool,
+  content_hash: Option<HashAlgorithm>,
+  bom: BomHandling,
+  /// Set when `bomSniffing(false)` turns off transcoding.
+  bom_sniffing_disabled: bool,
+  encoding_error: Option<EncodingErrorAction>,
+  /// Flags that stop the searches once any of them is set: the
+  /// cancellation token, and whether the consumer of a background search
+  /// closed it.
+  cancel: Vec<Arc<AtomicBool>>,
+  timeout: Option<Duration>,
+  /// The number of threads of parallel searches, if limited.
+  threads: Option<usize>,
+  /// Set by `afterContextUntil`, with the delimiter if there is one.
+  after_context_until: Option<Option<Arc<Engine>>>,
+  before_context_bytes: usize,
+  after_context_bytes: usize,
+  read_error_policy: ReadErrorPolicy,
+  /// Set on copies of the searcher that search off the JavaScript thread,
+  /// where failed reads may be retried.
+  background: bool,
+  overlapping: bool,
+  sort_by: Option<SortBy>,
+  profile: bool,
+  plain: bool,
+  memory_map: bool,
+  max_matches: Option<u32>,
+  max_match_count: Option<u32>,
+  max_matches_per_line: Option<u32>,
+  merge_ranges: bool,
+  max_result_bytes: Option<(usize, ResultLimitAction)>,
+}
+
+impl SearchConfig {
+  /// Whether a search can be stopped while it runs, so that it has to read
+  /// its input in blocks.
+  fn interruptible(&self) -> bool {
+    !self.cancel.is_empty() || self.timeout.is_some()
+  }
+
+  /// The number of threads parallel searches use.
+  fn threads(&self) -> usize {
+    self
+      .threads
+      .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+  }
+
+  /// Whether a cancellation flag is set, so that searches fail.
+  fn cancelled(&self) -> bool {
+    self
+      .cancel
+      .iter()
+      .any(|cancel| cancel.load(Ordering::Relaxed))
+  }
+
+  fn byte_context(&self) -> bool {
+    self.before_context_bytes > 0 || self.after_context_bytes > 0
+  }
+
+  /// Read a whole file, retrying failed reads according to the read error
+  /// policy.
+  fn read_file(&self, path: &str) -> std::io::Result<Vec<u8>> {
+    self.retry(|| std::fs::read(path))
+  }
+
+  /// Run the file operation `op`, retrying it according to the read error
+  /// policy while it fails with an error that may pass. Searches on the
+  /// JavaScript thread never retry, as waiting between attempts would block
+  /// it.
+  fn retry<T>(&self, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let retries = match self.background {
+      true => self.read_error_policy.retries.unwrap_or(0),
+      false => 0,
+    };
+    let mut delay = self.read_error_policy.backoff_ms.unwrap_or(0) as u64;
+    let mut attempt = 0;
+    loop {
+      match op() {
+        Ok(value) => return Ok(value),
+        Err(e) if attempt >= retries || !is_transient(&e) => return Err(e),
+        Err(_) => {
+          std::thread::sleep(std::time::Duration::from_millis(delay));
+          delay = delay.saturating_mul(2);
+          attempt += 1;
+        }
+      }
+    }
+  }
+}
+
+/// Whether a failed file operation may succeed if tried again: one that was
+/// interrupted, would have blocked, or found the file busy.
+fn is_transient(e: &std::io::Error) -> bool {
+  use std::io::ErrorKind;
+  matches!(
+    e.kind(),
+    ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::ResourceBusy
+  )
+}
 
 struct CollectSink {
-  matcher: Arc<GrepRegexMatcher>,
+  matcher: Arc<Engine>,
+  config: SearchConfig,
   matches: Vec<SearchMatch>,
   context: Vec<SearchContext>,
   finish: Option<SearchFinish>,
+  /// Time spent building results, when profiling.
+  collect_time: Duration,
+  /// The largest searcher buffer seen by the sink.
+  peak_buffer: usize,
+  /// Approximate size of the collected results.
+  result_bytes: usize,
+  /// Number of matches collected, for `maxMatchCount`.
+  match_count: usize,
+  /// Whether a match limit was reached.
+  truncated: bool,
+  /// When `timeoutMs` stops the search.
+  deadline: Option<Instant>,
+  /// Where the line at which the deadline passed starts, if it did. The
+  /// search ends before that line.
+  timed_out_at: Option<u64>,
+  /// Whether after context is still being collected, with
+  /// `afterContextUntil`.
+  after_open: bool,
+  /// The latest lines left out by `afterContextUntil`, which become before
+  /// context if a match follows.
+  held: VecDeque<SearchContext>,
 }
 
 impl CollectSink {
-  fn new(matcher: Arc<GrepRegexMatcher>) -> Self {
+  fn new(matcher: Arc<Engine>, config: &SearchConfig) -> Self {
     Self {
       matcher,
+      config: config.clone(),
       matches: Vec::new(),
       context: Vec::new(),
       finish: None,
+      collect_time: Duration::ZERO,
+      peak_buffer: 0,
+      result_bytes: 0,
+      match_count: 0,
+      truncated: false,
+      deadline: config.timeout.map(|timeout| Instant::now() + timeout),
+      timed_out_at: None,
+      after_open: false,
+      held: VecDeque::new(),
     }
   }
 
-  fn into_result(self) -> SearchResult {
+  /// Whether the deadline has passed, remembering the line starting at
+  /// `offset` as the one it stopped at if so.
+  fn past_deadline(&mut self, offset: u64) -> bool {
+    if self.timed_out_at.is_none() && self.deadline.is_some_and(|d| Instant::now() >= d) {
+      self.timed_out_at = Some(offset);
+    }
+    self.timed_out_at.is_some()
+  }
+
+  fn into_result(mut self) -> SearchResult {
+    if let Some(order) = self.config.sort_by {
+      sort_matches(&mut self.matches, order);
+    }
     SearchResult {
       matches: self.matches,
       context: self.context,
       finish: self.finish.unwrap_or(SearchFinish {
         byte_count: 0,
         binary_byte_offset: None,
+        peak_buffer_bytes: 0,
+        result_bytes: 0,
+        peak_memory_bytes: 0,
+        truncated: self.truncated,
+        resume_offset: None,
+        stopped_at: None,
+        encoding_errors: None,
+        undecodable_lines: None,
+        line_endings: None,
+        content_hash: None,
+        timed_out: None,
       }),
+      profile: None,
+    }
+  }
+
+  /// Account for `size` more bytes of results. Returns false, or fails,
+  /// when they would exceed `maxResultBytes`.
+  fn reserve(&mut self, size: usize) -> std::io::Result<bool> {
+    if let Some((limit, action)) = self.config.max_result_bytes {
+      if self.result_bytes + size > limit {
+        return match action {
+          ResultLimitAction::Truncate => {
+            self.truncated = true;
+            Ok(false)
+          }
+          ResultLimitAction::Error => Err(std::io::Error::other(format!(
+            "{} ({} bytes)",
+            error::RESULT_LIMIT,
+            limit
+          ))),
+        };
+      }
+    }
+    self.result_bytes += size;
+    Ok(true)
+  }
+
+  /// Keep a line left out by `afterContextUntil`, in case it is before
+  /// context of the next match.
+  fn hold(&mut self, mut line: SearchContext) {
+    if self.config.before_context == 0 {
+      return;
+    }
+    line.kind = ContextKind::Before;
+    self.held.push_back(line);
+    if self.held.len() > self.config.before_context {
+      self.held.pop_front();
+    }
+  }
+
+  /// Report the held lines as before context of a match.
+  fn release_held(&mut self) -> std::io::Result<bool> {
+    while let Some(line) = self.held.pop_front() {
+      let size = std::mem::size_of::<SearchContext>() + line.line.len() + line.bytes.len();
+      if !self.reserve(size)? {
+        return Ok(false);
+      }
+      self.context.push(line);
     }
+    Ok(true)
+  }
+
+  /// Cut a window of bytes around each match out of the searcher's buffer.
+  fn byte_windows(&self, mat: &SinkMatch<'_>, ranges: &[MatchRange]) -> Vec<ByteWindow> {
+    let buffer = mat.buffer();
+    let line_start = mat.bytes_range_in_buffer().start;
+    let buffer_offset = mat.absolute_byte_offset() as usize - line_start;
+    ranges
+      .iter()
+      .map(|r| {
+        byte_window(
+          buffer,
+          buffer_offset,
+          line_start + r.start as usize,
+          line_start + r.end as usize,
+          self.config.before_context_bytes,
+          self.config.after_context_bytes,
+        )
+      })
+      .collect()
   }
 }
 
@@ -677,32 +5323,81 @@ impl Sink for CollectSink {
     _searcher: &GrepSearcher,
     mat: &SinkMatch<'_>,
   ) -> std::result::Result<bool, Self::Error> {
+    check_cancelled(&self.config.cancel)?;
+    if self.past_deadline(mat.absolute_byte_offset()) {
+      return Ok(false);
+    }
     let line_bytes = mat.bytes();
-    let line_str = String::from_utf8_lossy(line_bytes).to_string();
 
     // Find all matches within this line
-    let mut match_ranges = Vec::new();
-    let mut start = 0;
-    while start < line_bytes.len() {
-      match self.matcher.find(&line_bytes[start..]) {
-        Ok(Some(m)) => {
-          match_ranges.push(MatchRange {
-            start: (start + m.start()) as u32,
-            end: (start + m.end()) as u32,
-          });
-          start += m.end().max(1);
-        }
-        _ => break,
+    let per_line = self.config.max_matches_per_line.map(|n| n as usize);
+    // One more than the limit tells whether any were left out.
+    let mut match_ranges = match_ranges(
+      &self.matcher,
+      line_bytes,
+      self.config.overlapping,
+      per_line.map_or(usize::MAX, |n| n + 1),
+    )?;
+    let more_matches = per_line.is_some_and(|n| match_ranges.len() > n);
+    if let Some(n) = per_line {
+      match_ranges.truncate(n);
+    }
+    if let Some(limit) = self.config.max_match_count {
+      let left = (limit as usize).saturating_sub(self.match_count);
+      if left == 0 {
+        self.truncated = true;
+        return Ok(false);
       }
+      match_ranges.truncate(left);
+      self.match_count += match_ranges.len().max(1);
+    }
+    if self.config.merge_ranges {
+      merge_ranges(&mut match_ranges);
+    }
+
+    if !self.release_held()? {
+      return Ok(false);
     }
+    self.after_open = true;
 
+    let started = self.config.profile.then(Instant::now);
+    self.peak_buffer = self.peak_buffer.max(mat.buffer().len());
+    let line_str = String::from_utf8_lossy(line_bytes).to_string();
+    let windows = if self.config.byte_context() {
+      Some(self.byte_windows(mat, &match_ranges))
+    } else {
+      None
+    };
+
+    let size = std::mem::size_of::<SearchMatch>()
+      + line_str.len()
+      + line_bytes.len()
+      + match_ranges.len() * std::mem::size_of::<MatchRange>()
+      + windows
+        .iter()
+        .flatten()
+        .map(|w| w.bytes.len())
+        .sum::<usize>();
+    if !self.reserve(size)? {
+      return Ok(false);
+    }
     self.matches.push(SearchMatch {
       line_number: mat.line_number().map(|n| n as u32),
       absolute_byte_offset: mat.absolute_byte_offset() as i64,
       line: line_str,
       bytes: Buffer::from(line_bytes.to_vec()),
       matches: match_ranges,
+      windows,
+      more_matches: more_matches.then_some(true),
     });
+    if let Some(started) = started {
+      self.collect_time += started.elapsed();
+    }
+    let limit = self.config.max_match_count;
+    if limit.is_some_and(|n| self.match_count >= n as usize) {
+      self.truncated = true;
+      return Ok(false);
+    }
     Ok(true)
   }
 
@@ -711,16 +5406,54 @@ impl Sink for CollectSink {
     _searcher: &GrepSearcher,
     ctx: &SinkContext<'_>,
   ) -> std::result::Result<bool, Self::Error> {
+    check_cancelled(&self.config.cancel)?;
+    if self.past_deadline(ctx.absolute_byte_offset()) {
+      return Ok(false);
+    }
+    let started = self.config.profile.then(Instant::now);
     let line_bytes = ctx.bytes();
-    let line_str = String::from_utf8_lossy(line_bytes).to_string();
-
-    self.context.push(SearchContext {
+    let line = || SearchContext {
       line_number: ctx.line_number().map(|n| n as u32),
       absolute_byte_offset: ctx.absolute_byte_offset() as i64,
-      line: line_str,
+      line: String::from_utf8_lossy(line_bytes).to_string(),
       bytes: Buffer::from(line_bytes.to_vec()),
       kind: ctx.kind().clone().into(),
-    });
+    };
+    if let Some(delimiter) = &self.config.after_context_until {
+      match ctx.kind() {
+        SinkContextKind::After => {
+          let content = line_bytes.strip_suffix(b"\n").unwrap_or(line_bytes);
+          let content = content.strip_suffix(b"\r").unwrap_or(content);
+          let ends = match delimiter {
+            Some(delimiter) => delimiter.is_match(content)?,
+            None => content.trim_ascii().is_empty(),
+          };
+          if ends || !self.after_open {
+            self.after_open = false;
+            self.hold(line());
+            return Ok(true);
+          }
+        }
+        // Held lines are not adjacent to the searcher's own before context.
+        SinkContextKind::Before => self.held.clear(),
+        SinkContextKind::Other => {}
+      }
+    }
+
+    let line = line();
+    let size = std::mem::size_of::<SearchContext>() + line.line.len() + line_bytes.len();
+    if !self.reserve(size)? {
+      return Ok(false);
+    }
+    self.context.push(line);
+    if let Some(started) = started {
+      self.collect_time += started.elapsed();
+    }
+    Ok(true)
+  }
+
+  fn context_break(&mut self, _searcher: &GrepSearcher) -> std::result::Result<bool, Self::Error> {
+    self.held.clear();
     Ok(true)
   }
 
@@ -729,14 +5462,642 @@ impl Sink for CollectSink {
     _searcher: &GrepSearcher,
     finish: &SinkFinish,
   ) -> std::result::Result<(), Self::Error> {
+    // A line the deadline stopped at is left for a resumed search.
+    let byte_count = self
+      .timed_out_at
+      .unwrap_or(u64::MAX)
+      .min(finish.byte_count());
     self.finish = Some(SearchFinish {
-      byte_count: finish.byte_count() as i64,
+      byte_count: byte_count as i64,
       binary_byte_offset: finish.binary_byte_offset().map(|o| o as i64),
+      peak_buffer_bytes: 0,
+      result_bytes: 0,
+      peak_memory_bytes: 0,
+      truncated: self.truncated
+        || self
+          .config
+          .max_matches
+          .is_some_and(|n| self.matches.len() >= n as usize),
+      resume_offset: None,
+      stopped_at: None,
+      encoding_errors: None,
+      undecodable_lines: None,
+      line_endings: None,
+      content_hash: None,
+      timed_out: None,
     });
     Ok(())
   }
 }
 
+/// Find the ranges of all matches in `bytes`.
+///
+/// Normally the search resumes at the end of each match. When `overlapping`
+/// is set it resumes one byte after the start of each match instead, so
+/// overlapping occurrences are reported too. At most `limit` ranges are
+/// returned.
+fn match_ranges(
+  matcher: &Engine,
+  bytes: &[u8],
+  overlapping: bool,
+  limit: usize,
+) -> std::result::Result<Vec<MatchRange>, NoError> {
+  let mut ranges = Vec::new();
+  let mut start = 0;
+  while start < bytes.len() && ranges.len() < limit {
+    match matcher.find(&bytes[start..])? {
+      Some(m) => {
+        ranges.push(MatchRange {
+          start: (start + m.start()) as u32,
+          end: (start + m.end()) as u32,
+        });
+        start += if overlapping {
+          m.start() + 1
+        } else {
+          m.end().max(1)
+        };
+      }
+      None => break,
+    }
+  }
+  Ok(ranges)
+}
+
+/// Merge overlapping and adjacent ranges, ordered by start, into maximal
+/// spans.
+fn merge_ranges(ranges: &mut Vec<MatchRange>) {
+  ranges.dedup_by(|next, span| {
+    if next.start > span.end {
+      return false;
+    }
+    span.end = span.end.max(next.end);
+    true
+  });
+}
+
+/// Cut a window of bytes around `buffer[start..end]`, clamped to the buffer.
+///
+/// `buffer_offset` is the absolute offset of the start of `buffer`.
+fn byte_window(
+  buffer: &[u8],
+  buffer_offset: usize,
+  start: usize,
+  end: usize,
+  before: usize,
+  after: usize,
+) -> ByteWindow {
+  let window_start = start.saturating_sub(before);
+  let window_end = end.saturating_add(after).min(buffer.len());
+  ByteWindow {
+    absolute_byte_offset: (buffer_offset + window_start) as i64,
+    bytes: Buffer::from(buffer[window_start..window_end].to_vec()),
+    length: (window_end - window_start) as u32,
+    match_start: (start - window_start) as u32,
+    match_end: (end - window_start) as u32,
+  }
+}
+
+/// Split a newline-separated pattern list into patterns.
+///
+/// Errors are prefixed with the offending line number.
+fn pattern_lines(
+  data: &[u8],
+  options: &PatternFileOptions,
+) -> std::result::Result<Vec<String>, String> {
+  let skip_blank = options.skip_blank_lines.unwrap_or(false);
+  let skip_comments = options.skip_comments.unwrap_or(false);
+  if data.is_empty() {
+    return Ok(Vec::new());
+  }
+  let data = data.strip_suffix(b"\n").unwrap_or(data);
+  let mut patterns = Vec::new();
+  for (i, line) in data.split(|&b| b == b'\n').enumerate() {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = std::str::from_utf8(line)
+      .map_err(|e| format!("{}: pattern is not valid UTF-8: {}", i + 1, e))?;
+    let trimmed = line.trim_start();
+    if (skip_blank && trimmed.is_empty()) || (skip_comments && trimmed.starts_with('#')) {
+      continue;
+    }
+    patterns.push(line.to_string());
+  }
+  Ok(patterns)
+}
+
+/// Map a result found in a normalized copy of `original` back onto
+/// `original`, replacing lines and windows with the original bytes.
+fn denormalize_result(result: &mut SearchResult, normalized: &Normalized, original: &[u8]) {
+  let line = |offset: i64, len: usize| {
+    let start = normalized.start(offset as usize);
+    let end = normalized.end(offset as usize + len);
+    (start, &original[start..end])
+  };
+  for m in &mut result.matches {
+    let offset = m.absolute_byte_offset as usize;
+    let (start, bytes) = line(m.absolute_byte_offset, m.bytes.len());
+    for r in &mut m.matches {
+      r.start = (normalized.start(offset + r.start as usize) - start) as u32;
+      r.end = (normalized.end(offset + r.end as usize) - start) as u32;
+    }
+    for w in m.windows.iter_mut().flatten() {
+      let window_offset = w.absolute_byte_offset as usize;
+      let (window_start, window_bytes) = line(w.absolute_byte_offset, w.bytes.len());
+      w.match_start =
+        (normalized.start(window_offset + w.match_start as usize) - window_start) as u32;
+      w.match_end = (normalized.end(window_offset + w.match_end as usize) - window_start) as u32;
+      w.absolute_byte_offset = window_start as i64;
+      w.bytes = Buffer::from(window_bytes.to_vec());
+      w.length = window_bytes.len() as u32;
+    }
+    m.absolute_byte_offset = start as i64;
+    m.line = String::from_utf8_lossy(bytes).to_string();
+    m.bytes = Buffer::from(bytes.to_vec());
+  }
+  for c in &mut result.context {
+    let (start, bytes) = line(c.absolute_byte_offset, c.bytes.len());
+    c.absolute_byte_offset = start as i64;
+    c.line = String::from_utf8_lossy(bytes).to_string();
+    c.bytes = Buffer::from(bytes.to_vec());
+  }
+  result.finish.byte_count = normalized.end(result.finish.byte_count as usize) as i64;
+  result.finish.binary_byte_offset = result
+    .finish
+    .binary_byte_offset
+    .map(|o| normalized.start(o as usize) as i64);
+}
+
+/// Sort matching lines of a single source. Every sort is stable, so ties
+/// stay in file order.
+fn sort_matches(matches: &mut [SearchMatch], order: SortBy) {
+  match order {
+    SortBy::Path => matches.sort_by_key(|m| m.absolute_byte_offset),
+    SortBy::MatchCount => matches.sort_by_key(|m| std::cmp::Reverse(m.matches.len())),
+    SortBy::FirstMatchOffset => matches.sort_by_key(|m| m.matches.first().map(|r| r.start)),
+  }
+}
+
+/// Sort the files of a search of many files: by path, by total number of
+/// matches, or by the offset of their first match, with files without
+/// matches last. Every sort is stable, so ties stay in search order.
+fn sort_files(files: &mut [FileSearchResult], order: SortBy) {
+  match order {
+    SortBy::Path => files.sort_by(|a, b| a.path.cmp(&b.path)),
+    SortBy::MatchCount => files.sort_by_key(|file| {
+      std::cmp::Reverse(file.matches.iter().map(|m| m.matches.len()).sum::<usize>())
+    }),
+    SortBy::FirstMatchOffset => files.sort_by_key(|file| {
+      let first = file
+        .matches
+        .iter()
+        .filter_map(|m| Some(m.absolute_byte_offset + m.matches.first()?.start as i64))
+        .min();
+      (first.is_none(), first)
+    }),
+  }
+}
+
+/// Reorder the files of a match matrix, keeping every column of `counts` in
+/// step with `paths`.
+fn sort_matrix(matrix: &mut MatchMatrix, order: SortBy) {
+  let mut columns: Vec<usize> = (0..matrix.paths.len()).collect();
+  match order {
+    SortBy::Path => columns.sort_by(|&a, &b| matrix.paths[a].cmp(&matrix.paths[b])),
+    SortBy::MatchCount => columns.sort_by_key(|&f| {
+      std::cmp::Reverse(matrix.counts.iter().map(|row| row[f] as u64).sum::<u64>())
+    }),
+    SortBy::FirstMatchOffset => return,
+  }
+  matrix.paths = columns.iter().map(|&f| matrix.paths[f].clone()).collect();
+  for row in &mut matrix.counts {
+    *row = columns.iter().map(|&f| row[f]).collect();
+  }
+}
+
+/// The input of a search.
+enum Haystack<'a> {
+  Path(&'a str),
+  Slice(&'a [u8]),
+  Reader(&'a [u8]),
+}
+
+/// A search result on its way to JavaScript.
+///
+/// The `line` and `bytes` of matches and context lines are converted on
+/// first access (see `lazy`), unless plain results were requested. When the
+/// result carries a profile, the conversion to JavaScript values is timed
+/// and added to it.
+pub struct SearchOutput {
+  result: SearchResult,
+  plain: bool,
+}
+
+impl TypeName for SearchOutput {
+  fn type_name() -> &'static str {
+    "SearchResult"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl FileSearchResult {
+  fn new(path: String, output: SearchOutput, error: Option<Error>) -> Self {
+    let SearchResult {
+      matches,
+      context,
+      finish,
+      profile,
+    } = output.result;
+    FileSearchResult {
+      path,
+      matches,
+      context,
+      finish,
+      profile,
+      error_code: error.as_ref().map(error::code),
+      error: error.map(|e| e.reason),
+      plain: output.plain,
+    }
+  }
+}
+
+impl ToNapiValue for FileSearchResult {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+    let output = SearchOutput {
+      result: SearchResult {
+        matches: val.matches,
+        context: val.context,
+        finish: val.finish,
+        profile: val.profile,
+      },
+      plain: val.plain,
+    };
+    let value = SearchOutput::to_napi_value(env, output)?;
+    let mut object = Object::from_raw(env, value);
+    object.set_named_property("path", val.path)?;
+    if let Some(error) = val.error {
+      object.set_named_property("error", error)?;
+    }
+    if let Some(code) = val.error_code {
+      object.set_named_property("errorCode", code)?;
+    }
+    Ok(value)
+  }
+}
+
+impl ToNapiValue for SearchOutput {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+    let SearchOutput { mut result, plain } = val;
+    let Some(mut profile) = result.profile.take() else {
+      return lazy::search_result(env, result, plain);
+    };
+    let started = Instant::now();
+    let value = lazy::search_result(env, result, plain)?;
+    let marshal = millis(started.elapsed());
+    profile.marshal_ms = marshal;
+    profile.total_ms += marshal;
+    Object::from_raw(env, value).set_named_property("profile", profile)?;
+    Ok(value)
+  }
+}
+
+/// A callback into JavaScript every so many bytes read by a synchronous
+/// search, which stops the search by returning false.
+struct Tick<'a> {
+  every: u64,
+  call: &'a dyn Fn(u64) -> bool,
+}
+
+/// What a `MeteredReader` observed.
+#[derive(Default)]
+struct ReadStats<'a> {
+  /// Time spent opening and reading.
+  elapsed: Cell<Duration>,
+  /// The largest read requested, which approximates the size of the
+  /// searcher's buffer.
+  max_read: Cell<usize>,
+  /// The line endings read, when they are counted.
+  line_endings: Cell<Option<LineEndingCount>>,
+  /// The hash of the input read, when it is hashed.
+  hasher: RefCell<Option<hash::Hasher>>,
+  /// Stops reading once cancelled.
+  cancel: Vec<Arc<AtomicBool>>,
+  /// Ends the input early once passed, at the end of a line.
+  deadline: Option<Instant>,
+  /// The line terminator, for ending the input at the end of a line.
+  terminator: u8,
+  /// Whether the deadline ended the input.
+  timed_out: Cell<bool>,
+  /// Called as the input is read, for synchronous searches.
+  tick: Option<&'a Tick<'a>>,
+  /// The bytes read so far, when ticking.
+  read_bytes: Cell<u64>,
+}
+
+impl ReadStats<'_> {
+  /// Hash the part of `input` that has not been hashed yet.
+  fn hash_input(&self, input: &[u8]) {
+    if let Some(hasher) = self.hasher.borrow_mut().as_mut() {
+      let hashed = (hasher.len() as usize).min(input.len());
+      hasher.update(&input[hashed..]);
+    }
+  }
+}
+
+/// Counts line endings in input read in chunks.
+#[derive(Clone, Copy, Default)]
+struct LineEndingCount {
+  lf: u64,
+  crlf: u64,
+  /// Whether the last chunk ended with `\r`.
+  after_cr: bool,
+}
+
+impl LineEndingCount {
+  fn add(&mut self, bytes: &[u8]) {
+    for (i, _) in bytes.iter().enumerate().filter(|&(_, &b)| b == b'\n') {
+      let cr = if i == 0 {
+        self.after_cr
+      } else {
+        bytes[i - 1] == b'\r'
+      };
+      if cr {
+        self.crlf += 1;
+      } else {
+        self.lf += 1;
+      }
+    }
+    if let Some(&last) = bytes.last() {
+      self.after_cr = last == b'\r';
+    }
+  }
+
+  fn report(&self) -> LineEndings {
+    LineEndings {
+      lf: self.lf as i64,
+      crlf: self.crlf as i64,
+      mixed: self.lf > 0 && self.crlf > 0,
+    }
+  }
+}
+
+/// A reader that records how it is used in a `ReadStats`.
+struct MeteredReader<'a, R> {
+  inner: R,
+  stats: &'a ReadStats<'a>,
+  /// Whether the bytes read so far end with a line terminator.
+  at_line_start: bool,
+}
+
+impl<'a, R> MeteredReader<'a, R> {
+  fn new(
+    stats: &'a ReadStats<'a>,
+    open: impl FnOnce() -> std::io::Result<R>,
+  ) -> std::io::Result<Self> {
+    let started = Instant::now();
+    let inner = open()?;
+    stats.elapsed.set(stats.elapsed.get() + started.elapsed());
+    Ok(Self {
+      inner,
+      stats,
+      at_line_start: true,
+    })
+  }
+}
+
+impl<R: std::io::Read> std::io::Read for MeteredReader<'_, R> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    check_cancelled(&self.stats.cancel)?;
+    let stats = self.stats;
+    if stats.deadline.is_some_and(|d| Instant::now() >= d) {
+      // Ending the input keeps what was found so far. A line read in part
+      // is read to its end first, so the search ends between lines.
+      stats.timed_out.set(true);
+      if self.at_line_start {
+        return Ok(0);
+      }
+    }
+    let started = Instant::now();
+    let mut n = self.inner.read(buf)?;
+    if stats.timed_out.get() {
+      if let Some(end) = buf[..n].iter().position(|&b| b == stats.terminator) {
+        n = end + 1;
+      }
+    }
+    if n > 0 {
+      self.at_line_start = buf[n - 1] == stats.terminator;
+    }
+    stats.elapsed.set(stats.elapsed.get() + started.elapsed());
+    stats.max_read.set(stats.max_read.get().max(buf.len()));
+    if let Some(mut count) = stats.line_endings.get() {
+      count.add(&buf[..n]);
+      stats.line_endings.set(Some(count));
+    }
+    if let Some(hasher) = stats.hasher.borrow_mut().as_mut() {
+      hasher.update(&buf[..n]);
+    }
+    if let Some(tick) = stats.tick {
+      let before = stats.read_bytes.get();
+      let after = before + n as u64;
+      stats.read_bytes.set(after);
+      if after / tick.every > before / tick.every && !(tick.call)(after) {
+        return Err(std::io::Error::other(error::CANCELLED));
+      }
+    }
+    Ok(n)
+  }
+}
+
+/// Fail with "search cancelled" once any of `cancel` is set.
+fn check_cancelled(cancel: &[Arc<AtomicBool>]) -> std::io::Result<()> {
+  if cancel.iter().any(|cancel| cancel.load(Ordering::Relaxed)) {
+    return Err(std::io::Error::other(error::CANCELLED));
+  }
+  Ok(())
+}
+
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+/// A sink that only counts matching lines.
+#[derive(Default)]
+struct CountSink {
+  count: u64,
+}
+
+impl Sink for CountSink {
+  type Error = std::io::Error;
+
+  fn matched(
+    &mut self,
+    _searcher: &GrepSearcher,
+    _mat: &SinkMatch<'_>,
+  ) -> std::result::Result<bool, Self::Error> {
+    self.count += 1;
+    Ok(true)
+  }
+}
+
+/// A sink that counts every match on the matching lines, or the lines
+/// themselves for an inverted search, whose lines have no matches.
+struct MatchCountSink<'a> {
+  matcher: &'a Engine,
+  overlapping: bool,
+  count: u64,
+}
+
+impl Sink for MatchCountSink<'_> {
+  type Error = std::io::Error;
+
+  fn matched(
+    &mut self,
+    _searcher: &GrepSearcher,
+    mat: &SinkMatch<'_>,
+  ) -> std::result::Result<bool, Self::Error> {
+    let ranges = match_ranges(self.matcher, mat.bytes(), self.overlapping, usize::MAX)?;
+    self.count += ranges.len().max(1) as u64;
+    Ok(true)
+  }
+}
+
+/// A sink that collects the lines from `first` to `last` other than
+/// `target`, for a searcher whose matcher matches every line.
+struct LineSink {
+  first: u64,
+  target: u64,
+  last: u64,
+  lines: Vec<SearchContext>,
+}
+
+impl Sink for LineSink {
+  type Error = std::io::Error;
+
+  fn matched(
+    &mut self,
+    _searcher: &GrepSearcher,
+    mat: &SinkMatch<'_>,
+  ) -> std::result::Result<bool, Self::Error> {
+    let mut number = mat.line_number().unwrap_or(0);
+    let mut offset = mat.absolute_byte_offset();
+    for line in mat.lines() {
+      if number > self.last {
+        return Ok(false);
+      }
+      if number >= self.first && number != self.target {
+        self.lines.push(SearchContext {
+          line_number: Some(number as u32),
+          absolute_byte_offset: offset as i64,
+          line: String::from_utf8_lossy(line).to_string(),
+          bytes: Buffer::from(line.to_vec()),
+          kind: if number < self.target {
+            ContextKind::Before
+          } else {
+            ContextKind::After
+          },
+        });
+      }
+      number += 1;
+      offset += line.len() as u64;
+    }
+    Ok(number <= self.last)
+  }
+}
+
+/// Put the byte order mark that grep stripped back into `result`: at the
+/// start of the line at `first_line` and into every offset.
+fn keep_bom(result: &mut SearchResult, first_line: i64) {
+  const BOM: &str = "\u{FEFF}";
+  offset_result(result, BOM.len() as i64, 0);
+  let first_line = first_line + BOM.len() as i64;
+  for m in &mut result.matches {
+    if m.absolute_byte_offset == first_line {
+      m.absolute_byte_offset -= BOM.len() as i64;
+      m.line.insert_str(0, BOM);
+      m.bytes = Buffer::from([BOM.as_bytes(), &m.bytes].concat());
+      for r in &mut m.matches {
+        r.start += BOM.len() as u32;
+        r.end += BOM.len() as u32;
+      }
+    }
+  }
+  for c in &mut result.context {
+    if c.absolute_byte_offset == first_line {
+      c.absolute_byte_offset -= BOM.len() as i64;
+      c.line.insert_str(0, BOM);
+      c.bytes = Buffer::from([BOM.as_bytes(), &c.bytes].concat());
+    }
+  }
+}
+
+/// Count the lines ended by `terminator` in the first `end` bytes of
+/// `haystack`, and find where the line after them starts.
+fn line_position(haystack: &Haystack<'_>, end: u64, terminator: u8) -> std::io::Result<(u64, u64)> {
+  let mut reader: Box<dyn Read> = match *haystack {
+    Haystack::Path(path) => Box::new(std::fs::File::open(path)?.take(end)),
+    Haystack::Slice(bytes) | Haystack::Reader(bytes) => Box::new(&bytes[..end as usize]),
+  };
+  let mut buf = [0; 64 * 1024];
+  let (mut lines, mut line_start, mut offset) = (0, 0, 0);
+  loop {
+    let n = reader.read(&mut buf)?;
+    if n == 0 {
+      return Ok((lines, line_start));
+    }
+    for (i, _) in buf[..n]
+      .iter()
+      .enumerate()
+      .filter(|&(_, &b)| b == terminator)
+    {
+      lines += 1;
+      line_start = offset + i as u64 + 1;
+    }
+    offset += n as u64;
+  }
+}
+
+/// Move the positions in `result` forward by `bytes` and its line numbers
+/// by `lines`, for a search that started that far into its input.
+fn offset_result(result: &mut SearchResult, bytes: i64, lines: u32) {
+  if bytes == 0 && lines == 0 {
+    return;
+  }
+  for m in &mut result.matches {
+    m.absolute_byte_offset += bytes;
+    m.line_number = m.line_number.map(|n| n + lines);
+    for w in m.windows.iter_mut().flatten() {
+      w.absolute_byte_offset += bytes;
+    }
+  }
+  for c in &mut result.context {
+    c.absolute_byte_offset += bytes;
+    c.line_number = c.line_number.map(|n| n + lines);
+  }
+  let finish = &mut result.finish;
+  finish.byte_count += bytes;
+  finish.binary_byte_offset = finish.binary_byte_offset.map(|o| o + bytes);
+  finish.resume_offset = finish.resume_offset.map(|o| o + bytes);
+}
+
+/// Search `file` in place, as grep would, unless the matcher folds its
+/// input, which has to be read whole to be normalized.
+fn search_opened<S: Sink<Error = std::io::Error>>(
+  searcher: &mut GrepSearcher,
+  matcher: &RegexMatcher,
+  mut file: std::fs::File,
+  sink: S,
+) -> std::io::Result<()> {
+  match matcher.inner.fold() {
+    Some(fold) => {
+      let mut data = Vec::new();
+      file.read_to_end(&mut data)?;
+      searcher.search_slice(&*matcher.inner, Normalized::new(&data, fold).text(), sink)
+    }
+    None => searcher.search_file(&*matcher.inner, &file, sink),
+  }
+}
+
 // ============================================================================
 // Convenience functions
 // ============================================================================
@@ -745,37 +6106,315 @@ impl Sink for CollectSink {
 ///
 /// This is a convenience function for simple searches.
 #[napi]
-pub fn search(pattern: String, haystack: Either<String, Buffer>) -> Result<SearchResult> {
-  let matcher = RegexMatcher::from_pattern(pattern)?;
-  let mut searcher = Searcher::new();
-  searcher.search_slice(&matcher, haystack)
+pub fn search(
+  env: &Env,
+  pattern: String,
+  haystack: Either<String, Buffer>,
+) -> Result<SearchResult> {
+  let matcher = RegexMatcher::from_pattern(env, pattern)?;
+  let searcher = Searcher::new();
+  searcher
+    .search_slice(env, &matcher, haystack, None, None)
+    .map(|output| output.result)
 }
 
 /// Search a file for a pattern with default options.
 #[napi]
-pub fn search_file(pattern: String, path: String) -> Result<SearchResult> {
-  let matcher = RegexMatcher::from_pattern(pattern)?;
-  let mut searcher = Searcher::new();
-  searcher.search_path(&matcher, path)
+pub fn search_file(env: &Env, pattern: String, path: String) -> Result<SearchResult> {
+  let matcher = RegexMatcher::from_pattern(env, pattern)?;
+  let searcher = Searcher::new();
+  searcher
+    .search_path(env, &matcher, path, None, None)
+    .map(|output| output.result)
+}
+
+/// Search the files below a directory with default options, grouping the
+/// results by file.
+///
+/// See `Searcher.searchDir`.
+#[napi]
+pub fn search_dir(
+  env: &Env,
+  matcher: &RegexMatcher,
+  dir: String,
+  options: Option<DirSearchOptions>,
+) -> Result<Vec<FileSearchResult>> {
+  Searcher::new().search_dir(env, matcher, dir, options)
+}
+
+/// Search the files below a directory with default options.
+///
+/// See `Searcher.searchDirIter`.
+#[napi(ts_return_type = "AsyncIterableIterator<DirSearchEntry>")]
+pub fn search_dir_iter<'env>(
+  env: &'env Env,
+  matcher: &RegexMatcher,
+  dir: String,
+  options: Option<DirSearchOptions>,
+) -> Result<ClassInstance<'env, DirSearch>> {
+  DirSearch::start(env, Searcher::new(), matcher, dir, options)
+}
+
+/// Search the files below a directory in parallel with default options.
+///
+/// See `Searcher.searchDirParallel`.
+#[napi(
+  ts_args_type = "matcher: RegexMatcher, dir: string, onFile: (entry: DirSearchEntry) => void, onFinish?: ((error: Error | null, finish?: DirSearchFinish) => void) | null, options?: DirSearchOptions | null"
+)]
+pub fn search_dir_parallel(
+  matcher: &RegexMatcher,
+  dir: String,
+  on_file: dir::FileCallback,
+  on_finish: Option<dir::FinishCallback>,
+  options: Option<DirSearchOptions>,
+) -> Result<()> {
+  Searcher::new().search_dir_parallel(matcher, dir, on_file, on_finish, options)
+}
+
+/// Cancel an async search by the `handle` of its promise, from
+/// `searchPathAsync`, `searchSliceAsync` or a `SearchService`.
+///
+/// The search stops at its next read and its promise rejects with a
+/// "search cancelled" error. A slice is searched in place, and so can only
+/// be cancelled before it starts, unless the searcher has a cancellation
+/// token or a timeout. Returns whether the search was still running.
+#[napi]
+pub fn cancel_search(handle: u32) -> bool {
+  registry::cancel(handle)
+}
+
+/// Counters of the searches run by the process so far, to export to a
+/// metrics system.
+///
+/// The counters only grow, apart from `searchesRunning`, so take the
+/// difference between snapshots for rates. They count the searches of every
+/// thread of the process, worker threads included.
+#[napi]
+pub fn search_metrics() -> SearchMetrics {
+  metrics::snapshot()
 }
 
 /// Check if a pattern matches anywhere in the given text.
 #[napi]
-pub fn is_match(pattern: String, text: Either<String, Buffer>) -> Result<bool> {
-  let matcher = RegexMatcher::from_pattern(pattern)?;
+pub fn is_match(env: &Env, pattern: String, text: Either<String, Buffer>) -> Result<bool> {
+  let matcher = RegexMatcher::from_pattern(env, pattern)?;
   matcher.is_match(text)
 }
 
 /// Find the first match of a pattern in the given text.
 #[napi]
-pub fn find(pattern: String, text: Either<String, Buffer>) -> Result<Option<MatchRange>> {
-  let matcher = RegexMatcher::from_pattern(pattern)?;
+pub fn find(
+  env: &Env,
+  pattern: String,
+  text: Either<String, Buffer>,
+) -> Result<Option<MatchRange>> {
+  let matcher = RegexMatcher::from_pattern(env, pattern)?;
   matcher.find(text)
 }
 
 /// Find all matches of a pattern in the given text.
 #[napi]
-pub fn find_all(pattern: String, text: Either<String, Buffer>) -> Result<Vec<MatchRange>> {
-  let matcher = RegexMatcher::from_pattern(pattern)?;
-  matcher.find_all(text)
+pub fn find_all(
+  env: &Env,
+  pattern: String,
+  text: Either<String, Buffer>,
+  options: Option<FindOptions>,
+) -> Result<Vec<MatchRange>> {
+  let matcher = RegexMatcher::from_pattern(env, pattern)?;
+  matcher.find_all(text, options)
+}
+
+/// Highlight the matches of several matchers in a text at once, as ranges
+/// that do not overlap, ordered by start.
+///
+/// Where matches overlap, the one that takes precedence keeps the
+/// overlapping bytes and the others keep only the bytes no stronger match
+/// covers, so a match may be split into several ranges. Empty matches are
+/// left out. Offsets are in bytes, as with `RegexMatcher.findAll`.
+#[napi]
+pub fn highlight(
+  matchers: Vec<ClassInstance<RegexMatcher>>,
+  text: Either<String, Buffer>,
+  options: Option<HighlightOptions>,
+) -> Result<Vec<HighlightRange>> {
+  let options = options.unwrap_or_default();
+  let bytes = match &text {
+    Either::A(s) => s.as_bytes(),
+    Either::B(b) => b.as_ref(),
+  };
+  let mut matches = Vec::new();
+  for (layer, matcher) in matchers.iter().enumerate() {
+    for range in matcher.find_all_bytes(bytes, false)? {
+      if range.start < range.end {
+        matches.push(highlight::Layered {
+          layer: layer as u32,
+          start: range.start,
+          end: range.end,
+        });
+      }
+    }
+  }
+  match options.precedence.unwrap_or_default() {
+    HighlightPrecedence::Layer => matches.sort_by_key(|m| (m.layer, m.start)),
+    HighlightPrecedence::Longest => {
+      matches.sort_by_key(|m| (std::cmp::Reverse(m.end - m.start), m.layer, m.start))
+    }
+  }
+  let label = |layer: u32| options.labels.as_ref()?.get(layer as usize).cloned();
+  Ok(
+    highlight::paint(&matches)
+      .into_iter()
+      .map(|m| HighlightRange {
+        start: m.start,
+        end: m.end,
+        layer: m.layer,
+        label: label(m.layer),
+      })
+      .collect(),
+  )
+}
+
+/// Merge results of searches over chunks or ranges of the same haystack.
+///
+/// Matches and context are ordered by absolute byte offset. Lines reported
+/// by more than one chunk (where chunks overlap), with the same offset and
+/// line number, are kept once, preferring the longest copy, and context
+/// lines that are matches elsewhere are dropped. Line numbers are taken
+/// as-is from the inputs. Plain results, such as those cloned from a
+/// worker, merge into a plain result.
+#[napi(ts_return_type = "SearchResult")]
+pub fn merge_results(results: Vec<MergeInput>) -> SearchOutput {
+  let mut plain = false;
+  let results = results
+    .into_iter()
+    .map(|result| SearchResult {
+      matches: result
+        .matches
+        .into_iter()
+        .map(|m| SearchMatch {
+          line_number: m.line_number,
+          absolute_byte_offset: m.absolute_byte_offset,
+          bytes: merged_bytes(m.bytes, m.line.as_bytes(), &mut plain),
+          line: m.line,
+          matches: m.matches,
+          windows: m.windows.map(|windows| {
+            windows
+              .into_iter()
+              .map(|w| ByteWindow {
+                absolute_byte_offset: w.absolute_byte_offset,
+                bytes: merged_bytes(w.bytes, b"", &mut plain),
+                length: w.length,
+                match_start: w.match_start,
+                match_end: w.match_end,
+              })
+              .collect()
+          }),
+          more_matches: m.more_matches,
+        })
+        .collect(),
+      context: result
+        .context
+        .into_iter()
+        .map(|c| SearchContext {
+          line_number: c.line_number,
+          absolute_byte_offset: c.absolute_byte_offset,
+          bytes: merged_bytes(c.bytes, c.line.as_bytes(), &mut plain),
+          line: c.line,
+          kind: c.kind,
+        })
+        .collect(),
+      finish: result.finish,
+      profile: None,
+    })
+    .collect();
+  SearchOutput {
+    result: merge(results),
+    plain,
+  }
+}
+
+/// Merge results as `mergeResults` does.
+fn merge(results: Vec<SearchResult>) -> SearchResult {
+  let mut matches = Vec::new();
+  let mut context = Vec::new();
+  let mut byte_count = 0;
+  let mut binary_byte_offset: Option<i64> = None;
+  let mut peak_buffer_bytes = 0;
+  let mut result_bytes = 0;
+  let mut peak_memory_bytes = 0;
+  let mut truncated = false;
+  for result in results {
+    matches.extend(result.matches);
+    context.extend(result.context);
+    byte_count = byte_count.max(result.finish.byte_count);
+    peak_buffer_bytes = peak_buffer_bytes.max(result.finish.peak_buffer_bytes);
+    result_bytes += result.finish.result_bytes;
+    peak_memory_bytes = peak_memory_bytes.max(result.finish.peak_memory_bytes);
+    truncated |= result.finish.truncated;
+    if let Some(offset) = result.finish.binary_byte_offset {
+      binary_byte_offset = Some(binary_byte_offset.map_or(offset, |o| o.min(offset)));
+    }
+  }
+  // Lines are the same line where both their offset and number are.
+  let key = |offset: i64, line_number: Option<u32>| (offset, line_number);
+  matches.sort_by_key(|m| key(m.absolute_byte_offset, m.line_number));
+  matches.dedup_by(|later, kept| {
+    if key(later.absolute_byte_offset, later.line_number)
+      != key(kept.absolute_byte_offset, kept.line_number)
+    {
+      return false;
+    }
+    if later.bytes.len() > kept.bytes.len() {
+      std::mem::swap(later, kept);
+    }
+    true
+  });
+  context.retain(|c| {
+    matches
+      .binary_search_by_key(&key(c.absolute_byte_offset, c.line_number), |m| {
+        key(m.absolute_byte_offset, m.line_number)
+      })
+      .is_err()
+  });
+  context.sort_by_key(|c| key(c.absolute_byte_offset, c.line_number));
+  context.dedup_by(|later, kept| {
+    if key(later.absolute_byte_offset, later.line_number)
+      != key(kept.absolute_byte_offset, kept.line_number)
+    {
+      return false;
+    }
+    if later.bytes.len() > kept.bytes.len() {
+      std::mem::swap(later, kept);
+    }
+    true
+  });
+  SearchResult {
+    matches,
+    context,
+    finish: SearchFinish {
+      byte_count,
+      binary_byte_offset,
+      peak_buffer_bytes,
+      result_bytes,
+      peak_memory_bytes,
+      truncated,
+      resume_offset: None,
+      stopped_at: None,
+      encoding_errors: None,
+      undecodable_lines: None,
+      line_endings: None,
+      content_hash: None,
+      timed_out: None,
+    },
+    profile: None,
+  }
+}
+
+/// The bytes of a line or window passed to `mergeResults`, or `fallback`
+/// for one from a plain result, which makes the merged result plain.
+fn merged_bytes(bytes: Option<Buffer>, fallback: &[u8], plain: &mut bool) -> Buffer {
+  bytes.unwrap_or_else(|| {
+    *plain = true;
+    Buffer::from(fallback)
+  })
 }
@@ -0,0 +1,73 @@
+//! Process-wide counters of searches, for services to export.
+//!
+//! Every search that collects results or streams them records here as it
+//! starts and ends. The counters are relaxed atomics, so a snapshot taken
+//! while searches run may be a few increments apart across counters.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use napi::Error;
+
+use crate::error::CANCELLED;
+use crate::SearchMetrics;
+
+static STARTED: AtomicU64 = AtomicU64::new(0);
+static COMPLETED: AtomicU64 = AtomicU64::new(0);
+static CANCELLED_SEARCHES: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+static RUNNING: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static MATCHES: AtomicU64 = AtomicU64::new(0);
+
+/// A search in progress, counted as running until it is dropped.
+pub(crate) struct Running;
+
+impl Drop for Running {
+  fn drop(&mut self) {
+    RUNNING.fetch_sub(1, Ordering::Relaxed);
+  }
+}
+
+/// Count a search as started and running.
+pub(crate) fn start() -> Running {
+  STARTED.fetch_add(1, Ordering::Relaxed);
+  RUNNING.fetch_add(1, Ordering::Relaxed);
+  Running
+}
+
+/// Count a search that read `bytes` bytes and produced `matches` matches.
+pub(crate) fn completed(bytes: i64, matches: usize) {
+  COMPLETED.fetch_add(1, Ordering::Relaxed);
+  BYTES.fetch_add(bytes.max(0) as u64, Ordering::Relaxed);
+  MATCHES.fetch_add(matches as u64, Ordering::Relaxed);
+}
+
+/// Count a search that was cancelled before it started, as started and
+/// cancelled.
+pub(crate) fn cancelled_early() {
+  STARTED.fetch_add(1, Ordering::Relaxed);
+  CANCELLED_SEARCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a search that failed with `error`, or was cancelled.
+pub(crate) fn failed(error: &Error) {
+  if error.reason.ends_with(CANCELLED) {
+    CANCELLED_SEARCHES.fetch_add(1, Ordering::Relaxed);
+  } else {
+    FAILED.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+/// The counters as they are now.
+pub(crate) fn snapshot() -> SearchMetrics {
+  let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as i64;
+  SearchMetrics {
+    searches_started: load(&STARTED),
+    searches_completed: load(&COMPLETED),
+    searches_cancelled: load(&CANCELLED_SEARCHES),
+    searches_failed: load(&FAILED),
+    searches_running: load(&RUNNING),
+    bytes_searched: load(&BYTES),
+    matches: load(&MATCHES),
+  }
+}
@@ -0,0 +1,211 @@
+//! Unicode normalization and diacritic folding of haystacks with offset
+//! mapping.
+//!
+//! Text is split into segments that normalize independently (each starts at
+//! a character that cannot combine with anything before it), and every
+//! segment is folded on its own. Offsets in the normalized text are
+//! mapped back to the original bytes segment by segment, so a match inside
+//! a rewritten segment widens to cover the whole original segment.
+
+use unicode_normalization::char::{
+  canonical_combining_class, decompose_canonical, decompose_compatible,
+};
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::NormalizationForm;
+
+/// How text is rewritten before matching.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Fold {
+  /// Normalize to this form.
+  pub form: Option<NormalizationForm>,
+  /// Remove combining marks and transliterate letters that carry a
+  /// diacritic without decomposing (e.g. `ø`, `ł`).
+  pub diacritics: bool,
+}
+
+impl Fold {
+  /// Apply the fold to a string.
+  pub(crate) fn apply(&self, s: &str) -> String {
+    if !self.diacritics {
+      return match self.form {
+        Some(NormalizationForm::Nfkc) => s.nfkc().collect(),
+        _ => s.nfc().collect(),
+      };
+    }
+    let mut stripped = String::with_capacity(s.len());
+    let decomposed: Box<dyn Iterator<Item = char>> = match self.form {
+      Some(NormalizationForm::Nfkc) => Box::new(s.nfkd()),
+      _ => Box::new(s.nfd()),
+    };
+    for c in decomposed.filter(|&c| canonical_combining_class(c) == 0) {
+      match transliterate(c) {
+        Some(t) => stripped.push_str(t),
+        None => stripped.push(c),
+      }
+    }
+    // Recompose what is left, e.g. Hangul jamo.
+    stripped.nfc().collect()
+  }
+
+  fn form(&self) -> NormalizationForm {
+    self.form.unwrap_or(NormalizationForm::Nfc)
+  }
+}
+
+/// A run of the normalized text and where it came from.
+struct Span {
+  /// Offset of the span in the normalized text.
+  normalized: usize,
+  /// Offset of the span in the original bytes.
+  original: usize,
+  /// Whether the span was copied unchanged, in which case offsets inside it
+  /// map one to one.
+  identity: bool,
+}
+
+/// A folded copy of a haystack.
+pub(crate) struct Normalized {
+  text: Vec<u8>,
+  /// Spans ordered by offset, ending with an empty sentinel span at the end
+  /// of both texts.
+  spans: Vec<Span>,
+}
+
+impl Normalized {
+  /// Fold `bytes`. Invalid UTF-8 is copied unchanged.
+  pub(crate) fn new(bytes: &[u8], fold: Fold) -> Self {
+    let mut normalized = Normalized {
+      text: Vec::with_capacity(bytes.len()),
+      spans: Vec::new(),
+    };
+    let mut offset = 0;
+    for chunk in bytes.utf8_chunks() {
+      let valid = chunk.valid();
+      let mut start = 0;
+      for (i, c) in valid.char_indices().skip(1) {
+        if is_boundary(c, fold.form()) {
+          normalized.push_segment(&valid[start..i], offset + start, fold);
+          start = i;
+        }
+      }
+      if start < valid.len() {
+        normalized.push_segment(&valid[start..], offset + start, fold);
+      }
+      offset += valid.len();
+      let invalid = chunk.invalid();
+      if !invalid.is_empty() {
+        normalized.push(invalid, offset, true);
+        offset += invalid.len();
+      }
+    }
+    normalized.spans.push(Span {
+      normalized: normalized.text.len(),
+      original: offset,
+      identity: true,
+    });
+    normalized
+  }
+
+  /// The folded text.
+  pub(crate) fn text(&self) -> &[u8] {
+    &self.text
+  }
+
+  /// Map the start of a range in the folded text to the original bytes.
+  pub(crate) fn start(&self, pos: usize) -> usize {
+    let i = self.spans.partition_point(|s| s.normalized <= pos) - 1;
+    let span = &self.spans[i];
+    if span.identity {
+      span.original + (pos - span.normalized)
+    } else {
+      span.original
+    }
+  }
+
+  /// Map the end of a range in the folded text to the original bytes.
+  pub(crate) fn end(&self, pos: usize) -> usize {
+    if pos == 0 {
+      return 0;
+    }
+    let i = self.spans.partition_point(|s| s.normalized < pos) - 1;
+    let span = &self.spans[i];
+    if span.identity {
+      span.original + (pos - span.normalized)
+    } else {
+      self.spans[i + 1].original
+    }
+  }
+
+  fn push_segment(&mut self, segment: &str, original: usize, fold: Fold) {
+    // ASCII is left alone by every fold.
+    if segment.is_ascii() {
+      self.push(segment.as_bytes(), original, true);
+      return;
+    }
+    let folded = fold.apply(segment);
+    if folded == segment {
+      self.push(segment.as_bytes(), original, true);
+    } else {
+      self.push(folded.as_bytes(), original, false);
+    }
+  }
+
+  fn push(&mut self, bytes: &[u8], original: usize, identity: bool) {
+    // Consecutive unchanged segments share one span.
+    if !(identity && self.spans.last().is_some_and(|s| s.identity)) {
+      self.spans.push(Span {
+        normalized: self.text.len(),
+        original,
+        identity,
+      });
+    }
+    self.text.extend_from_slice(bytes);
+  }
+}
+
+/// Whether normalization never combines `c` with the text before it, so the
+/// text can be split before `c`.
+///
+/// That is the case when the first character of `c`'s decomposition is a
+/// starter that never composes with a preceding character.
+fn is_boundary(c: char, form: NormalizationForm) -> bool {
+  let mut first = None;
+  match form {
+    NormalizationForm::Nfc => decompose_canonical(c, |d| {
+      first.get_or_insert(d);
+    }),
+    NormalizationForm::Nfkc => decompose_compatible(c, |d| {
+      first.get_or_insert(d);
+    }),
+  }
+  let first = first.unwrap_or(c);
+  let quick = match form {
+    NormalizationForm::Nfc => is_nfc_quick(std::iter::once(first)),
+    NormalizationForm::Nfkc => is_nfkc_quick(std::iter::once(first)),
+  };
+  canonical_combining_class(first) == 0 && quick == IsNormalized::Yes
+}
+
+/// Letters that carry a diacritic but have no canonical decomposition.
+fn transliterate(c: char) -> Option<&'static str> {
+  Some(match c {
+    'ø' => "o",
+    'Ø' => "O",
+    'ł' => "l",
+    'Ł' => "L",
+    'đ' => "d",
+    'Đ' => "D",
+    'ħ' => "h",
+    'Ħ' => "H",
+    'ŧ' => "t",
+    'Ŧ' => "T",
+    'ı' => "i",
+    'ß' => "ss",
+    'æ' => "ae",
+    'Æ' => "AE",
+    'œ' => "oe",
+    'Œ' => "OE",
+    _ => return None,
+  })
+}
@@ -0,0 +1,38 @@
+//! Async searches that can be cancelled by handle.
+//!
+//! Every async search is registered under a numeric handle, so it can be
+//! cancelled from anywhere with `cancelSearch`, without passing a
+//! cancellation token to the code that started it. Only weak references to
+//! the searches' cancellation flags are kept, so a search that has settled
+//! drops out of the registry on its own.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+fn table() -> &'static Mutex<HashMap<u32, Weak<AtomicBool>>> {
+  static TABLE: OnceLock<Mutex<HashMap<u32, Weak<AtomicBool>>>> = OnceLock::new();
+  TABLE.get_or_init(Default::default)
+}
+
+/// Register a search that stops once `cancel` is set, returning its handle.
+pub(crate) fn register(cancel: &Arc<AtomicBool>) -> u32 {
+  static NEXT: AtomicU32 = AtomicU32::new(1);
+  let handle = NEXT.fetch_add(1, Ordering::Relaxed);
+  let mut table = table().lock().unwrap();
+  table.retain(|_, search| search.strong_count() > 0);
+  table.insert(handle, Arc::downgrade(cancel));
+  handle
+}
+
+/// Cancel the search under `handle`. Returns whether it was still running.
+pub(crate) fn cancel(handle: u32) -> bool {
+  let search = table().lock().unwrap().remove(&handle);
+  match search.and_then(|search| search.upgrade()) {
+    Some(cancel) => {
+      cancel.store(true, Ordering::Relaxed);
+      true
+    }
+    None => false,
+  }
+}
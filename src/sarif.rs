@@ -0,0 +1,136 @@
+//! SARIF output for rule packs.
+//!
+//! SARIF 2.1.0 is the format code-scanning services and CI systems ingest.
+//! A report becomes one run whose driver lists the rules of the pack and
+//! whose results carry one region per match. Files skipped because of read
+//! errors are reported as tool execution notifications. The matched text is
+//! left out, since a finding may well be a secret.
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::RuleReport;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Render `report`, produced by running a pack over `dir`, as SARIF.
+pub(crate) fn render(dir: &str, report: &RuleReport) -> String {
+  let rules: Vec<Value> = report
+    .rules
+    .iter()
+    .map(|rule| {
+      let mut descriptor = json!({
+        "id": rule.name,
+        "defaultConfiguration": { "level": level(rule.severity.as_deref()) },
+      });
+      if let Some(severity) = &rule.severity {
+        descriptor["properties"] = json!({ "severity": severity });
+      }
+      descriptor
+    })
+    .collect();
+  let mut results = Vec::new();
+  for (index, rule) in report.rules.iter().enumerate() {
+    for file in &rule.files {
+      let uri = uri(dir, &file.path);
+      for m in &file.matches {
+        for range in &m.matches {
+          let (start, end) = (range.start as usize, range.end as usize);
+          let mut region = json!({
+            "byteOffset": m.absolute_byte_offset + start as i64,
+            "byteLength": end - start,
+          });
+          if let Some(line) = m.line_number {
+            region["startLine"] = json!(line);
+            region["startColumn"] = json!(column(&m.bytes, start));
+            region["endColumn"] = json!(column(&m.bytes, end));
+          }
+          results.push(json!({
+            "ruleId": rule.name,
+            "ruleIndex": index,
+            "level": level(rule.severity.as_deref()),
+            "message": { "text": format!("Matched rule {}.", rule.name) },
+            "locations": [{
+              "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": region,
+              },
+            }],
+          }));
+        }
+      }
+    }
+  }
+  let notifications: Vec<Value> = report
+    .errors
+    .iter()
+    .map(|error| {
+      json!({
+        "level": "error",
+        "message": { "text": error.message },
+        "locations": [{
+          "physicalLocation": { "artifactLocation": { "uri": uri(dir, &error.path) } },
+        }],
+      })
+    })
+    .collect();
+  json!({
+    "$schema": SCHEMA,
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "grep-js", "rules": rules } },
+      "columnKind": "utf16CodeUnits",
+      "invocations": [{
+        "executionSuccessful": true,
+        "toolExecutionNotifications": notifications,
+      }],
+      "results": results,
+    }],
+  })
+  .to_string()
+}
+
+/// The SARIF level for a rule severity. Severities that are not SARIF levels
+/// map to `warning`, the SARIF default.
+fn level(severity: Option<&str>) -> &'static str {
+  match severity {
+    Some("error") => "error",
+    Some("note") => "note",
+    Some("none") => "none",
+    _ => "warning",
+  }
+}
+
+/// The 1-based column of byte `offset` of a line, in UTF-16 code units.
+fn column(line: &[u8], offset: usize) -> usize {
+  let prefix = &line[..offset.min(line.len())];
+  String::from_utf8_lossy(prefix).encode_utf16().count() + 1
+}
+
+/// A relative URI reference for `path`, relative to the searched `dir`.
+fn uri(dir: &str, path: &str) -> String {
+  let path = Path::new(path);
+  let relative = match path.strip_prefix(dir) {
+    Ok(relative) if !relative.as_os_str().is_empty() => relative,
+    _ => Path::new(path.file_name().unwrap_or_default()),
+  };
+  let segments: Vec<String> = relative
+    .components()
+    .map(|c| escape(&c.as_os_str().to_string_lossy()))
+    .collect();
+  segments.join("/")
+}
+
+/// Percent-encode everything but unreserved characters.
+fn escape(segment: &str) -> String {
+  let mut escaped = String::with_capacity(segment.len());
+  for b in segment.bytes() {
+    if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+      escaped.push(b as char);
+    } else {
+      escaped.push_str(&format!("%{:02X}", b));
+    }
+  }
+  escaped
+}
@@ -0,0 +1,216 @@
+//! A pool of search threads with a priority queue of jobs.
+//!
+//! Jobs wait in a queue ordered by priority, then by submission, and each
+//! worker takes the first one when it finishes the previous one. With aging,
+//! a job's priority rises the longer it waits, so a steady stream of urgent
+//! jobs cannot hold back the others forever. The queue is scanned for the
+//! first job, as aging reorders it over time.
+//!
+//! A job's promise is settled through a deferred, whose resolver runs on the
+//! JavaScript thread and owns the task from then on, so the task's input is
+//! also released there.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi::JsDeferred;
+
+use crate::error::CANCELLED;
+use crate::metrics;
+use crate::{SearchOutput, SearchTask};
+
+type Resolver = Box<dyn FnOnce(Env) -> Result<SearchOutput>>;
+
+/// A queued search.
+pub(crate) struct Job {
+  priority: i32,
+  /// The order of submission, to run jobs of the same priority in turn.
+  sequence: u64,
+  queued_at: Instant,
+  task: SearchTask,
+  deferred: JsDeferred<SearchOutput, Resolver>,
+}
+
+impl Job {
+  /// Settle the job's promise with the outcome of its task.
+  fn settle(self, outcome: Result<SearchOutput>) {
+    let mut task = self.task;
+    self.deferred.resolve(Box::new(move |env| match outcome {
+      Ok(output) => task.resolve(env, output),
+      Err(e) => task.reject(env, e),
+    }));
+  }
+
+  /// Reject the job's promise as cancelled.
+  fn cancel(self) {
+    metrics::cancelled_early();
+    self.settle(Err(Error::new(Status::GenericFailure, CANCELLED)));
+  }
+}
+
+/// How long the jobs of one priority waited for a thread.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Waits {
+  pub started: u32,
+  pub total: Duration,
+  pub max: Duration,
+}
+
+#[derive(Default)]
+struct State {
+  jobs: Vec<Job>,
+  /// The waits of the jobs started so far, by priority.
+  waits: BTreeMap<i32, Waits>,
+  submitted: u64,
+  running: usize,
+  /// Set once no more jobs are taken, so workers exit when the queue drains.
+  shut_down: bool,
+}
+
+impl State {
+  /// Take the job to run next: the one with the highest priority, raised by
+  /// one for every `aging` it has waited, and the earliest of those.
+  fn take(&mut self, aging: Option<Duration>) -> Option<Job> {
+    let now = Instant::now();
+    let priority = |job: &Job| {
+      let raise = aging.map_or(0, |aging| {
+        (now.duration_since(job.queued_at).as_nanos() / aging.as_nanos().max(1)) as i64
+      });
+      job.priority as i64 + raise
+    };
+    let (index, _) = self
+      .jobs
+      .iter()
+      .enumerate()
+      .max_by_key(|(_, job)| (priority(job), Reverse(job.sequence)))?;
+    let job = self.jobs.remove(index);
+    let waited = now.duration_since(job.queued_at);
+    let waits = self.waits.entry(job.priority).or_default();
+    waits.started += 1;
+    waits.total += waited;
+    waits.max = waits.max.max(waited);
+    Some(job)
+  }
+}
+
+#[derive(Default)]
+struct Shared {
+  state: Mutex<State>,
+  changed: Condvar,
+  aging: Option<Duration>,
+}
+
+/// Search threads sharing a queue of jobs.
+pub(crate) struct Pool {
+  shared: Arc<Shared>,
+}
+
+impl Pool {
+  /// Start `threads` search threads, aging queued jobs by `aging` if set.
+  pub(crate) fn new(threads: usize, aging: Option<Duration>) -> Self {
+    let shared = Arc::new(Shared {
+      aging,
+      ..Default::default()
+    });
+    for _ in 0..threads {
+      let shared = shared.clone();
+      std::thread::spawn(move || work(&shared));
+    }
+    Pool { shared }
+  }
+
+  /// Queue `task`, to settle the promise of `deferred` with its result.
+  /// The pool must not be shut down: a deferred that is never settled keeps
+  /// the event loop alive.
+  pub(crate) fn submit(
+    &self,
+    task: SearchTask,
+    deferred: JsDeferred<SearchOutput, Resolver>,
+    priority: i32,
+  ) {
+    let mut state = self.shared.state.lock().unwrap();
+    debug_assert!(!state.shut_down);
+    let sequence = state.submitted;
+    state.submitted += 1;
+    state.jobs.push(Job {
+      priority,
+      sequence,
+      queued_at: Instant::now(),
+      task,
+      deferred,
+    });
+    self.shared.changed.notify_one();
+  }
+
+  /// Whether the pool no longer takes jobs.
+  pub(crate) fn is_shut_down(&self) -> bool {
+    self.shared.state.lock().unwrap().shut_down
+  }
+
+  /// Take the job under `handle` off the queue and reject it as cancelled.
+  /// Returns whether it was still queued.
+  pub(crate) fn cancel(&self, handle: u32) -> bool {
+    let mut state = self.shared.state.lock().unwrap();
+    let found = state.jobs.iter().position(|job| job.task.handle == handle);
+    let job = found.map(|i| state.jobs.remove(i));
+    drop(state);
+    job.map(Job::cancel).is_some()
+  }
+
+  /// The number of jobs waiting for a thread.
+  pub(crate) fn queued(&self) -> usize {
+    self.shared.state.lock().unwrap().jobs.len()
+  }
+
+  /// The number of jobs being searched.
+  pub(crate) fn running(&self) -> usize {
+    self.shared.state.lock().unwrap().running
+  }
+
+  /// How long the jobs started so far waited, by priority.
+  pub(crate) fn waits(&self) -> Vec<(i32, Waits)> {
+    let state = self.shared.state.lock().unwrap();
+    state.waits.iter().map(|(&p, &w)| (p, w)).collect()
+  }
+
+  /// Stop taking jobs, rejecting the queued ones as cancelled. Running jobs
+  /// finish, and then the threads exit.
+  pub(crate) fn close(&self) {
+    let mut state = self.shared.state.lock().unwrap();
+    state.shut_down = true;
+    self.shared.changed.notify_all();
+    let jobs = std::mem::take(&mut state.jobs);
+    drop(state);
+    jobs.into_iter().for_each(Job::cancel);
+  }
+
+  /// Stop taking jobs once the queued ones have run.
+  pub(crate) fn drain(&self) {
+    self.shared.state.lock().unwrap().shut_down = true;
+    self.shared.changed.notify_all();
+  }
+}
+
+/// Run jobs until the pool shuts down and its queue is empty.
+fn work(shared: &Shared) {
+  loop {
+    let mut state = shared.state.lock().unwrap();
+    let mut job = loop {
+      if let Some(job) = state.take(shared.aging) {
+        break job;
+      }
+      if state.shut_down {
+        return;
+      }
+      state = shared.changed.wait(state).unwrap();
+    };
+    state.running += 1;
+    drop(state);
+    let outcome = job.task.compute();
+    job.settle(outcome);
+    shared.state.lock().unwrap().running -= 1;
+  }
+}
@@ -0,0 +1,35 @@
+//! Matchers shared between worker threads.
+//!
+//! Every worker thread has its own JavaScript objects, but all of them run
+//! in one process with one copy of this library. A shared matcher is kept
+//! in a process-wide table under a numeric handle, which can be posted to a
+//! worker and turned back into a matcher there without compiling it again.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::engine::Engine;
+
+fn table() -> &'static Mutex<HashMap<u32, Arc<Engine>>> {
+  static TABLE: OnceLock<Mutex<HashMap<u32, Arc<Engine>>>> = OnceLock::new();
+  TABLE.get_or_init(Default::default)
+}
+
+/// Keep `engine` under a new handle until it is released.
+pub(crate) fn share(engine: Arc<Engine>) -> u32 {
+  static NEXT: AtomicU32 = AtomicU32::new(1);
+  let handle = NEXT.fetch_add(1, Ordering::Relaxed);
+  table().lock().unwrap().insert(handle, engine);
+  handle
+}
+
+/// The engine shared under `handle`, if it has not been released.
+pub(crate) fn get(handle: u32) -> Option<Arc<Engine>> {
+  table().lock().unwrap().get(&handle).cloned()
+}
+
+/// Stop sharing the engine under `handle`. Returns whether it was shared.
+pub(crate) fn release(handle: u32) -> bool {
+  table().lock().unwrap().remove(&handle).is_some()
+}
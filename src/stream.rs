@@ -0,0 +1,411 @@
+//! Streaming search of pipes and stdin on a background thread.
+//!
+//! The input is read through the searcher's line buffer, so memory use is
+//! bounded by the searcher's heap limit and the queue (see `iter`) rather
+//! than by the size of the input. Every match and context line is queued, or
+//! passed to a callback, as soon as the searcher reports it.
+
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Condvar, Mutex};
+
+use grep::searcher::{Searcher as GrepSearcher, Sink, SinkContext, SinkFinish, SinkMatch};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+  ThreadsafeFunction, ThreadsafeFunctionCallMode, UnknownReturnValue,
+};
+
+use crate::error::Failure;
+use crate::iter::{self, Item, Queue};
+use crate::metrics;
+use crate::{
+  lazy, BinaryDetectionMode, BinaryEvent, CollectSink, ErrorCode, MeteredReader, ReadStats,
+  SearchContext, SearchFinish, SearchMatch, Searcher,
+};
+
+/// A callback that receives lines. Its own queue is unbounded; the search
+/// bounds it with `Pending`.
+pub type LineCallback =
+  ThreadsafeFunction<StreamLine, UnknownReturnValue, StreamLine, Status, false, false>;
+
+/// A callback that receives binary data found by a search.
+pub type BinaryCallback =
+  ThreadsafeFunction<BinaryEvent, UnknownReturnValue, BinaryEvent, Status, false, false>;
+
+/// A callback that receives the outcome of a search.
+pub type FinishCallback =
+  ThreadsafeFunction<SearchFinish, UnknownReturnValue, SearchFinish, ErrorCode, true>;
+
+/// A match or context line of a streaming search.
+pub struct StreamLine {
+  line: Either<SearchMatch, SearchContext>,
+  plain: bool,
+}
+
+impl ToNapiValue for StreamLine {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+    lazy::search_line(env, val.line, val.plain)
+  }
+}
+
+/// A line, binary data or the summary of a streaming search, tagged with
+/// its `type`.
+pub enum StreamRecord {
+  Line(StreamLine),
+  Binary(BinaryEvent),
+  Finish(SearchFinish),
+}
+
+impl ToNapiValue for StreamRecord {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+    let (kind, value) = match val {
+      StreamRecord::Line(line) => {
+        let kind = match line.line {
+          Either::A(_) => "match",
+          Either::B(_) => "context",
+        };
+        (kind, StreamLine::to_napi_value(env, line)?)
+      }
+      StreamRecord::Binary(event) => ("binary", BinaryEvent::to_napi_value(env, event)?),
+      StreamRecord::Finish(finish) => ("finish", SearchFinish::to_napi_value(env, finish)?),
+    };
+    Object::from_raw(env, value).set_named_property("type", kind)?;
+    Ok(value)
+  }
+}
+
+/// A line or binary data found by a streaming search.
+pub(crate) enum Found {
+  Line(Either<SearchMatch, SearchContext>),
+  Binary(BinaryEvent),
+}
+
+/// The input of a streaming search.
+pub(crate) enum Source {
+  /// A file, or stdin for `-`.
+  Path(String),
+  /// The input itself.
+  Bytes(Buffer),
+}
+
+/// Start searching `source`, buffering at most `queue_size` lines. Context
+/// lines are left out unless `with_context` is set.
+pub(crate) fn spawn(
+  searcher: Searcher,
+  sink: CollectSink,
+  source: Source,
+  queue_size: usize,
+  with_context: bool,
+) -> Queue<StreamLine> {
+  let (sender, queue) = iter::channel(queue_size);
+  std::thread::spawn(move || {
+    let plain = searcher.config.plain;
+    let deliver = |found| match found {
+      Found::Line(Either::B(_)) if !with_context => true,
+      Found::Line(line) => sender.send(Ok(StreamLine { line, plain })).is_ok(),
+      Found::Binary(_) => true,
+    };
+    if let Err(e) = search(searcher, sink, source, deliver) {
+      // A failed send means the consumer is gone.
+      let _ = sender.send(Err(e));
+    }
+  });
+  queue
+}
+
+/// Start searching `path`, or stdin for `-`, buffering at most `queue_size`
+/// records. The last record is the summary, unless the search fails.
+pub(crate) fn spawn_records(
+  searcher: Searcher,
+  sink: CollectSink,
+  path: String,
+  queue_size: usize,
+) -> Queue<StreamRecord> {
+  let (sender, queue) = iter::channel(queue_size);
+  std::thread::spawn(move || {
+    let plain = searcher.config.plain;
+    let deliver = |found| {
+      let record = match found {
+        Found::Line(line) => StreamRecord::Line(StreamLine { line, plain }),
+        Found::Binary(event) => StreamRecord::Binary(event),
+      };
+      sender.send(Ok(record)).is_ok()
+    };
+    let result = search(searcher, sink, Source::Path(path), deliver);
+    // A failed send means the consumer is gone.
+    let _ = sender.send(result.map(StreamRecord::Finish));
+  });
+  queue
+}
+
+/// The number of callback calls waiting for the JavaScript thread.
+#[derive(Default)]
+pub(crate) struct Pending {
+  count: Mutex<usize>,
+  changed: Condvar,
+}
+
+impl Pending {
+  /// Wait until at most `limit` calls are waiting.
+  pub(crate) fn wait(&self, limit: usize) {
+    let count = self.count.lock().unwrap();
+    drop(
+      self
+        .changed
+        .wait_while(count, |count| *count > limit)
+        .unwrap(),
+    );
+  }
+}
+
+/// One waiting call, counted until it has run or been dropped with its
+/// callback.
+pub(crate) struct Slot(Arc<Pending>);
+
+impl Slot {
+  pub(crate) fn new(pending: &Arc<Pending>) -> Self {
+    *pending.count.lock().unwrap() += 1;
+    Slot(pending.clone())
+  }
+}
+
+impl Drop for Slot {
+  fn drop(&mut self) {
+    *self.0.count.lock().unwrap() -= 1;
+    self.0.changed.notify_all();
+  }
+}
+
+/// The callbacks of a streaming search.
+pub(crate) struct Callbacks {
+  pub on_match: LineCallback,
+  pub on_context: Option<LineCallback>,
+  pub on_binary: Option<BinaryCallback>,
+  pub on_finish: Option<FinishCallback>,
+}
+
+/// Start searching `path`, or stdin for `-`, passing each line to `on_match`
+/// or `on_context`, binary data to `on_binary` and the outcome to
+/// `on_finish`. The search waits while `queue_size` calls are waiting for
+/// the JavaScript thread.
+pub(crate) fn spawn_callbacks(
+  searcher: Searcher,
+  sink: CollectSink,
+  path: String,
+  queue_size: usize,
+  callbacks: Callbacks,
+) {
+  std::thread::spawn(move || {
+    let Callbacks {
+      on_match,
+      on_context,
+      on_binary,
+      on_finish,
+    } = callbacks;
+    let plain = searcher.config.plain;
+    let pending = Arc::new(Pending::default());
+    // Each callback has its own queue, so before switching callbacks, wait
+    // for the calls of the last one to keep lines in order.
+    let mut last = None;
+    let deliver = |found: Found| {
+      // Which callback is called: 0 for matches, 1 for context lines and 2
+      // for binary data.
+      let kind = match &found {
+        Found::Line(Either::A(_)) => 0,
+        Found::Line(Either::B(_)) if on_context.is_some() => 1,
+        Found::Binary(_) if on_binary.is_some() => 2,
+        _ => return true,
+      };
+      let limit = match last.replace(kind) {
+        Some(last) if last != kind => 0,
+        _ => queue_size.max(1) - 1,
+      };
+      pending.wait(limit);
+      let slot = Slot::new(&pending);
+      let done = move |_, _| {
+        drop(slot);
+        Ok(())
+      };
+      let mode = ThreadsafeFunctionCallMode::Blocking;
+      let status = match (found, &on_context, &on_binary) {
+        (Found::Line(line @ Either::A(_)), _, _) => {
+          on_match.call_with_return_value(StreamLine { line, plain }, mode, done)
+        }
+        (Found::Line(line), Some(on_context), _) => {
+          on_context.call_with_return_value(StreamLine { line, plain }, mode, done)
+        }
+        (Found::Binary(event), _, Some(on_binary)) => {
+          on_binary.call_with_return_value(event, mode, done)
+        }
+        _ => Status::Ok,
+      };
+      // Anything but Ok means the environment is shutting down.
+      status == Status::Ok
+    };
+    let result = search(searcher, sink, Source::Path(path), deliver);
+    pending.wait(0);
+    if let Some(on_finish) = on_finish {
+      let result = result.map_err(Failure::into_callback_error);
+      on_finish.call(result, ThreadsafeFunctionCallMode::Blocking);
+    }
+  });
+}
+
+/// Search `source`, delivering lines and binary data as they are found
+/// until `deliver` returns false, and record the search in the metrics.
+fn search<F>(
+  searcher: Searcher,
+  sink: CollectSink,
+  source: Source,
+  mut deliver: F,
+) -> Item<SearchFinish>
+where
+  F: FnMut(Found) -> bool,
+{
+  let _running = metrics::start();
+  let mut matches = 0;
+  let counting = |found: Found| {
+    if let Found::Line(Either::A(_)) = found {
+      matches += 1;
+    }
+    deliver(found)
+  };
+  let path = match &source {
+    Source::Path(path) if path != "-" => Some(path.clone()),
+    _ => None,
+  };
+  let finish = search_source(searcher, sink, source, counting);
+  match &finish {
+    Ok(finish) => metrics::completed(finish.byte_count, matches),
+    Err(e) => metrics::failed(e),
+  }
+  finish.map_err(|e| Failure::classify(e, path.as_deref()))
+}
+
+/// Search `source`, delivering lines and binary data as they are found
+/// until `deliver` returns false.
+fn search_source<F>(
+  searcher: Searcher,
+  sink: CollectSink,
+  source: Source,
+  deliver: F,
+) -> Result<SearchFinish>
+where
+  F: FnMut(Found) -> bool,
+{
+  let Searcher {
+    mut inner, config, ..
+  } = searcher;
+  // Reading in blocks through a metered reader stops at cancellation and
+  // at the deadline.
+  let stats = ReadStats {
+    cancel: config.cancel.clone(),
+    deadline: sink.deadline,
+    terminator: inner.line_terminator().as_byte(),
+    ..Default::default()
+  };
+  let (reader, path): (std::io::Result<Box<dyn Read>>, _) = match source {
+    Source::Path(path) if path == "-" => (Ok(Box::new(std::io::stdin())), Some(path)),
+    Source::Path(path) => (
+      config
+        .retry(|| std::fs::File::open(&path))
+        .map(|file| Box::new(file) as Box<dyn Read>),
+      Some(path),
+    ),
+    Source::Bytes(bytes) => (Ok(Box::new(Cursor::new(bytes))), None),
+  };
+  let mut sink = StreamSink {
+    inner: sink,
+    deliver,
+    found_binary: false,
+  };
+  let matcher = sink.inner.matcher.clone();
+  reader
+    .and_then(|reader| MeteredReader::new(&stats, || Ok(reader)))
+    .and_then(|reader| inner.search_reader(&*matcher, reader, &mut sink))
+    .map_err(|e| match &path {
+      Some(path) => Error::new(Status::GenericFailure, format!("{}: {}", path, e)),
+      None => Error::new(Status::GenericFailure, e.to_string()),
+    })?;
+  let timed_out = sink.inner.timed_out_at.is_some() || stats.timed_out.get();
+  let mut finish = sink.inner.into_result().finish;
+  if config.timeout.is_some() {
+    finish.timed_out = Some(timed_out);
+  }
+  Ok(finish)
+}
+
+/// Delivers each line collected by the inner sink as soon as it is found,
+/// and the first binary data.
+struct StreamSink<F> {
+  inner: CollectSink,
+  deliver: F,
+  found_binary: bool,
+}
+
+impl<F: FnMut(Found) -> bool> StreamSink<F> {
+  /// Deliver the collected lines. Returns false once the consumer is gone.
+  fn flush(&mut self) -> bool {
+    let context = self.inner.context.drain(..).map(Either::B);
+    let matches = self.inner.matches.drain(..).map(Either::A);
+    context
+      .chain(matches)
+      .map(Found::Line)
+      .all(&mut self.deliver)
+  }
+}
+
+impl<F: FnMut(Found) -> bool> Sink for StreamSink<F> {
+  type Error = std::io::Error;
+
+  fn matched(
+    &mut self,
+    searcher: &GrepSearcher,
+    mat: &SinkMatch<'_>,
+  ) -> std::result::Result<bool, Self::Error> {
+    let more = self.inner.matched(searcher, mat)?;
+    Ok(self.flush() && more)
+  }
+
+  fn context(
+    &mut self,
+    searcher: &GrepSearcher,
+    ctx: &SinkContext<'_>,
+  ) -> std::result::Result<bool, Self::Error> {
+    let more = self.inner.context(searcher, ctx)?;
+    Ok(self.flush() && more)
+  }
+
+  fn context_break(&mut self, searcher: &GrepSearcher) -> std::result::Result<bool, Self::Error> {
+    self.inner.context_break(searcher)
+  }
+
+  fn binary_data(
+    &mut self,
+    searcher: &GrepSearcher,
+    binary_byte_offset: u64,
+  ) -> std::result::Result<bool, Self::Error> {
+    let more = self.inner.binary_data(searcher, binary_byte_offset)?;
+    // The searcher may report binary data more than once.
+    if self.found_binary {
+      return Ok(more);
+    }
+    self.found_binary = true;
+    let action = match searcher.binary_detection().quit_byte() {
+      Some(_) => BinaryDetectionMode::Quit,
+      None => BinaryDetectionMode::Convert,
+    };
+    let event = BinaryEvent {
+      offset: binary_byte_offset as i64,
+      action,
+    };
+    Ok(self.flush() && (self.deliver)(Found::Binary(event)) && more)
+  }
+
+  fn finish(
+    &mut self,
+    searcher: &GrepSearcher,
+    finish: &SinkFinish,
+  ) -> std::result::Result<(), Self::Error> {
+    self.inner.finish(searcher, finish)
+  }
+}
@@ -0,0 +1,92 @@
+//! Transcoding of inputs that start with a byte order mark.
+//!
+//! grep transcodes such inputs to UTF-8 itself, replacing undecodable
+//! sequences without saying so. When an encoding error policy is set, the
+//! input is decoded here instead, so every undecodable sequence is known
+//! and can be counted, reported or refused.
+
+use encoding_rs::{DecoderResult, Encoding};
+
+/// An input decoded to UTF-8.
+pub(crate) struct Decoded {
+  /// The decoded text, with U+FFFD in place of undecodable sequences.
+  pub text: Vec<u8>,
+  /// Offsets in `text` of every replacement character that stands for an
+  /// undecodable sequence.
+  pub errors: Vec<usize>,
+  /// Offset in the input of the first undecodable sequence.
+  pub first_error: Option<usize>,
+  /// The encoding named by the byte order mark.
+  pub encoding: &'static Encoding,
+}
+
+/// Whether `bytes` starts with a byte order mark, so that it is transcoded.
+pub(crate) fn has_bom(bytes: &[u8]) -> bool {
+  Encoding::for_bom(bytes).is_some()
+}
+
+/// Decode `bytes` if it starts with a byte order mark.
+pub(crate) fn decode(bytes: &[u8]) -> Option<Decoded> {
+  let (encoding, bom_len) = Encoding::for_bom(bytes)?;
+  let input = &bytes[bom_len..];
+  let mut decoder = encoding.new_decoder_without_bom_handling();
+  let mut text = String::with_capacity(input.len());
+  let mut errors = Vec::new();
+  let mut first_error = None;
+  let mut read = 0;
+  loop {
+    let (result, n) = decoder.decode_to_string_without_replacement(&input[read..], &mut text, true);
+    read += n;
+    match result {
+      DecoderResult::InputEmpty => break,
+      DecoderResult::OutputFull => {
+        let remaining = input.len() - read;
+        text.reserve(
+          decoder
+            .max_utf8_buffer_length_without_replacement(remaining)
+            .unwrap_or(remaining * 3),
+        );
+      }
+      DecoderResult::Malformed(bad, unread) => {
+        first_error.get_or_insert(bom_len + read - unread as usize - bad as usize);
+        errors.push(text.len());
+        text.push('\u{FFFD}');
+      }
+    }
+  }
+  Some(Decoded {
+    text: text.into_bytes(),
+    errors,
+    first_error,
+    encoding,
+  })
+}
+
+impl Decoded {
+  /// The lines of `text` with undecodable sequences, as byte ranges ending
+  /// after the line terminator, with their line numbers.
+  pub(crate) fn error_lines(&self) -> Vec<(std::ops::Range<usize>, u32)> {
+    let mut lines: Vec<(std::ops::Range<usize>, u32)> = Vec::new();
+    let mut counted = (0, 1);
+    for &at in &self.errors {
+      if lines.last().is_some_and(|(range, _)| range.contains(&at)) {
+        continue;
+      }
+      let start = self.text[..at]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+      let end = self.text[at..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(self.text.len(), |i| at + i + 1);
+      counted.1 += self.text[counted.0..start]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count() as u32;
+      counted.0 = start;
+      lines.push((start..end, counted.1));
+    }
+    lines
+  }
+}
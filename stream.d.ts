@@ -0,0 +1,84 @@
+/* grep-js/stream types */
+
+import type { Readable, Transform } from 'stream'
+
+import type { BinaryMatch, RegexMatcher } from './matcher'
+import type { BinaryEvent, SearchContext, SearchFinish, SearchMatch, Searcher } from './searcher'
+
+/** A record of a streaming search. The last one is the summary. */
+export type SearchRecord =
+  | ({ type: 'match' } & SearchMatch)
+  | ({ type: 'context' } & SearchContext)
+  | ({ type: 'binary' } & BinaryEvent)
+  | ({ type: 'finish' } & SearchFinish)
+
+/** Options for `createSearchStream`. */
+export interface SearchStreamOptions {
+  /** The searcher to search with. Defaults to `new Searcher()`. */
+  searcher?: Searcher
+  /** How many records to search ahead of the stream. Defaults to 64. */
+  queueSize?: number
+  /** How many records the stream buffers before it stops reading. */
+  highWaterMark?: number
+}
+
+/**
+ * Search a file, or stdin for `-`, as an object-mode Readable of
+ * `{ type: 'match' | 'context' | 'binary' | 'finish', ... }` records.
+ *
+ * Records are only searched for as the stream is read, so a paused or slow
+ * consumer also pauses the search, and destroying the stream stops it. The
+ * `binary` record, produced when binary data is first found, is also
+ * emitted as a `'binary'` event. Matchers that normalize their input are not
+ * supported.
+ */
+export declare function createSearchStream(
+  matcher: RegexMatcher,
+  path: string,
+  options?: SearchStreamOptions | undefined | null,
+): Readable
+
+/** Options for `createMatchStream`. */
+export interface MatchStreamOptions {
+  /**
+   * The length in bytes of the longest match to report in full. Defaults
+   * to 64 KiB.
+   */
+  maxMatchLength?: number
+  /** How many matches the stream buffers before it stops transforming. */
+  highWaterMark?: number
+}
+
+/**
+ * Find matches in the bytes written to a Transform, which emits every match
+ * as a `{ start, end }` object with absolute byte offsets. Matches may span
+ * written chunks (see `StreamMatcher`). Matchers that normalize their input
+ * are not supported.
+ */
+export declare function createMatchStream(
+  matcher: RegexMatcher,
+  options?: MatchStreamOptions | undefined | null,
+): Transform
+
+/** Options for `createMatchTap`. */
+export interface MatchTapOptions {
+  /**
+   * The length in bytes of the longest match to report in full. Defaults
+   * to 64 KiB.
+   */
+  maxMatchLength?: number
+  /** Called with every match, before its `'match'` event. */
+  onMatch?: (match: BinaryMatch) => void
+}
+
+/**
+ * A Transform that passes the bytes written to it through unchanged and
+ * reports the matches in them on the side, as `'match'` events and to
+ * `onMatch`, with absolute byte offsets. Matches may span written chunks
+ * (see `StreamMatcher`). Matchers that normalize their input are not
+ * supported.
+ */
+export declare function createMatchTap(
+  matcher: RegexMatcher,
+  options?: MatchTapOptions | undefined | null,
+): Transform
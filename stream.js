@@ -0,0 +1,102 @@
+// grep-js/stream - Node.js stream adapters
+const { Readable, Transform } = require('stream')
+
+const binding = require('./index.js')
+
+/**
+ * Search a file, or stdin for `-`, as an object-mode Readable of
+ * `{ type: 'match' | 'context' | 'binary' | 'finish', ... }` records.
+ *
+ * Records are only searched for as the stream is read, so a paused or slow
+ * consumer also pauses the search, and destroying the stream stops it. The
+ * `binary` record is also emitted as a `'binary'` event.
+ */
+function createSearchStream(matcher, path, options) {
+  const { searcher = new binding.Searcher(), queueSize, highWaterMark } = options ?? {}
+  const records = searcher.searchRecords(matcher, path, { queueSize })
+  async function* emitting() {
+    for await (const record of records) {
+      if (record.type === 'binary') {
+        stream.emit('binary', record)
+      }
+      yield record
+    }
+  }
+  const stream = Readable.from(emitting(), { objectMode: true, highWaterMark })
+  return stream
+}
+
+/**
+ * Find matches in the bytes written to a Transform, which emits every match
+ * as a `{ start, end }` object with absolute byte offsets. Matches may span
+ * written chunks (see `StreamMatcher`).
+ */
+function createMatchStream(matcher, options) {
+  const { maxMatchLength, highWaterMark } = options ?? {}
+  const stream = new binding.StreamMatcher(matcher, { maxMatchLength })
+  const emit = (transform, matches) => {
+    for (const match of matches) {
+      transform.push(match)
+    }
+  }
+  return new Transform({
+    readableObjectMode: true,
+    readableHighWaterMark: highWaterMark,
+    transform(chunk, encoding, callback) {
+      try {
+        emit(this, stream.push(typeof chunk === 'string' ? Buffer.from(chunk, encoding) : chunk))
+        callback()
+      } catch (e) {
+        callback(e)
+      }
+    },
+    flush(callback) {
+      try {
+        emit(this, stream.end())
+        callback()
+      } catch (e) {
+        callback(e)
+      }
+    },
+  })
+}
+
+/**
+ * A Transform that passes the bytes written to it through unchanged and
+ * reports the matches in them on the side, as `'match'` events and to
+ * `onMatch`, with absolute byte offsets. Matches may span written chunks
+ * (see `StreamMatcher`).
+ */
+function createMatchTap(matcher, options) {
+  const { maxMatchLength, onMatch } = options ?? {}
+  const stream = new binding.StreamMatcher(matcher, { maxMatchLength })
+  const report = (transform, matches) => {
+    for (const match of matches) {
+      onMatch?.(match)
+      transform.emit('match', match)
+    }
+  }
+  return new Transform({
+    transform(chunk, encoding, callback) {
+      try {
+        const bytes = typeof chunk === 'string' ? Buffer.from(chunk, encoding) : chunk
+        report(this, stream.push(bytes))
+        callback(null, bytes)
+      } catch (e) {
+        callback(e)
+      }
+    },
+    flush(callback) {
+      try {
+        report(this, stream.end())
+        callback()
+      } catch (e) {
+        callback(e)
+      }
+    },
+  })
+}
+
+module.exports.createSearchStream = createSearchStream
+module.exports.createMatchStream = createMatchStream
+module.exports.createMatchTap = createMatchTap
@@ -0,0 +1,4 @@
+// ESM wrapper for grep-js/stream
+import stream from './stream.js'
+
+export const { createSearchStream, createMatchStream, createMatchTap } = stream